@@ -39,5 +39,6 @@ fn main() -> Result<(), String> {
         }
     }
     println!("Scan Time: {:?}", result.scan_time);
+    println!("Stats: {}", result.stats);
     Ok(())
 }
@@ -0,0 +1,12 @@
+use e_libscanner::{exporter::Exporter, Opts};
+fn main() -> Result<(), String> {
+    // example: cargo run --example exporter --features exporter -- --model exporter --ips 192.168.1.0/24 --ports 22,80,443 --interval 300
+    // then scrape http://127.0.0.1:9539/metrics or http://127.0.0.1:9539/probe?target=192.168.1.1:22&module=tcp_connect
+    let opts = Opts::new(None::<Vec<String>>)?;
+    let exporter = match opts.init()?.downcast::<Exporter>() {
+        Ok(exporter) => exporter,
+        Err(_) => return Err(String::from("use --model exporter")),
+    };
+    println!("listening on http://{}", exporter.listen);
+    exporter.run(None)
+}
@@ -32,6 +32,12 @@ fn main() -> Result<(), String> {
         let result = handle.join().unwrap();
         // Print results
         println!("Status: {:?}", result.scan_status);
+        for warning in result.warnings.iter().chain(result.errors.iter()) {
+            println!("{}", warning);
+        }
+        for violation in result.scope_violations.iter() {
+            println!("{}, skipped", violation);
+        }
         println!("UP Hosts:");
         let len = result.ips.len();
         for host in result.ips {
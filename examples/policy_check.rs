@@ -0,0 +1,29 @@
+use e_libscanner::{output::to_json, policy::PolicyReport, Opts};
+fn main() {
+    // exit status 0 when the results are compliant, 1 when they are not and 2 on error
+    match run() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Print the report, returns true if the results are compliant
+fn run() -> Result<bool, String> {
+    // example: cargo run --example policy_check -- --model policy --policy policy.toml --results scan.json
+    let opts = Opts::new(None::<Vec<String>>)?;
+    let report = match opts.init()?.downcast::<PolicyReport>() {
+        Ok(report) => report,
+        Err(_) => return Err(String::from("use --model policy")),
+    };
+    // -v prints the report as JSON
+    if opts.verbose > 0 {
+        println!("{}", to_json(&report)?);
+    } else {
+        print!("{}", report);
+    }
+    Ok(report.is_compliant())
+}
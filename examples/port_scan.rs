@@ -28,15 +28,22 @@ fn main() -> Result<(), String> {
     .downcast::<sync_scan::Scanner>()
     .unwrap();
     let rx = scanner.get_progress_receiver();
+    let stats = scanner.get_stats_counters();
     // Run scan
     let handle = thread::spawn(move || scanner.scan(None));
     // Print progress
     while let Ok(socket_addr) = rx.lock().unwrap().recv() {
-        println!("Check: {}", socket_addr);
+        println!("Check: {} [{}]", socket_addr, stats.snapshot());
     }
     let result = handle.join().unwrap();
     // Print results
     println!("Status: {:?}", result.scan_status);
+    for warning in result.warnings.iter().chain(result.errors.iter()) {
+        println!("{}", warning);
+    }
+    for violation in result.scope_violations.iter() {
+        println!("{}, skipped", violation);
+    }
     for (ip, ports) in result.ip_with_port {
         println!("{}", ip);
         for port in ports {
@@ -44,5 +51,6 @@ fn main() -> Result<(), String> {
         }
     }
     println!("Scan Time: {:?}", result.scan_time);
+    println!("Stats: {}", result.stats);
     Ok(())
 }
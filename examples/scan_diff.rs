@@ -0,0 +1,30 @@
+use e_libscanner::{diff::ScanDiff, output::to_json, Opts};
+fn main() {
+    // exit status 0 when the scans are the same, 1 when they differ and 2 on error, like ndiff;
+    // failed or timed out scans are errors, the hosts they missed would show as down
+    match run() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Print the differences, returns true if the scans are the same
+fn run() -> Result<bool, String> {
+    // example: cargo run --example scan_diff -- --model diff --diff monday.json,tuesday.json
+    let opts = Opts::new(None::<Vec<String>>)?;
+    let diff = match opts.init()?.downcast::<ScanDiff>() {
+        Ok(diff) => diff,
+        Err(_) => return Err(String::from("use --model diff")),
+    };
+    // -v prints the changes as JSON
+    if opts.verbose > 0 {
+        println!("{}", to_json(&diff)?);
+    } else {
+        print!("{}", diff);
+    }
+    Ok(diff.is_empty())
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "tokio")]
+use e_libscanner::frame::result::ScanEvent;
+use e_libscanner::{tokio_scan, Opts};
+use tokio_stream::StreamExt;
+
+fn main() -> Result<(), String> {
+    // more command information use: -h
+    let scanner = Opts::new(Some(&[
+        "e-libscanner",
+        "--ips",
+        "192.168.80.1",
+        "--ports",
+        "8000",
+        "8080",
+        "80",
+        "20-26",
+        "--model",
+        "tokio",
+        "--scan",
+        "TcpConnect",
+        "--no-gui",
+    ]))?
+    .init()?
+    .downcast::<tokio_scan::Scanner>()
+    .unwrap();
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    rt.block_on(async {
+        let mut events = scanner.scan_stream(None)?;
+        // Print events as they arrive
+        while let Some(event) = events.next().await {
+            match event {
+                ScanEvent::Probe(socket_addr) => println!("Check: {}", socket_addr),
+                ScanEvent::Host(host) => println!("Host: {}", host),
+                ScanEvent::Port(ip, port) => println!("{} {:?}", ip, port),
+                ScanEvent::Error(e) => println!("Error: {}", e),
+                ScanEvent::Done(result) => {
+                    println!("Status: {:?}", result.scan_status);
+                    println!("Scan Time: {:?}", result.scan_time);
+                }
+            }
+        }
+        Ok(())
+    })
+}
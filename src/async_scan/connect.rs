@@ -0,0 +1,231 @@
+use super::pacer::Pacer;
+use super::reporter::Reporter;
+use crate::data::Protocol;
+use crate::frame::connect::{self, backoff, is_exhausted, ConnectOutcome, CONNECT_RETRIES};
+use crate::frame::{
+    result::{HostInfo, PortInfo, PortStatus, ScanCounters, ScanEvent, ScanResult, ScanResults},
+    udp, ScanSetting,
+};
+use async_io::{Async, Timer};
+use futures::future;
+use futures::stream::{self, StreamExt};
+use futures_lite::future::FutureExt;
+use std::io;
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Connect to the socket address on a non-blocking socket
+///
+/// Attempts that fail on local resources(file descriptors, ephemeral ports)
+/// are retried with back-off instead of being reported as port status.
+async fn try_connect(
+    socket_addr: SocketAddr,
+    conn_timeout: Duration,
+    stats: &ScanCounters,
+) -> io::Result<PortStatus> {
+    let mut retry = 0;
+    loop {
+        let stream = Async::<TcpStream>::connect(socket_addr)
+            .or(async {
+                Timer::after(conn_timeout).await;
+                Err(io::ErrorKind::TimedOut.into())
+            })
+            .await;
+        match ConnectOutcome::from_result(stream) {
+            ConnectOutcome::Status(status) => return Ok(status),
+            ConnectOutcome::Exhausted(e) => {
+                if retry >= CONNECT_RETRIES {
+                    return Err(e);
+                }
+                stats.retry();
+                Timer::after(backoff(retry)).await;
+                retry += 1;
+            }
+        }
+    }
+}
+
+/// Send an empty datagram on a connected UDP socket and wait for the answer
+///
+/// Only a failure to open the socket is returned as error.
+async fn try_udp(socket_addr: SocketAddr, wait_time: Duration) -> io::Result<PortStatus> {
+    let socket = Async::<UdpSocket>::bind(udp::bind_addr(&socket_addr))?;
+    let result = match socket.get_ref().connect(socket_addr) {
+        Ok(_) => socket.send(&[]).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        return if is_exhausted(&e) {
+            Err(e)
+        } else {
+            Ok(udp::status_of::<()>(&Err(e)))
+        };
+    }
+    let mut buf = [0u8; 1500];
+    let result = socket
+        .recv(&mut buf)
+        .or(async {
+            Timer::after(wait_time).await;
+            Err(io::ErrorKind::TimedOut.into())
+        })
+        .await;
+    Ok(udp::status_of(&result))
+}
+
+/// Probe one port and record its status
+async fn probe_port(
+    socket_addr: SocketAddr,
+    protocol: Protocol,
+    scan_setting: &ScanSetting,
+    pacer: &Pacer,
+    reporter: &Reporter,
+    scan_result: &Mutex<ScanResults>,
+) {
+    pacer.wait().await;
+    reporter.probe(socket_addr).await;
+    let stats = &scan_setting.stats;
+    let status = match protocol {
+        Protocol::Udp => try_udp(socket_addr, scan_setting.wait_time).await,
+        _ => try_connect(socket_addr, scan_setting.connect_timeout, stats).await,
+    };
+    // only running out of local sockets or ports fails a probe
+    stats.sent(&status);
+    let event = match status {
+        Ok(status) => ScanEvent::Port(
+            socket_addr.ip(),
+            PortInfo::new(socket_addr.port(), protocol, status),
+        ),
+        Err(e) => ScanEvent::Error(format!("{}: {}", socket_addr, e)),
+    };
+    if scan_result.lock().unwrap().apply(&event) {
+        reporter.event(event).await;
+    }
+}
+
+/// TCP connect scan, UDP ports are probed over connected UDP sockets
+///
+/// At most `hosts_concurrency` hosts are scanned at a time, with at most
+/// `ports_concurrency` connections in flight per host.
+pub(crate) async fn run_connect_scan(
+    scan_setting: ScanSetting,
+    reporter: &Reporter,
+    stop: &Arc<Mutex<bool>>,
+) -> ScanResult {
+    let start_time = Instant::now();
+    let pacer = Pacer::new(scan_setting.send_rate);
+    let scan_result: Mutex<ScanResults> = Mutex::new(ScanResults::new());
+    let running = || {
+        !*stop.lock().unwrap() && Instant::now().duration_since(start_time) < scan_setting.timeout
+    };
+    stream::iter(scan_setting.destinations.iter())
+        .take_while(|_| future::ready(running()))
+        .for_each_concurrent(scan_setting.hosts_concurrency, |dst| {
+            let targets: Vec<(SocketAddr, Protocol)> = dst
+                .dst_ports
+                .iter()
+                .map(|port| (SocketAddr::new(dst.dst_ip, *port), Protocol::Tcp))
+                .chain(
+                    udp::udp_targets(std::slice::from_ref(dst))
+                        .into_iter()
+                        .map(|socket_addr| (socket_addr, Protocol::Udp)),
+                )
+                .collect();
+            let (scan_setting, pacer, scan_result, running) =
+                (&scan_setting, &pacer, &scan_result, &running);
+            stream::iter(targets)
+                .take_while(move |_| future::ready(running()))
+                .for_each_concurrent(
+                    scan_setting.ports_concurrency,
+                    move |(socket_addr, protocol)| {
+                        probe_port(
+                            socket_addr,
+                            protocol,
+                            scan_setting,
+                            pacer,
+                            reporter,
+                            scan_result,
+                        )
+                    },
+                )
+        })
+        .await;
+    scan_result.into_inner().unwrap().result
+}
+
+/// UDP ports of the destinations probed over connected UDP sockets
+///
+/// Stands in for raw UDP probes when no raw UDP socket can be opened.
+pub(crate) async fn run_udp_probes(
+    scan_setting: &ScanSetting,
+    pacer: &Pacer,
+    reporter: &Reporter,
+    scan_result: &Mutex<ScanResults>,
+    stop: &Arc<Mutex<bool>>,
+) {
+    let start_time = Instant::now();
+    let running = || {
+        !*stop.lock().unwrap() && Instant::now().duration_since(start_time) < scan_setting.timeout
+    };
+    stream::iter(udp::udp_targets(&scan_setting.destinations))
+        .take_while(|_| future::ready(running()))
+        .for_each_concurrent(scan_setting.ports_concurrency, |socket_addr| {
+            probe_port(
+                socket_addr,
+                Protocol::Udp,
+                scan_setting,
+                pacer,
+                reporter,
+                scan_result,
+            )
+        })
+        .await;
+}
+
+/// TCP host discovery; any answer from a port, open or refused, means the host is up
+///
+/// Stands in for raw socket pings in unprivileged mode.
+pub(crate) async fn run_connect_ping(
+    scan_setting: ScanSetting,
+    reporter: &Reporter,
+    stop: &Arc<Mutex<bool>>,
+) -> ScanResult {
+    let start_time = Instant::now();
+    let pacer = Pacer::new(scan_setting.send_rate);
+    let scan_result: Mutex<ScanResults> = Mutex::new(ScanResults::new());
+    let running = || {
+        !*stop.lock().unwrap() && Instant::now().duration_since(start_time) < scan_setting.timeout
+    };
+    stream::iter(scan_setting.destinations.iter())
+        .take_while(|_| future::ready(running()))
+        .for_each_concurrent(scan_setting.hosts_concurrency, |dst| {
+            let (scan_setting, pacer, scan_result, running) =
+                (&scan_setting, &pacer, &scan_result, &running);
+            async move {
+                for port in connect::ping_ports(dst) {
+                    if !running() {
+                        break;
+                    }
+                    pacer.wait().await;
+                    let socket_addr = SocketAddr::new(dst.dst_ip, *port);
+                    reporter.probe(socket_addr).await;
+                    let stats = &scan_setting.stats;
+                    let status =
+                        try_connect(socket_addr, scan_setting.connect_timeout, stats).await;
+                    stats.sent(&status);
+                    if let Ok(PortStatus::Open) | Ok(PortStatus::Closed) = status {
+                        let event = ScanEvent::Host(HostInfo {
+                            ip_addr: dst.dst_ip,
+                            ttl: 0,
+                        });
+                        if scan_result.lock().unwrap().apply(&event) {
+                            reporter.event(event).await;
+                        }
+                        break;
+                    }
+                }
+            }
+        })
+        .await;
+    scan_result.into_inner().unwrap().result
+}
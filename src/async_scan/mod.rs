@@ -1,4 +1,7 @@
+mod connect;
+mod pacer;
 mod receiver;
+mod reporter;
 mod scanner;
 mod socket;
 
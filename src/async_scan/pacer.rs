@@ -0,0 +1,37 @@
+use async_io::Timer;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shared send pacer for concurrent tasks
+///
+/// Every call to `wait` reserves the next free sending slot and sleeps on an
+/// async timer until it is reached, so the executor thread is never blocked.
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl Pacer {
+    /// Create new Pacer with the interval between two sends(0 for unlimited)
+    pub(crate) fn new(interval: Duration) -> Pacer {
+        Pacer {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+    /// Wait for the next sending slot
+    pub(crate) async fn wait(&self) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = if *next > now { *next } else { now };
+            *next = slot + self.interval;
+            slot
+        };
+        Timer::at(slot).await;
+    }
+}
@@ -1,7 +1,8 @@
-use crate::data::DATA;
+use super::reporter::Reporter;
+use crate::data::Protocol;
 use crate::frame::{
-    result::{HostInfo, PortInfo, PortStatus, ScanResults},
-    ScanSetting, ScanType,
+    result::{HostInfo, PortInfo, PortStatus, ScanEvent, ScanResults},
+    udp, ScanSetting, ScanType,
 };
 use pnet_packet::Packet;
 use std::net::{IpAddr, SocketAddr};
@@ -13,20 +14,26 @@ pub(crate) async fn receive_packets(
     scan_setting: ScanSetting,
     scan_result: &Arc<Mutex<ScanResults>>,
     stop: &Arc<Mutex<bool>>,
+    reporter: &Reporter,
 ) {
     let start_time = Instant::now();
     loop {
         match rx.next() {
             Ok(frame) => {
+                scan_setting.stats.frame_received();
                 let frame = pnet_packet::ethernet::EthernetPacket::new(frame).unwrap();
-                match frame.get_ethertype() {
-                    pnet_packet::ethernet::EtherTypes::Ipv4 => {
-                        ipv4_handler(&frame, &scan_setting, &scan_result);
+                let event = match frame.get_ethertype() {
+                    pnet_packet::ethernet::EtherTypes::Ipv4 => ipv4_handler(&frame, &scan_setting),
+                    pnet_packet::ethernet::EtherTypes::Ipv6 => ipv6_handler(&frame, &scan_setting),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    // Only discoveries not seen before are reported.
+                    let fresh = scan_result.lock().unwrap().apply(&event);
+                    scan_setting.stats.frame_matched(fresh);
+                    if fresh {
+                        reporter.event(event).await;
                     }
-                    pnet_packet::ethernet::EtherTypes::Ipv6 => {
-                        ipv6_handler(&frame, &scan_setting, &scan_result);
-                    }
-                    _ => {}
                 }
             }
             Err(_) => {}
@@ -43,278 +50,174 @@ pub(crate) async fn receive_packets(
 fn ipv4_handler(
     ethernet: &pnet_packet::ethernet::EthernetPacket<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
+) -> Option<ScanEvent> {
     if let Some(packet) = pnet_packet::ipv4::Ipv4Packet::new(ethernet.payload()) {
         match packet.get_next_level_protocol() {
-            pnet_packet::ip::IpNextHeaderProtocols::Tcp => {
-                tcp_handler_v4(&packet, scan_setting, scan_result);
-            }
-            pnet_packet::ip::IpNextHeaderProtocols::Udp => {
-                udp_handler_v4(&packet, scan_setting, scan_result);
-            }
-            pnet_packet::ip::IpNextHeaderProtocols::Icmp => {
-                icmp_handler_v4(&packet, scan_setting, scan_result);
-            }
-            _ => {}
+            pnet_packet::ip::IpNextHeaderProtocols::Tcp => tcp_handler_v4(&packet, scan_setting),
+            pnet_packet::ip::IpNextHeaderProtocols::Udp => udp_handler_v4(&packet, scan_setting),
+            pnet_packet::ip::IpNextHeaderProtocols::Icmp => icmp_handler_v4(&packet, scan_setting),
+            _ => None,
         }
+    } else {
+        None
     }
 }
 
 fn ipv6_handler(
     ethernet: &pnet_packet::ethernet::EthernetPacket<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
+) -> Option<ScanEvent> {
     if let Some(packet) = pnet_packet::ipv6::Ipv6Packet::new(ethernet.payload()) {
         match packet.get_next_header() {
-            pnet_packet::ip::IpNextHeaderProtocols::Tcp => {
-                tcp_handler_v6(&packet, scan_setting, scan_result);
-            }
-            pnet_packet::ip::IpNextHeaderProtocols::Udp => {
-                udp_handler_v6(&packet, scan_setting, scan_result);
-            }
+            pnet_packet::ip::IpNextHeaderProtocols::Tcp => tcp_handler_v6(&packet, scan_setting),
+            pnet_packet::ip::IpNextHeaderProtocols::Udp => udp_handler_v6(&packet, scan_setting),
             pnet_packet::ip::IpNextHeaderProtocols::Icmpv6 => {
-                icmp_handler_v6(&packet, scan_setting, scan_result);
+                icmp_handler_v6(&packet, scan_setting)
             }
-            _ => {}
+            _ => None,
         }
+    } else {
+        None
     }
 }
 
 fn tcp_handler_v4(
     packet: &pnet_packet::ipv4::Ipv4Packet<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
-    let tcp_packet = pnet_packet::tcp::TcpPacket::new(packet.payload());
-    if let Some(tcp_packet) = tcp_packet {
-        let host_info: HostInfo = HostInfo {
-            ip_addr: IpAddr::V4(packet.get_source()),
-            ttl: packet.get_ttl(),
-        };
-        handle_tcp_packet(tcp_packet, host_info, &scan_setting, scan_result);
-    }
+) -> Option<ScanEvent> {
+    let tcp_packet = pnet_packet::tcp::TcpPacket::new(packet.payload())?;
+    let host_info: HostInfo = HostInfo {
+        ip_addr: IpAddr::V4(packet.get_source()),
+        ttl: packet.get_ttl(),
+    };
+    handle_tcp_packet(tcp_packet, host_info, scan_setting)
 }
 
 fn tcp_handler_v6(
     packet: &pnet_packet::ipv6::Ipv6Packet<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
-    let tcp_packet = pnet_packet::tcp::TcpPacket::new(packet.payload());
-    if let Some(tcp_packet) = tcp_packet {
-        let host_info: HostInfo = HostInfo {
-            ip_addr: IpAddr::V6(packet.get_source()),
-            ttl: packet.get_hop_limit(),
-        };
-        handle_tcp_packet(tcp_packet, host_info, &scan_setting, scan_result);
-    }
+) -> Option<ScanEvent> {
+    let tcp_packet = pnet_packet::tcp::TcpPacket::new(packet.payload())?;
+    let host_info: HostInfo = HostInfo {
+        ip_addr: IpAddr::V6(packet.get_source()),
+        ttl: packet.get_hop_limit(),
+    };
+    handle_tcp_packet(tcp_packet, host_info, scan_setting)
 }
 
 fn udp_handler_v4(
     packet: &pnet_packet::ipv4::Ipv4Packet<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
-    let udp = pnet_packet::udp::UdpPacket::new(packet.payload());
-    if let Some(udp) = udp {
-        handle_udp_packet(udp, &scan_setting, scan_result);
-    }
+) -> Option<ScanEvent> {
+    let udp = pnet_packet::udp::UdpPacket::new(packet.payload())?;
+    handle_udp_packet(udp, IpAddr::V4(packet.get_source()), scan_setting)
 }
 
 fn udp_handler_v6(
     packet: &pnet_packet::ipv6::Ipv6Packet<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
-    let udp = pnet_packet::udp::UdpPacket::new(packet.payload());
-    if let Some(udp) = udp {
-        handle_udp_packet(udp, &scan_setting, scan_result);
-    }
+) -> Option<ScanEvent> {
+    let udp = pnet_packet::udp::UdpPacket::new(packet.payload())?;
+    handle_udp_packet(udp, IpAddr::V6(packet.get_source()), scan_setting)
 }
 
 fn icmp_handler_v4(
     packet: &pnet_packet::ipv4::Ipv4Packet<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
-    let icmp_packet = pnet_packet::icmp::IcmpPacket::new(packet.payload());
-    if let Some(_icmp) = icmp_packet {
-        if scan_setting
-            .ip_set
-            .contains(&IpAddr::V4(packet.get_source()))
-            && !scan_result
-                .lock()
-                .unwrap()
-                .ip_set
-                .contains(&IpAddr::V4(packet.get_source()))
-        {
-            scan_result.lock().unwrap().result.ips.push(HostInfo {
-                ip_addr: IpAddr::V4(packet.get_source()),
-                ttl: packet.get_ttl(),
-            });
-            scan_result
-                .lock()
-                .unwrap()
-                .ip_set
-                .insert(IpAddr::V4(packet.get_source()));
+) -> Option<ScanEvent> {
+    pnet_packet::icmp::IcmpPacket::new(packet.payload())?;
+    // ICMP errors about UDP probes may come from any router on the path
+    if let ScanType::TcpSynScan = scan_setting.scan_type {
+        if let Some(error) = udp::parse_icmpv4_error(packet.payload()) {
+            return handle_udp_error(error, scan_setting);
         }
     }
+    let ip_addr = IpAddr::V4(packet.get_source());
+    if !scan_setting.ip_set.contains(&ip_addr) {
+        return None;
+    }
+    Some(ScanEvent::Host(HostInfo {
+        ip_addr,
+        ttl: packet.get_ttl(),
+    }))
 }
 
 fn icmp_handler_v6(
     packet: &pnet_packet::ipv6::Ipv6Packet<'_>,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
-    let icmp_packet = pnet_packet::icmp::IcmpPacket::new(packet.payload());
-    if let Some(_icmp) = icmp_packet {
-        if scan_setting
-            .ip_set
-            .contains(&IpAddr::V6(packet.get_source()))
-            && !scan_result
-                .lock()
-                .unwrap()
-                .ip_set
-                .contains(&IpAddr::V6(packet.get_source()))
-        {
-            scan_result.lock().unwrap().result.ips.push(HostInfo {
-                ip_addr: IpAddr::V6(packet.get_source()),
-                ttl: packet.get_hop_limit(),
-            });
-            scan_result
-                .lock()
-                .unwrap()
-                .ip_set
-                .insert(IpAddr::V6(packet.get_source()));
+) -> Option<ScanEvent> {
+    pnet_packet::icmp::IcmpPacket::new(packet.payload())?;
+    // ICMP errors about UDP probes may come from any router on the path
+    if let ScanType::TcpSynScan = scan_setting.scan_type {
+        if let Some(error) = udp::parse_icmpv6_error(packet.payload()) {
+            return handle_udp_error(error, scan_setting);
         }
     }
+    let ip_addr = IpAddr::V6(packet.get_source());
+    if !scan_setting.ip_set.contains(&ip_addr) {
+        return None;
+    }
+    Some(ScanEvent::Host(HostInfo {
+        ip_addr,
+        ttl: packet.get_hop_limit(),
+    }))
 }
 
 fn handle_tcp_packet(
     tcp_packet: pnet_packet::tcp::TcpPacket<'_>,
     host_info: HostInfo,
     scan_setting: &ScanSetting,
-    scan_result: &Arc<Mutex<ScanResults>>,
-) {
+) -> Option<ScanEvent> {
     let socket_addr: SocketAddr = SocketAddr::new(host_info.ip_addr, tcp_packet.get_source());
-    if tcp_packet.get_flags() == pnet_packet::tcp::TcpFlags::SYN | pnet_packet::tcp::TcpFlags::ACK {
-        match scan_setting.scan_type {
-            ScanType::TcpSynScan => {
-                if !scan_result
-                    .lock()
-                    .unwrap()
-                    .socket_set
-                    .contains(&socket_addr)
-                {
-                    let port_info = PortInfo {
-                        port: socket_addr.port(),
-                        status: PortStatus::Open,
-                        describe: DATA
-                            .portmap
-                            .get(&socket_addr.port())
-                            .unwrap_or(&"unknown type")
-                            .to_string(),
-                    };
-                    // Avoid deadlock.
-                    let exists: bool = if let Some(r) = scan_result
-                        .lock()
-                        .unwrap()
-                        .result
-                        .ip_with_port
-                        .get_mut(&socket_addr.ip())
-                    {
-                        r.push(port_info.clone());
-                        true
-                    } else {
-                        false
-                    };
-                    if !exists {
-                        scan_result
-                            .lock()
-                            .unwrap()
-                            .result
-                            .ip_with_port
-                            .insert(socket_addr.ip(), vec![port_info]);
-                    }
-                    scan_result.lock().unwrap().socket_set.insert(socket_addr);
-                }
-            }
-            _ => {
-                scan_result.lock().unwrap().result.ips.push(host_info);
-                match host_info.ip_addr {
-                    IpAddr::V4(ip) => {
-                        scan_result.lock().unwrap().ip_set.insert(IpAddr::V4(ip));
-                    }
-                    IpAddr::V6(ip) => {
-                        scan_result.lock().unwrap().ip_set.insert(IpAddr::V6(ip));
-                    }
-                }
-            }
-        }
+    let status = if tcp_packet.get_flags()
+        == pnet_packet::tcp::TcpFlags::SYN | pnet_packet::tcp::TcpFlags::ACK
+    {
+        PortStatus::Open
     } else if tcp_packet.get_flags()
         == pnet_packet::tcp::TcpFlags::RST | pnet_packet::tcp::TcpFlags::ACK
     {
-        match scan_setting.scan_type {
-            ScanType::TcpSynScan => {
-                if !scan_result
-                    .lock()
-                    .unwrap()
-                    .socket_set
-                    .contains(&socket_addr)
-                {
-                    let port_info = PortInfo {
-                        port: socket_addr.port(),
-                        status: PortStatus::Closed,
-                        describe: DATA
-                            .portmap
-                            .get(&socket_addr.port())
-                            .unwrap_or(&"unknown type")
-                            .to_string(),
-                    };
-                    // Avoid deadlock.
-                    let exists: bool = if let Some(r) = scan_result
-                        .lock()
-                        .unwrap()
-                        .result
-                        .ip_with_port
-                        .get_mut(&socket_addr.ip())
-                    {
-                        r.push(port_info.clone());
-                        true
-                    } else {
-                        false
-                    };
-                    if !exists {
-                        scan_result
-                            .lock()
-                            .unwrap()
-                            .result
-                            .ip_with_port
-                            .insert(socket_addr.ip(), vec![port_info]);
-                    }
-                    scan_result.lock().unwrap().socket_set.insert(socket_addr);
-                }
-            }
-            _ => {
-                scan_result.lock().unwrap().result.ips.push(host_info);
-                match host_info.ip_addr {
-                    IpAddr::V4(ip) => {
-                        scan_result.lock().unwrap().ip_set.insert(IpAddr::V4(ip));
-                    }
-                    IpAddr::V6(ip) => {
-                        scan_result.lock().unwrap().ip_set.insert(IpAddr::V6(ip));
-                    }
-                }
-            }
-        }
+        PortStatus::Closed
+    } else {
+        return None;
+    };
+    match scan_setting.scan_type {
+        ScanType::TcpSynScan => Some(ScanEvent::Port(
+            socket_addr.ip(),
+            PortInfo::new(socket_addr.port(), Protocol::Tcp, status),
+        )),
+        _ => Some(ScanEvent::Host(host_info)),
     }
 }
 
 fn handle_udp_packet(
-    _udp_packet: pnet_packet::udp::UdpPacket<'_>,
-    _scan_setting: &ScanSetting,
-    _scan_result: &Arc<Mutex<ScanResults>>,
-) {
+    udp_packet: pnet_packet::udp::UdpPacket<'_>,
+    ip_addr: IpAddr,
+    scan_setting: &ScanSetting,
+) -> Option<ScanEvent> {
+    // any answer to a UDP probe means the port is open
+    match scan_setting.scan_type {
+        ScanType::TcpSynScan
+            if udp_packet.get_destination() == scan_setting.src_port
+                && scan_setting.ip_set.contains(&ip_addr) =>
+        {
+            Some(ScanEvent::Port(
+                ip_addr,
+                PortInfo::new(udp_packet.get_source(), Protocol::Udp, PortStatus::Open),
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn handle_udp_error(
+    (socket_addr, src_port, status): (SocketAddr, u16, PortStatus),
+    scan_setting: &ScanSetting,
+) -> Option<ScanEvent> {
+    if src_port != scan_setting.src_port || !scan_setting.ip_set.contains(&socket_addr.ip()) {
+        return None;
+    }
+    Some(ScanEvent::Port(
+        socket_addr.ip(),
+        PortInfo::new(socket_addr.port(), Protocol::Udp, status),
+    ))
 }
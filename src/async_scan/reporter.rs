@@ -0,0 +1,58 @@
+use crate::frame::result::ScanEvent;
+use futures::channel::mpsc;
+use futures::SinkExt;
+use std::net::SocketAddr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Delivers progress and discoveries of a running scan
+///
+/// Progress goes to the legacy `std::sync::mpsc` channel when one is attached,
+/// every event goes to the bounded stream channel when one is attached.
+/// Sending on the stream channel waits for free capacity, which is how the
+/// stream consumer applies back-pressure to the scan.
+#[derive(Clone, Debug)]
+pub(crate) struct Reporter {
+    progress: Option<Arc<Mutex<Sender<SocketAddr>>>>,
+    events: Option<mpsc::Sender<ScanEvent>>,
+    stop: Option<Arc<Mutex<bool>>>,
+}
+
+impl Reporter {
+    /// Report progress through the legacy channel only
+    pub(crate) fn progress(ptx: &Arc<Mutex<Sender<SocketAddr>>>) -> Reporter {
+        Reporter {
+            progress: Some(Arc::clone(ptx)),
+            events: None,
+            stop: None,
+        }
+    }
+    /// Report every event through the stream channel
+    /// `stop` is raised once the consumer has gone away
+    pub(crate) fn stream(events: mpsc::Sender<ScanEvent>, stop: Arc<Mutex<bool>>) -> Reporter {
+        Reporter {
+            progress: None,
+            events: Some(events),
+            stop: Some(stop),
+        }
+    }
+    /// Probe has been sent to the socket address
+    pub(crate) async fn probe(&self, socket_addr: SocketAddr) {
+        if let Some(ptx) = &self.progress {
+            if let Ok(lr) = ptx.lock() {
+                let _ = lr.send(socket_addr);
+            }
+        }
+        self.event(ScanEvent::Probe(socket_addr)).await;
+    }
+    /// Send event to the stream consumer
+    pub(crate) async fn event(&self, event: ScanEvent) {
+        if let Some(events) = &self.events {
+            if events.clone().send(event).await.is_err() {
+                if let Some(stop) = &self.stop {
+                    *stop.lock().unwrap() = true;
+                }
+            }
+        }
+    }
+}
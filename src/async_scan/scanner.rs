@@ -1,12 +1,22 @@
+use super::reporter::Reporter;
 use super::scan_target;
-use crate::data::id::{DEFAULT_HOSTS_CONCURRENCY, DEFAULT_PORTS_CONCURRENCY, DEFAULT_SRC_PORT};
-use crate::frame::result::ScanResult;
+use crate::data::id::{
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_HOSTS_CONCURRENCY, DEFAULT_PORTS_CONCURRENCY, DEFAULT_SRC_PORT,
+    EVENT_CHANNEL_SIZE,
+};
+use crate::frame::result::{ScanCounters, ScanEvent, ScanResult, ScanStats};
 use crate::frame::{result::ScanStatus, Destination, ScanSetting, ScanType};
 use crate::interface;
+use crate::netns;
+use crate::exclude::Exclusions;
+use crate::scope::{Scope, ScopeViolation};
+use futures::channel::{mpsc, oneshot};
+use futures::Stream;
 use std::collections::HashSet;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 
 /// Async Host Scanner
@@ -77,8 +87,26 @@ pub struct Scanner {
     pub wait_time: Duration,
     /// Packet sending interval(0 for unlimited)
     pub send_rate: Duration,
+    /// Number of hosts scanned concurrently
+    pub hosts_concurrency: usize,
+    /// Number of ports scanned concurrently per host(connections in flight for connect scan)
+    pub ports_concurrency: usize,
+    /// Timeout of a single TCP connection attempt
+    pub connect_timeout: Duration,
+    /// Scan over TCP connections instead of raw sockets(Linux only)
+    pub unprivileged: bool,
+    /// Network namespace the scan runs in(Linux only)
+    pub netns: Option<String>,
+    /// Authorized scope of destinations
+    pub scope: Option<Scope>,
+    /// Out of scope targets dropped before reaching the scanner
+    pub scope_violations: Vec<ScopeViolation>,
+    /// Hosts and ports left out of the scan
+    pub exclusions: Exclusions,
     /// Scan Result
     pub scan_result: ScanResult,
+    /// Counters of the running scan
+    pub stats: Arc<ScanCounters>,
     /// Sender for progress messaging
     pub tx: Arc<Mutex<Sender<SocketAddr>>>,
     /// Receiver for progress messaging
@@ -120,12 +148,29 @@ impl Scanner {
             timeout: Duration::from_millis(300_000),
             wait_time: Duration::from_millis(200),
             send_rate: Duration::from_millis(0),
+            hosts_concurrency: DEFAULT_HOSTS_CONCURRENCY,
+            ports_concurrency: DEFAULT_PORTS_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            unprivileged: false,
+            netns: None,
+            scope: None,
+            scope_violations: vec![],
+            exclusions: Exclusions::new(),
             scan_result: ScanResult::new(),
+            stats: Arc::new(ScanCounters::new()),
             tx: Arc::new(Mutex::new(tx)),
             rx: Arc::new(Mutex::new(rx)),
         };
         Ok(scanner)
     }
+    /// Create new HostScanner inside the named network namespace(`/var/run/netns/<name>`)
+    ///
+    /// Interface and gateway lookup and the scan itself run inside the namespace. Linux only.
+    pub fn new_in_netns(src_ip: IpAddr, netns: &str) -> Result<Scanner, String> {
+        let mut scanner = netns::run_in(Some(netns), move || Scanner::new(src_ip))??;
+        scanner.netns = Some(netns.to_string());
+        Ok(scanner)
+    }
     /// get scan count
     pub fn len(&self) -> usize {
         let mut len = 0;
@@ -190,44 +235,223 @@ impl Scanner {
     pub fn get_send_rate(&self) -> Duration {
         self.send_rate.clone()
     }
+    /// Set number of hosts scanned concurrently
+    pub fn set_hosts_concurrency(&mut self, concurrency: usize) {
+        self.hosts_concurrency = concurrency.max(1);
+    }
+    /// Get number of hosts scanned concurrently
+    pub fn get_hosts_concurrency(&self) -> usize {
+        self.hosts_concurrency
+    }
+    /// Set number of ports scanned concurrently per host
+    pub fn set_ports_concurrency(&mut self, concurrency: usize) {
+        self.ports_concurrency = concurrency.max(1);
+    }
+    /// Get number of ports scanned concurrently per host
+    pub fn get_ports_concurrency(&self) -> usize {
+        self.ports_concurrency
+    }
+    /// Set timeout of a single TCP connection attempt
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+    /// Get timeout of a single TCP connection attempt
+    pub fn get_connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+    /// Set unprivileged mode
+    ///
+    /// Port scans fall back to TCP connect, host discovery to TCP connect pings.
+    /// Linux only.
+    pub fn set_unprivileged(&mut self, unprivileged: bool) {
+        self.unprivileged = unprivileged;
+    }
+    /// Get unprivileged mode
+    pub fn get_unprivileged(&self) -> bool {
+        self.unprivileged
+    }
+    /// Get network namespace
+    pub fn get_netns(&self) -> Option<String> {
+        self.netns.clone()
+    }
+    /// Set authorized scope
+    ///
+    /// Out of scope destinations are skipped, in strict mode the scan fails without sending.
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = Some(scope);
+    }
+    /// Get authorized scope
+    pub fn get_scope(&self) -> Option<Scope> {
+        self.scope.clone()
+    }
+    /// Set out of scope targets dropped before reaching the scanner
+    ///
+    /// They are reported in the scan result along with the destinations refused by the scope.
+    pub fn set_scope_violations(&mut self, violations: Vec<ScopeViolation>) {
+        self.scope_violations = violations;
+    }
+    /// Get out of scope targets dropped before reaching the scanner
+    pub fn get_scope_violations(&self) -> Vec<ScopeViolation> {
+        self.scope_violations.clone()
+    }
+    /// Set hosts and ports left out of the scan
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) {
+        self.exclusions = exclusions;
+    }
+    /// Get hosts and ports left out of the scan
+    pub fn get_exclusions(&self) -> Exclusions {
+        self.exclusions.clone()
+    }
     /// Get scan result
     pub fn get_scan_result(&self) -> ScanResult {
         self.scan_result.clone()
     }
+    /// Get counters of the running scan, readable while `scan` or `scan_stream` runs
+    pub fn get_stats_counters(&self) -> Arc<ScanCounters> {
+        self.stats.clone()
+    }
+    /// Get current counters of the scan
+    pub fn get_stats(&self) -> ScanStats {
+        self.stats.snapshot()
+    }
     /// Get progress receiver
     pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<SocketAddr>>> {
         self.rx.clone()
     }
-    /// Run Scan
-    pub async fn run_scan(&mut self, pstop: Option<Arc<Mutex<bool>>>) {
+    fn scan_setting(&self) -> Result<(ScanSetting, Vec<ScopeViolation>), String> {
+        self.stats.reset();
+        // out of scope and excluded destinations never reach the scan
+        let destinations = self.exclusions.filter_destinations(self.destinations.clone());
+        let (destinations, violations) = match &self.scope {
+            Some(scope) => scope.filter_destinations(destinations)?,
+            None => (destinations, vec![]),
+        };
         let mut ip_set: HashSet<IpAddr> = HashSet::new();
-        for dst in self.destinations.clone() {
+        for dst in destinations.iter() {
             ip_set.insert(dst.dst_ip);
         }
-        let scan_setting: ScanSetting = ScanSetting {
+        let scan_setting = ScanSetting {
             if_index: self.if_index.clone(),
             src_mac: pnet_datalink::MacAddr::from(self.src_mac),
             dst_mac: pnet_datalink::MacAddr::from(self.dst_mac),
             src_ip: self.src_ip.clone(),
             src_port: self.src_port.clone(),
-            destinations: self.destinations.clone(),
+            destinations,
             ip_set,
             timeout: self.timeout.clone(),
             wait_time: self.wait_time.clone(),
             send_rate: self.send_rate.clone(),
             scan_type: self.scan_type.clone(),
-            hosts_concurrency: DEFAULT_HOSTS_CONCURRENCY,
-            ports_concurrency: DEFAULT_PORTS_CONCURRENCY,
+            hosts_concurrency: self.hosts_concurrency,
+            ports_concurrency: self.ports_concurrency,
+            connect_timeout: self.connect_timeout,
+            unprivileged: self.unprivileged,
+            stats: Arc::clone(&self.stats),
         };
-        let start_time = Instant::now();
-        let mut result: ScanResult = scan_target(scan_setting, &self.tx, pstop).await;
-        result.scan_time = Instant::now().duration_since(start_time);
-        if result.scan_time > self.timeout {
-            result.scan_status = ScanStatus::Timeout;
-        } else {
-            result.scan_status = ScanStatus::Done;
-        }
-        self.scan_result = result;
+        let mut scope_violations = self.scope_violations.clone();
+        scope_violations.extend(violations);
+        Ok((scan_setting, scope_violations))
+    }
+    /// Run Scan
+    pub async fn run_scan(&mut self, pstop: Option<Arc<Mutex<bool>>>) {
+        let (scan_setting, violations) = match self.scan_setting() {
+            Ok(scoped) => scoped,
+            Err(e) => {
+                self.scan_result = ScanResult::failed(e);
+                return;
+            }
+        };
+        let reporter = Reporter::progress(&self.tx);
+        self.scan_result = match self.netns.clone() {
+            // sockets and the receiver pool must be created inside the namespace
+            Some(name) => {
+                let (tx, rx) = oneshot::channel();
+                thread::spawn(move || {
+                    let result = match netns::enter(&name) {
+                        Ok(_) => async_io::block_on(run_with(scan_setting, &reporter, pstop)),
+                        Err(e) => ScanResult::failed(e),
+                    };
+                    let _ = tx.send(result);
+                });
+                rx.await
+                    .unwrap_or_else(|_| ScanResult::failed(String::from("network namespace thread panicked")))
+            }
+            None => run_with(scan_setting, &reporter, pstop).await,
+        };
+        self.scan_result.scope_violations = violations;
+    }
+    /// Run scan in the background and stream its events
+    ///
+    /// Probes, discovered hosts and ports are yielded as they happen, the last
+    /// item is always `ScanEvent::Done` carrying the final result. The scan
+    /// waits while the stream is not polled and stops once it is dropped.
+    /// # Example
+    /// ```
+    /// fn main() -> Result<(), String> {
+    /// #[cfg(feature = "async")]
+    /// {
+    ///     use e_libscanner::{async_scan, frame::result::ScanEvent, Opts};
+    ///     use futures::StreamExt;
+    ///     let scanner = Opts::new(Some(&[
+    ///         "e-libscanner",
+    ///         "--ips",
+    ///         "192.168.1.0/24",
+    ///         "--model",
+    ///         "async",
+    ///         "--scan",
+    ///         "icmp",
+    ///         "--no-gui",
+    ///     ]))?
+    ///     .init()?
+    ///     .downcast::<async_scan::Scanner>()
+    ///     .unwrap();
+    ///     async_io::block_on(async {
+    ///         let mut events = scanner.scan_stream(None);
+    ///         while let Some(event) = events.next().await {
+    ///             match event {
+    ///                 ScanEvent::Host(host) => println!("UP: {}", host.ip_addr),
+    ///                 ScanEvent::Done(result) => println!("Scan Time: {:?}", result.scan_time),
+    ///                 _ => {}
+    ///             }
+    ///         }
+    ///     });
+    /// }
+    /// Ok(())
+    /// }
+    /// ```
+    pub fn scan_stream(
+        &self,
+        pstop: Option<Arc<Mutex<bool>>>,
+    ) -> impl Stream<Item = ScanEvent> + Send + Unpin + 'static {
+        let (tx, rx) = mpsc::channel(EVENT_CHANNEL_SIZE);
+        let scan_setting = self.scan_setting();
+        let stop = pstop.unwrap_or_else(|| Arc::new(Mutex::new(false)));
+        let reporter = Reporter::stream(tx, Arc::clone(&stop));
+        let netns = self.netns.clone();
+        thread::spawn(move || {
+            async_io::block_on(async {
+                let entered = match netns {
+                    Some(name) => netns::enter(&name),
+                    None => Ok(()),
+                };
+                let result = match entered.and(scan_setting) {
+                    Ok((scan_setting, violations)) => {
+                        for violation in violations.iter() {
+                            reporter.event(ScanEvent::Error(violation.to_string())).await;
+                        }
+                        let mut result = run_with(scan_setting, &reporter, Some(stop)).await;
+                        result.scope_violations = violations;
+                        result
+                    }
+                    Err(e) => {
+                        reporter.event(ScanEvent::Error(e.clone())).await;
+                        ScanResult::failed(e)
+                    }
+                };
+                reporter.event(ScanEvent::Done(result)).await;
+            })
+        });
+        rx
     }
     /// Run Sync scan and return result
     pub async fn scan(&mut self, pstop: Option<Arc<Mutex<bool>>>) -> ScanResult {
@@ -235,3 +459,22 @@ impl Scanner {
         self.scan_result.clone()
     }
 }
+
+async fn run_with(
+    scan_setting: ScanSetting,
+    reporter: &Reporter,
+    pstop: Option<Arc<Mutex<bool>>>,
+) -> ScanResult {
+    let timeout = scan_setting.timeout;
+    let stats = Arc::clone(&scan_setting.stats);
+    let start_time = Instant::now();
+    let mut result: ScanResult = scan_target(scan_setting, reporter, pstop).await;
+    result.scan_time = Instant::now().duration_since(start_time);
+    result.stats = stats.snapshot();
+    if result.scan_time > timeout {
+        result.scan_status = ScanStatus::Timeout;
+    } else {
+        result.scan_status = ScanStatus::Done;
+    }
+    result
+}
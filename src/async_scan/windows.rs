@@ -1,26 +1,22 @@
+use super::connect;
+use super::pacer::Pacer;
 use super::receiver;
+use super::reporter::Reporter;
 use super::socket::AsyncSocket;
-use crate::data::DATA;
 use crate::frame::result::ScanStatus;
 use crate::frame::{
-    result::{PortInfo, PortStatus, ScanResult, ScanResults},
-    Destination, ScanSetting, ScanType,
+    result::{ScanResult, ScanResults},
+    ScanSetting, ScanType,
 };
 use crate::packet;
-use async_io::{Async, Timer};
+use async_io::Timer;
 use futures::executor::ThreadPool;
 use futures::stream::{self, StreamExt};
 use futures::task::SpawnExt;
-use futures_lite::{future::FutureExt, io};
 use pnet_packet::Packet;
 use socket2::{Protocol, SockAddr, Type};
-use std::collections::HashMap;
-use std::net::{IpAddr, SocketAddr, TcpStream};
-use std::sync::mpsc;
-use std::sync::mpsc::Sender;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
 
 async fn build_icmpv4_echo_packet() -> Vec<u8> {
     let mut buf = vec![0; 16];
@@ -46,11 +42,8 @@ async fn build_tcp_syn_packet(
 }
 
 async fn build_udp_packet(src_ip: IpAddr, src_port: u16, dst_ip: IpAddr, dst_port: u16) -> Vec<u8> {
-    let mut vec: Vec<u8> = vec![0; 66];
-    let mut udp_packet = pnet_packet::udp::MutableUdpPacket::new(
-        &mut vec[(packet::ethernet::ETHERNET_HEADER_LEN + packet::ipv4::IPV4_HEADER_LEN)..],
-    )
-    .unwrap();
+    let mut vec: Vec<u8> = vec![0; 8];
+    let mut udp_packet = pnet_packet::udp::MutableUdpPacket::new(&mut vec[..]).unwrap();
     packet::udp::build_udp_packet(&mut udp_packet, src_ip, src_port, dst_ip, dst_port);
     udp_packet.packet().to_vec()
 }
@@ -58,28 +51,19 @@ async fn build_udp_packet(src_ip: IpAddr, src_port: u16, dst_ip: IpAddr, dst_por
 async fn send_icmp_echo_packets(
     socket: &AsyncSocket,
     scan_setting: &ScanSetting,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    pacer: &Pacer,
+    reporter: &Reporter,
 ) {
     let fut_host = stream::iter(scan_setting.destinations.clone()).for_each_concurrent(
         scan_setting.hosts_concurrency,
-        |dst| {
-            thread::sleep(scan_setting.send_rate);
+        |dst| async move {
+            pacer.wait().await;
             let socket_addr = SocketAddr::new(dst.dst_ip, 0);
             let sock_addr = SockAddr::from(socket_addr);
-            async move {
-                let mut icmp_packet: Vec<u8> = build_icmpv4_echo_packet().await;
-                match socket.send_to(&mut icmp_packet, &sock_addr).await {
-                    Ok(_) => {}
-                    Err(_) => {}
-                }
-                match ptx.lock() {
-                    Ok(lr) => match lr.send(socket_addr) {
-                        Ok(_) => {}
-                        Err(_) => {}
-                    },
-                    Err(_) => {}
-                }
-            }
+            let mut icmp_packet: Vec<u8> = build_icmpv4_echo_packet().await;
+            let sent = socket.send_to(&mut icmp_packet, &sock_addr).await;
+            scan_setting.stats.sent(&sent);
+            reporter.probe(socket_addr).await;
         },
     );
     fut_host.await;
@@ -88,7 +72,8 @@ async fn send_icmp_echo_packets(
 async fn send_tcp_syn_packets(
     socket: &AsyncSocket,
     scan_setting: &ScanSetting,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    pacer: &Pacer,
+    reporter: &Reporter,
 ) {
     let fut_host = stream::iter(scan_setting.destinations.clone()).for_each_concurrent(
         scan_setting.hosts_concurrency,
@@ -96,11 +81,11 @@ async fn send_tcp_syn_packets(
             let fut_port = stream::iter(dst.dst_ports.clone()).for_each_concurrent(
                 scan_setting.ports_concurrency,
                 |port| {
-                    thread::sleep(scan_setting.send_rate);
                     let dst = dst.clone();
-                    let socket_addr = SocketAddr::new(dst.dst_ip, port);
-                    let sock_addr = SockAddr::from(socket_addr);
                     async move {
+                        pacer.wait().await;
+                        let socket_addr = SocketAddr::new(dst.dst_ip, port);
+                        let sock_addr = SockAddr::from(socket_addr);
                         let mut tcp_packet: Vec<u8> = build_tcp_syn_packet(
                             scan_setting.src_ip,
                             scan_setting.src_port,
@@ -108,17 +93,9 @@ async fn send_tcp_syn_packets(
                             port,
                         )
                         .await;
-                        match socket.send_to(&mut tcp_packet, &sock_addr).await {
-                            Ok(_) => {}
-                            Err(_) => {}
-                        }
-                        match ptx.lock() {
-                            Ok(lr) => match lr.send(socket_addr) {
-                                Ok(_) => {}
-                                Err(_) => {}
-                            },
-                            Err(_) => {}
-                        }
+                        let sent = socket.send_to(&mut tcp_packet, &sock_addr).await;
+                        scan_setting.stats.sent(&sent);
+                        reporter.probe(socket_addr).await;
                     }
                 },
             );
@@ -131,7 +108,8 @@ async fn send_tcp_syn_packets(
 async fn send_udp_packets(
     socket: &AsyncSocket,
     scan_setting: &ScanSetting,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    pacer: &Pacer,
+    reporter: &Reporter,
 ) {
     let fut_host = stream::iter(scan_setting.destinations.clone()).for_each_concurrent(
         scan_setting.hosts_concurrency,
@@ -139,11 +117,11 @@ async fn send_udp_packets(
             let fut_port = stream::iter(dst.dst_ports.clone()).for_each_concurrent(
                 scan_setting.ports_concurrency,
                 |port| {
-                    thread::sleep(scan_setting.send_rate);
                     let dst = dst.clone();
-                    let socket_addr = SocketAddr::new(dst.dst_ip, port);
-                    let sock_addr = SockAddr::from(socket_addr);
                     async move {
+                        pacer.wait().await;
+                        let socket_addr = SocketAddr::new(dst.dst_ip, port);
+                        let sock_addr = SockAddr::from(socket_addr);
                         let mut udp_packet: Vec<u8> = build_udp_packet(
                             scan_setting.src_ip,
                             scan_setting.src_port,
@@ -151,17 +129,9 @@ async fn send_udp_packets(
                             port,
                         )
                         .await;
-                        match socket.send_to(&mut udp_packet, &sock_addr).await {
-                            Ok(_) => {}
-                            Err(_) => {}
-                        }
-                        match ptx.lock() {
-                            Ok(lr) => match lr.send(socket_addr) {
-                                Ok(_) => {}
-                                Err(_) => {}
-                            },
-                            Err(_) => {}
-                        }
+                        let sent = socket.send_to(&mut udp_packet, &sock_addr).await;
+                        scan_setting.stats.sent(&sent);
+                        reporter.probe(socket_addr).await;
                     }
                 },
             );
@@ -171,105 +141,21 @@ async fn send_udp_packets(
     fut_host.await;
 }
 
-async fn try_connect_ports(
-    concurrency: usize,
-    send_rate: Duration,
-    dst: Destination,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
-) -> (IpAddr, Vec<PortInfo>) {
-    let (channel_tx, channel_rx) = mpsc::channel();
-    let conn_timeout = Duration::from_millis(200);
-    let fut = stream::iter(dst.dst_ports.clone()).for_each_concurrent(concurrency, |port| {
-        thread::sleep(send_rate);
-        let dst = dst.clone();
-        let channel_tx = channel_tx.clone();
-        async move {
-            let socket_addr = SocketAddr::new(dst.dst_ip, port);
-            let stream = Async::<TcpStream>::connect(socket_addr)
-                .or(async {
-                    Timer::after(conn_timeout).await;
-                    Err(io::ErrorKind::TimedOut.into())
-                })
-                .await;
-            match stream {
-                Ok(_) => {
-                    let _ = channel_tx.send(port);
-                }
-                _ => {}
-            }
-            match ptx.lock() {
-                Ok(lr) => match lr.send(socket_addr) {
-                    Ok(_) => {}
-                    Err(_) => {}
-                },
-                Err(_) => {}
-            }
-        }
-    });
-    fut.await;
-    drop(channel_tx);
-    let mut open_ports: Vec<PortInfo> = vec![];
-    loop {
-        match channel_rx.recv() {
-            Ok(port) => {
-                open_ports.push(PortInfo {
-                    port,
-                    status: PortStatus::Open,
-                    describe: DATA
-                        .portmap
-                        .get(&port)
-                        .unwrap_or(&"unknown type")
-                        .to_string(),
-                });
-            }
-            Err(_) => {
-                break;
-            }
-        }
-    }
-    (dst.dst_ip, open_ports)
-}
-
-async fn run_connect_scan(
-    scan_setting: ScanSetting,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
-) -> ScanResult {
-    let scan_result: Vec<(IpAddr, Vec<PortInfo>)> =
-        stream::iter(scan_setting.destinations.clone().into_iter())
-            .map(|dst| {
-                try_connect_ports(
-                    scan_setting.ports_concurrency,
-                    scan_setting.send_rate,
-                    dst,
-                    ptx,
-                )
-            })
-            .buffer_unordered(scan_setting.hosts_concurrency)
-            .collect()
-            .await;
-    let mut ip_with_port: HashMap<IpAddr, Vec<PortInfo>> = HashMap::new();
-    for (ip, ports) in scan_result {
-        ip_with_port.insert(ip, ports);
-    }
-    let mut results = ScanResults::new();
-    results.result.ip_with_port = ip_with_port;
-    results.result
-}
-
 async fn send_ping_packet(
     socket: &AsyncSocket,
     scan_setting: &ScanSetting,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    pacer: &Pacer,
+    reporter: &Reporter,
 ) {
     match scan_setting.scan_type {
         ScanType::IcmpPingScan => {
-            send_icmp_echo_packets(socket, scan_setting, ptx).await;
+            send_icmp_echo_packets(socket, scan_setting, pacer, reporter).await;
         }
         ScanType::TcpPingScan => {
-            send_tcp_syn_packets(socket, scan_setting, ptx).await;
+            send_tcp_syn_packets(socket, scan_setting, pacer, reporter).await;
         }
         ScanType::UdpPingScan => {
-            send_udp_packets(socket, scan_setting, ptx).await;
+            send_udp_packets(socket, scan_setting, pacer, reporter).await;
         }
         _ => {
             return;
@@ -279,9 +165,17 @@ async fn send_ping_packet(
 
 pub(crate) async fn scan_target(
     scan_setting: ScanSetting,
-    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    reporter: &Reporter,
     pstop: Option<Arc<Mutex<bool>>>,
 ) -> ScanResult {
+    let stop: Arc<Mutex<bool>> = if let Some(p) = pstop {
+        p
+    } else {
+        Arc::new(Mutex::new(false))
+    };
+    if let ScanType::TcpConnectScan = scan_setting.scan_type {
+        return connect::run_connect_scan(scan_setting, reporter, &stop).await;
+    }
     let interfaces = pnet_datalink::interfaces();
     let interface = match interfaces
         .into_iter()
@@ -303,17 +197,13 @@ pub(crate) async fn scan_target(
         linux_fanout: None,
         promiscuous: false,
     };
-    let (_tx, mut rx) = match pnet_datalink::channel(&interface, config) {
+    let channel = pnet_datalink::channel(&interface, config);
+    let (_tx, mut rx) = match channel {
         Ok(pnet_datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
         Ok(_) => panic!("Unknown channel type"),
         Err(e) => panic!("Error happened {}", e),
     };
     let scan_result: Arc<Mutex<ScanResults>> = Arc::new(Mutex::new(ScanResults::new()));
-    let stop: Arc<Mutex<bool>> = if let Some(p) = pstop {
-        p
-    } else {
-        Arc::new(Mutex::new(false))
-    };
     let receive_result = Arc::clone(&scan_result);
     let receive_setting: ScanSetting = scan_setting.clone();
     let socket = match scan_setting.scan_type {
@@ -326,9 +216,7 @@ pub(crate) async fn scan_target(
         ScanType::UdpPingScan => {
             AsyncSocket::new(scan_setting.src_ip, Type::RAW, Protocol::UDP).unwrap()
         }
-        ScanType::TcpConnectScan => {
-            return run_connect_scan(scan_setting, ptx).await;
-        }
+        ScanType::TcpConnectScan => unreachable!(),
         ScanType::TcpSynScan => {
             // TODO
             // Winsock2 does not allow TCP data to be sent over Raw Socket
@@ -338,13 +226,24 @@ pub(crate) async fn scan_target(
             return result;
         }
     };
+    let pacer = Pacer::new(scan_setting.send_rate);
     let executor = ThreadPool::new().unwrap();
+    let receive_stop = Arc::clone(&stop);
+    let receive_reporter = reporter.clone();
     let future = async move {
-        receiver::receive_packets(&mut rx, receive_setting, &receive_result, &stop).await;
+        receiver::receive_packets(
+            &mut rx,
+            receive_setting,
+            &receive_result,
+            &receive_stop,
+            &receive_reporter,
+        )
+        .await;
     };
     executor.spawn(future).unwrap();
-    send_ping_packet(&socket, &scan_setting, ptx).await;
-    thread::sleep(scan_setting.wait_time);
+    send_ping_packet(&socket, &scan_setting, &pacer, reporter).await;
+    Timer::after(scan_setting.wait_time).await;
+    *stop.lock().unwrap() = true;
 
     let result: ScanResult = scan_result.lock().unwrap().result.clone();
     return result;
@@ -1,3 +1,7 @@
+use std::time::Duration;
+
 pub(crate) const DEFAULT_SRC_PORT: u16 = 53443;
 pub(crate) const DEFAULT_HOSTS_CONCURRENCY: usize = 50;
-pub(crate) const DEFAULT_PORTS_CONCURRENCY: usize = 100;
\ No newline at end of file
+pub(crate) const DEFAULT_PORTS_CONCURRENCY: usize = 100;
+pub(crate) const EVENT_CHANNEL_SIZE: usize = 1024;
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_millis(200);
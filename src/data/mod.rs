@@ -1,17 +1,205 @@
 /// Const key value
 pub mod id;
 mod portmap;
+mod top_ports;
 use std::collections::HashMap;
+use std::sync::RwLock;
 
 use once_cell::sync::Lazy;
+use serde_derive::{Deserialize, Serialize};
+
+/// Name of ports without a known service
+pub const UNKNOWN_SERVICE: &str = "unknown";
 
 /// The know port of name in hash
-#[derive(Debug)] 
+#[derive(Debug)]
 pub struct Data {
     /// The know port of name in hash
     pub portmap: HashMap<u16, &'static str>,
+    /// The know UDP port of name in hash
+    pub udp_portmap: HashMap<u16, &'static str>,
+    /// The know SCTP port of name in hash
+    pub sctp_portmap: HashMap<u16, &'static str>,
+    /// Service names loaded at runtime, take precedence over the embedded maps
+    overrides: RwLock<HashMap<(Protocol, u16), String>>,
 }
-/// Static data api 
+/// Static data api
 pub static DATA: Lazy<Data> = Lazy::new(|| Data {
     portmap: portmap::get_tcp_portmap(),
+    udp_portmap: portmap::get_udp_portmap(),
+    sctp_portmap: portmap::get_sctp_portmap(),
+    overrides: RwLock::new(HashMap::new()),
 });
+
+/// Transport protocol of a port
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// TCP
+    Tcp,
+    /// UDP
+    Udp,
+    /// SCTP
+    Sctp,
+}
+
+impl Protocol {
+    /// Parse protocol name as used by services files
+    pub fn from_name(name: &str) -> Option<Protocol> {
+        match name.to_ascii_lowercase().as_str() {
+            "tcp" => Some(Protocol::Tcp),
+            "udp" => Some(Protocol::Udp),
+            "sctp" => Some(Protocol::Sctp),
+            _ => None,
+        }
+    }
+    /// Lowercase protocol name; example: "tcp"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+        }
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Data {
+    /// Get embedded map of the protocol
+    fn embedded(&self, proto: Protocol) -> &HashMap<u16, &'static str> {
+        match proto {
+            Protocol::Tcp => &self.portmap,
+            Protocol::Udp => &self.udp_portmap,
+            Protocol::Sctp => &self.sctp_portmap,
+        }
+    }
+    /// Get service name of the port, `unknown` if the port has none
+    pub fn service_name(&self, proto: Protocol, port: u16) -> String {
+        if let Ok(overrides) = self.overrides.read() {
+            if let Some(name) = overrides.get(&(proto, port)) {
+                return name.clone();
+            }
+        }
+        self.embedded(proto)
+            .get(&port)
+            .unwrap_or(&UNKNOWN_SERVICE)
+            .to_string()
+    }
+    /// Get ports of the service name in ascending order
+    pub fn service_ports(&self, proto: Protocol, name: &str) -> Vec<u16> {
+        let overrides = match self.overrides.read() {
+            Ok(overrides) => overrides.clone(),
+            Err(_) => HashMap::new(),
+        };
+        let mut ports: Vec<u16> = self
+            .embedded(proto)
+            .iter()
+            .filter(|(port, service)| **service == name && !overrides.contains_key(&(proto, **port)))
+            .map(|(port, _)| *port)
+            .chain(
+                overrides
+                    .iter()
+                    .filter(|((p, _), service)| *p == proto && service.as_str() == name)
+                    .map(|((_, port), _)| *port),
+            )
+            .collect();
+        ports.sort_unstable();
+        ports
+    }
+    /// Set service name of the port, overriding the embedded one
+    pub fn set_service(&self, proto: Protocol, port: u16, name: &str) {
+        if let Ok(mut overrides) = self.overrides.write() {
+            overrides.insert((proto, port), name.to_string());
+        }
+    }
+    /// Load services file in `/etc/services` format and return the number of entries
+    ///
+    /// Each line is `name port/protocol [aliases...] [# comment]`; entries
+    /// override or extend the embedded maps.
+    /// # Example
+    /// ```
+    /// use e_libscanner::data::{Protocol, DATA};
+    /// std::fs::write("custom-services", "intranet-api 9443/tcp # our API\n").unwrap();
+    /// assert_eq!(DATA.load_services_file("custom-services"), Ok(1));
+    /// assert_eq!(DATA.service_name(Protocol::Tcp, 9443), "intranet-api");
+    /// assert_eq!(DATA.service_name(Protocol::Udp, 1), "unknown");
+    /// # std::fs::remove_file("custom-services").unwrap();
+    /// ```
+    pub fn load_services_file(&self, path: &str) -> Result<usize, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut entries = vec![];
+        for (n, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (name, port_proto) = match (fields.next(), fields.next()) {
+                (Some(name), Some(port_proto)) => (name, port_proto),
+                _ => return Err(format!("{}:{}: expected `name port/protocol`", path, n + 1)),
+            };
+            let (port, proto) = port_proto
+                .split_once('/')
+                .ok_or_else(|| format!("{}:{}: expected `port/protocol`", path, n + 1))?;
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| format!("{}:{}: invalid port `{}`", path, n + 1, port))?;
+            // protocols other than tcp, udp and sctp(ddp, ...) are not scanned
+            if let Some(proto) = Protocol::from_name(proto) {
+                entries.push((proto, port, name.to_string()));
+            }
+        }
+        let count = entries.len();
+        if let Ok(mut overrides) = self.overrides.write() {
+            for (proto, port, name) in entries {
+                overrides.insert((proto, port), name);
+            }
+        }
+        Ok(count)
+    }
+    /// Load the services file of the operating system
+    pub fn load_system_services(&self) -> Result<usize, String> {
+        #[cfg(target_os = "windows")]
+        let path = format!(
+            "{}\\System32\\drivers\\etc\\services",
+            std::env::var("SystemRoot").unwrap_or(String::from("C:\\Windows"))
+        );
+        #[cfg(not(target_os = "windows"))]
+        let path = String::from("/etc/services");
+        self.load_services_file(&path)
+    }
+    /// Get the `n` most frequently open ports of the protocol, most common first
+    ///
+    /// The frequency tables rank 295 TCP and 88 UDP ports; a larger `n` is an error.
+    /// # Example
+    /// ```
+    /// use e_libscanner::data::{Protocol, DATA};
+    /// let ports = DATA.top_ports(Protocol::Tcp, 100).unwrap();
+    /// assert_eq!(ports.len(), 100);
+    /// assert_eq!(ports[0], 80);
+    /// assert_eq!(DATA.top_ports(Protocol::Udp, 3).unwrap(), vec![631, 161, 137]);
+    /// assert!(DATA.top_ports(Protocol::Tcp, 1000).is_err());
+    /// ```
+    pub fn top_ports(&self, proto: Protocol, n: usize) -> Result<Vec<u16>, String> {
+        let table: &[u16] = match proto {
+            Protocol::Tcp => &top_ports::TCP_TOP_PORTS,
+            Protocol::Udp => &top_ports::UDP_TOP_PORTS,
+            Protocol::Sctp => &[],
+        };
+        if n > table.len() {
+            return Err(format!(
+                "top ports: only {} {:?} ports are ranked, {} requested",
+                table.len(),
+                proto,
+                n
+            ));
+        }
+        Ok(table[..n].to_vec())
+    }
+}
@@ -5806,4 +5806,125 @@ pub(crate) fn get_tcp_portmap() -> HashMap<u16, &'static str> {
     tcp_portmap.insert(49001, "nusrp");
     tcp_portmap.insert(49150, "inspider");
     tcp_portmap
-}
\ No newline at end of file
+}
+
+pub(crate) fn get_udp_portmap() -> HashMap<u16, &'static str> {
+    let mut udp_portmap:HashMap<u16, &'static str> = HashMap::new();
+    udp_portmap.insert(7, "echo");
+    udp_portmap.insert(9, "discard");
+    udp_portmap.insert(13, "daytime");
+    udp_portmap.insert(17, "qotd");
+    udp_portmap.insert(19, "chargen");
+    udp_portmap.insert(37, "time");
+    udp_portmap.insert(49, "tacacs");
+    udp_portmap.insert(53, "domain");
+    udp_portmap.insert(67, "bootps");
+    udp_portmap.insert(68, "bootpc");
+    udp_portmap.insert(69, "tftp");
+    udp_portmap.insert(88, "kerberos");
+    udp_portmap.insert(111, "sunrpc");
+    udp_portmap.insert(123, "ntp");
+    udp_portmap.insert(137, "netbios-ns");
+    udp_portmap.insert(138, "netbios-dgm");
+    udp_portmap.insert(139, "netbios-ssn");
+    udp_portmap.insert(161, "snmp");
+    udp_portmap.insert(162, "snmptrap");
+    udp_portmap.insert(177, "xdmcp");
+    udp_portmap.insert(389, "ldap");
+    udp_portmap.insert(427, "svrloc");
+    udp_portmap.insert(443, "https");
+    udp_portmap.insert(464, "kpasswd");
+    udp_portmap.insert(500, "isakmp");
+    udp_portmap.insert(512, "biff");
+    udp_portmap.insert(513, "who");
+    udp_portmap.insert(514, "syslog");
+    udp_portmap.insert(517, "talk");
+    udp_portmap.insert(518, "ntalk");
+    udp_portmap.insert(520, "router");
+    udp_portmap.insert(521, "ripng");
+    udp_portmap.insert(546, "dhcpv6-client");
+    udp_portmap.insert(547, "dhcpv6-server");
+    udp_portmap.insert(623, "asf-rmcp");
+    udp_portmap.insert(631, "ipp");
+    udp_portmap.insert(636, "ldaps");
+    udp_portmap.insert(853, "domain-s");
+    udp_portmap.insert(1194, "openvpn");
+    udp_portmap.insert(1434, "ms-sql-m");
+    udp_portmap.insert(1604, "icabrowser");
+    udp_portmap.insert(1645, "sightline");
+    udp_portmap.insert(1701, "l2f");
+    udp_portmap.insert(1719, "h323gatestat");
+    udp_portmap.insert(1812, "radius");
+    udp_portmap.insert(1813, "radius-acct");
+    udp_portmap.insert(1900, "ssdp");
+    udp_portmap.insert(1985, "hsrp");
+    udp_portmap.insert(2049, "nfs");
+    udp_portmap.insert(2123, "gtp-control");
+    udp_portmap.insert(2152, "gtp-user");
+    udp_portmap.insert(2427, "mgcp-gateway");
+    udp_portmap.insert(2727, "mgcp-callagent");
+    udp_portmap.insert(3283, "net-assistant");
+    udp_portmap.insert(3478, "stun");
+    udp_portmap.insert(3702, "ws-discovery");
+    udp_portmap.insert(3784, "bfd-control");
+    udp_portmap.insert(3785, "bfd-echo");
+    udp_portmap.insert(4500, "ipsec-nat-t");
+    udp_portmap.insert(4784, "bfd-multi-ctl");
+    udp_portmap.insert(4789, "vxlan");
+    udp_portmap.insert(5060, "sip");
+    udp_portmap.insert(5061, "sips");
+    udp_portmap.insert(5351, "nat-pmp");
+    udp_portmap.insert(5353, "mdns");
+    udp_portmap.insert(5355, "llmnr");
+    udp_portmap.insert(5632, "pcanywherestat");
+    udp_portmap.insert(5683, "coap");
+    udp_portmap.insert(5684, "coaps");
+    udp_portmap.insert(6081, "geneve");
+    udp_portmap.insert(6343, "sflow");
+    udp_portmap.insert(6514, "syslog-tls");
+    udp_portmap.insert(11211, "memcache");
+    udp_portmap.insert(33434, "traceroute");
+    udp_portmap.insert(47808, "bacnet");
+    udp_portmap
+}
+
+pub(crate) fn get_sctp_portmap() -> HashMap<u16, &'static str> {
+    let mut sctp_portmap:HashMap<u16, &'static str> = HashMap::new();
+    sctp_portmap.insert(7, "echo");
+    sctp_portmap.insert(9, "discard");
+    sctp_portmap.insert(20, "ftp-data");
+    sctp_portmap.insert(21, "ftp");
+    sctp_portmap.insert(22, "ssh");
+    sctp_portmap.insert(80, "http");
+    sctp_portmap.insert(179, "bgp");
+    sctp_portmap.insert(443, "https");
+    sctp_portmap.insert(1167, "cisco-ipsla");
+    sctp_portmap.insert(2225, "rcip-itu");
+    sctp_portmap.insert(2904, "m2ua");
+    sctp_portmap.insert(2905, "m3ua");
+    sctp_portmap.insert(2944, "megaco-h248");
+    sctp_portmap.insert(2945, "h248-binary");
+    sctp_portmap.insert(3097, "itu-bicc-stc");
+    sctp_portmap.insert(3565, "m2pa");
+    sctp_portmap.insert(3863, "asap-sctp");
+    sctp_portmap.insert(3864, "asap-sctp-tls");
+    sctp_portmap.insert(3868, "diameter");
+    sctp_portmap.insert(4739, "ipfix");
+    sctp_portmap.insert(4740, "ipfixs");
+    sctp_portmap.insert(5060, "sip");
+    sctp_portmap.insert(5061, "sips");
+    sctp_portmap.insert(5090, "car");
+    sctp_portmap.insert(5091, "cxtp");
+    sctp_portmap.insert(5672, "amqp");
+    sctp_portmap.insert(6704, "frc-hp");
+    sctp_portmap.insert(7626, "simco");
+    sctp_portmap.insert(9082, "lcs-ap");
+    sctp_portmap.insert(9084, "aurora");
+    sctp_portmap.insert(9900, "iua");
+    sctp_portmap.insert(14001, "sua");
+    sctp_portmap.insert(29118, "sgsap");
+    sctp_portmap.insert(29168, "sbcap");
+    sctp_portmap.insert(36412, "s1-control");
+    sctp_portmap.insert(36422, "x2-control");
+    sctp_portmap
+}
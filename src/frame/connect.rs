@@ -0,0 +1,122 @@
+use super::result::{PortStatus, ScanCounters};
+use super::Destination;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io;
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+
+/// Number of attempts for a connection that failed on local resources
+pub(crate) const CONNECT_RETRIES: u32 = 5;
+/// First back-off delay after local resources ran out; doubled on every retry
+pub(crate) const CONNECT_BACKOFF: Duration = Duration::from_millis(50);
+/// Destination ports of TCP pings when the destination has no ports
+pub(crate) const TCP_PING_PORTS: [u16; 2] = [80, 443];
+
+/// Outcome of a single TCP connection attempt
+#[derive(Debug)]
+pub(crate) enum ConnectOutcome {
+    /// The remote end answered; port status is known
+    Status(PortStatus),
+    /// No socket or local port was available, the attempt says nothing about the target
+    Exhausted(io::Error),
+}
+
+impl ConnectOutcome {
+    /// Classify the result of a connection attempt
+    ///
+    /// ECONNREFUSED means Closed, a timeout or any other network error means Filtered.
+    pub(crate) fn from_result<T>(result: io::Result<T>) -> ConnectOutcome {
+        match result {
+            Ok(_) => ConnectOutcome::Status(PortStatus::Open),
+            Err(e) if is_exhausted(&e) => ConnectOutcome::Exhausted(e),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                ConnectOutcome::Status(PortStatus::Closed)
+            }
+            Err(_) => ConnectOutcome::Status(PortStatus::Filtered),
+        }
+    }
+}
+
+/// Check if the error comes from running out of file descriptors,
+/// buffers or ephemeral ports on the local host
+pub(crate) fn is_exhausted(e: &io::Error) -> bool {
+    if e.kind() == io::ErrorKind::AddrNotAvailable {
+        return true;
+    }
+    match e.raw_os_error() {
+        #[cfg(not(target_os = "windows"))]
+        Some(code) => {
+            code == libc::EMFILE
+                || code == libc::ENFILE
+                || code == libc::ENOBUFS
+                || code == libc::EADDRNOTAVAIL
+        }
+        // WSAEMFILE, WSAENOBUFS, WSAEADDRNOTAVAIL
+        #[cfg(target_os = "windows")]
+        Some(code) => code == 10024 || code == 10055 || code == 10049,
+        None => false,
+    }
+}
+
+/// Ports a TCP connect ping tries on the destination, the first answer proves the host up
+pub(crate) fn ping_ports(dst: &Destination) -> &[u16] {
+    if dst.dst_ports.is_empty() {
+        &TCP_PING_PORTS[..]
+    } else {
+        &dst.dst_ports[..]
+    }
+}
+
+/// Warning of a scan run without raw sockets over TCP connections, if its type is degraded
+#[cfg(any(feature = "async", feature = "tokio"))]
+pub(crate) fn degraded_warning(scan_type: &super::ScanType) -> Option<String> {
+    use super::ScanType;
+    let degraded = match scan_type {
+        ScanType::TcpConnectScan => return None,
+        ScanType::TcpSynScan => String::from("TcpConnectScan"),
+        _ => format!("TCP connect ping on ports {:?}", TCP_PING_PORTS),
+    };
+    Some(format!(
+        "unprivileged mode, {:?} degraded to {}",
+        scan_type, degraded
+    ))
+}
+
+/// Back-off delay before the given retry
+pub(crate) fn backoff(retry: u32) -> Duration {
+    CONNECT_BACKOFF * 2u32.pow(retry.min(6))
+}
+
+/// Blocking TCP connect with timeout, retried while local resources are exhausted
+pub(crate) fn try_connect(
+    socket_addr: SocketAddr,
+    conn_timeout: Duration,
+    stats: &ScanCounters,
+) -> io::Result<PortStatus> {
+    let sock_addr = SockAddr::from(socket_addr);
+    let mut retry = 0;
+    loop {
+        let result = Socket::new(
+            Domain::for_address(socket_addr),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )
+        .and_then(|socket| socket.connect_timeout(&sock_addr, conn_timeout));
+        match ConnectOutcome::from_result(result) {
+            ConnectOutcome::Status(status) => {
+                stats.probe_sent();
+                return Ok(status);
+            }
+            ConnectOutcome::Exhausted(e) => {
+                if retry >= CONNECT_RETRIES {
+                    stats.send_error();
+                    return Err(e);
+                }
+                stats.retry();
+                thread::sleep(backoff(retry));
+                retry += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use pnet_datalink::{Config, DataLinkReceiver, NetworkInterface};
+use std::io;
+
+/// Drop counter of the packet socket a scan receives its frames on
+///
+/// Frames the kernel dropped because the receiver did not keep up; Linux only.
+/// The kernel zeroes the count on every read, so the scan reads it once when
+/// its receiver stops.
+#[derive(Debug)]
+pub(crate) struct DropCounter {
+    #[cfg(target_os = "linux")]
+    socket: std::sync::Arc<socket2::Socket>,
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::DropCounter;
+    use pnet_datalink::{Config, DataLinkReceiver, NetworkInterface};
+    use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+    use std::io::{self, Read};
+    use std::mem;
+    use std::os::raw::{c_int, c_uint};
+    use std::os::unix::io::AsRawFd;
+    use std::sync::Arc;
+
+    /// `PACKET_STATISTICS` option of `SOL_PACKET`
+    const PACKET_STATISTICS: c_int = 6;
+
+    /// `struct tpacket_stats`
+    #[repr(C)]
+    #[derive(Default)]
+    struct TpacketStats {
+        tp_packets: c_uint,
+        tp_drops: c_uint,
+    }
+
+    /// Receiver on a packet socket the scan owns, pnet keeps its own private
+    struct PacketReceiver {
+        socket: Arc<Socket>,
+        buf: Vec<u8>,
+    }
+
+    impl DataLinkReceiver for PacketReceiver {
+        fn next(&mut self) -> io::Result<&[u8]> {
+            let n = (&*self.socket).read(&mut self.buf)?;
+            Ok(&self.buf[..n])
+        }
+    }
+
+    /// Packet socket receiving every frame of the interface
+    fn packet_socket(interface: &NetworkInterface, config: &Config) -> io::Result<Socket> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be() as c_int;
+        let socket = Socket::new(Domain::PACKET, Type::RAW, Some(Protocol::from(protocol)))?;
+        // SAFETY: sockaddr_storage is larger than sockaddr_ll and all zero is valid for both.
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let addr = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_ll) };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol as u16;
+        addr.sll_ifindex = interface.index as c_int;
+        let len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        // SAFETY: storage holds a sockaddr_ll of `len` bytes.
+        socket.bind(&unsafe { SockAddr::new(storage, len) })?;
+        socket.set_read_timeout(config.read_timeout)?;
+        Ok(socket)
+    }
+
+    pub(super) fn receiver(
+        interface: &NetworkInterface,
+        config: Config,
+    ) -> io::Result<(Box<dyn DataLinkReceiver>, Option<DropCounter>)> {
+        let socket = Arc::new(packet_socket(interface, &config)?);
+        let counter = DropCounter {
+            socket: Arc::clone(&socket),
+        };
+        // frames dropped before the socket was bound to the interface are not the scan's
+        counter.take();
+        let receiver = PacketReceiver {
+            socket,
+            buf: vec![0; config.read_buffer_size.max(65536)],
+        };
+        Ok((Box::new(receiver), Some(counter)))
+    }
+
+    impl DropCounter {
+        /// Frames dropped since the last read
+        pub(super) fn read(&self) -> Option<u64> {
+            let mut stats = TpacketStats::default();
+            let mut len = mem::size_of::<TpacketStats>() as libc::socklen_t;
+            // SAFETY: stats outlives the call and len matches its size.
+            let ret = unsafe {
+                libc::getsockopt(
+                    self.socket.as_raw_fd(),
+                    libc::SOL_PACKET,
+                    PACKET_STATISTICS,
+                    &mut stats as *mut TpacketStats as *mut libc::c_void,
+                    &mut len,
+                )
+            };
+            if ret == 0 {
+                Some(stats.tp_drops as u64)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl DropCounter {
+    /// Frames dropped since the last call; 0 where the count is unavailable
+    pub(crate) fn take(&self) -> u64 {
+        #[cfg(target_os = "linux")]
+        {
+            self.read().unwrap_or(0)
+        }
+        #[cfg(not(target_os = "linux"))]
+        0
+    }
+}
+
+/// Open the receiving side of a datalink channel together with the drop counter of its socket
+///
+/// On Linux the scan opens the packet socket itself so its statistics can be read;
+/// elsewhere it is a pnet channel without a drop count.
+pub(crate) fn receiver(
+    interface: &NetworkInterface,
+    config: Config,
+) -> io::Result<(Box<dyn DataLinkReceiver>, Option<DropCounter>)> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::receiver(interface, config)
+    }
+    #[cfg(not(target_os = "linux"))]
+    match pnet_datalink::channel(interface, config)? {
+        pnet_datalink::Channel::Ethernet(_, rx) => Ok((rx, None)),
+        _ => Err(io::Error::new(io::ErrorKind::Other, "Unknown channel type")),
+    }
+}
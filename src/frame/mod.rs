@@ -1,8 +1,14 @@
+pub(crate) mod connect;
+#[cfg(not(target_os = "windows"))]
+pub(crate) mod datalink;
+pub(crate) mod udp;
 #[doc(hidden)]
 pub mod result;
 use pnet_datalink::MacAddr;
+use result::ScanCounters;
 use std::collections::HashSet;
 use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Scan Type
@@ -35,8 +41,10 @@ pub enum ScanType {
 pub struct Destination {
     /// Destination IP address
     pub dst_ip: IpAddr,
-    /// Destination ports
+    /// Destination TCP ports
     pub dst_ports: Vec<u16>,
+    /// Destination UDP ports, scanned along with the TCP ports by port scans
+    pub dst_udp_ports: Vec<u16>,
 }
 
 impl Destination {
@@ -45,6 +53,7 @@ impl Destination {
         Destination {
             dst_ip: ip_addr,
             dst_ports: ports,
+            dst_udp_ports: vec![],
         }
     }
     /// Create new Destination with IP address and port range
@@ -56,6 +65,7 @@ impl Destination {
         Destination {
             dst_ip: ip_addr,
             dst_ports: ports,
+            dst_udp_ports: vec![],
         }
     }
     /// Set destination IP address
@@ -74,6 +84,18 @@ impl Destination {
     pub fn get_dst_port(&self) -> Vec<u16> {
         self.dst_ports.clone()
     }
+    /// Set destination UDP ports
+    pub fn set_dst_udp_port(&mut self, ports: Vec<u16>) {
+        self.dst_udp_ports = ports;
+    }
+    /// Get destination UDP ports
+    pub fn get_dst_udp_port(&self) -> Vec<u16> {
+        self.dst_udp_ports.clone()
+    }
+    /// Returns true if the destination has TCP or UDP ports
+    pub fn has_ports(&self) -> bool {
+        !self.dst_ports.is_empty() || !self.dst_udp_ports.is_empty()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -96,4 +118,8 @@ pub(crate) struct ScanSetting {
     pub(crate) hosts_concurrency: usize,
     #[allow(dead_code)]
     pub(crate) ports_concurrency: usize,
+    pub(crate) connect_timeout: Duration,
+    #[allow(dead_code)]
+    pub(crate) unprivileged: bool,
+    pub(crate) stats: Arc<ScanCounters>,
 }
@@ -1,10 +1,17 @@
+use crate::data::{Protocol, DATA};
+use crate::scope::ScopeViolation;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::io;
 use std::net::{IpAddr, SocketAddr};
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 /// Status of scan task
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ScanStatus {
     Ready,
     Done,
@@ -13,11 +20,14 @@ pub enum ScanStatus {
 }
 
 /// Status of the scanned port
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PortStatus {
     Open,
     Closed,
     Filtered,
+    /// No answer to a UDP probe; the port is open or the probe was dropped
+    OpenFiltered,
 }
 impl fmt::Display for PortStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -28,12 +38,13 @@ impl fmt::Display for PortStatus {
                 PortStatus::Open => "Open",
                 PortStatus::Closed => "Closed",
                 PortStatus::Filtered => "Filtered",
+                PortStatus::OpenFiltered => "Open|Filtered",
             }
         )
     }
 }
 /// Information about the scanned host
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct HostInfo {
     /// IP address of the host
     pub ip_addr: IpAddr,
@@ -47,23 +58,40 @@ impl fmt::Display for HostInfo {
 }
 
 /// Information about the scanned port
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PortInfo {
     /// Port number
     pub port: u16,
+    /// Transport protocol of the port
+    pub protocol: Protocol,
     /// Port status
     pub status: PortStatus,
     // Port describe
     pub describe: String,
 }
+impl PortInfo {
+    /// Create port information, describe is the service name of the port
+    pub fn new(port: u16, protocol: Protocol, status: PortStatus) -> PortInfo {
+        PortInfo {
+            port,
+            protocol,
+            status,
+            describe: DATA.service_name(protocol, port),
+        }
+    }
+}
 impl fmt::Display for PortInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{} {} {}] ", self.port, self.status, self.describe)
+        write!(
+            f,
+            "[{}/{} {} {}] ",
+            self.port, self.protocol, self.status, self.describe
+        )
     }
 }
 
 /// Result of port scan
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ScanResult {
     pub ips: Vec<HostInfo>,
     /// HashMap of scanned IP addresses and their respective port scan results.
@@ -72,6 +100,24 @@ pub struct ScanResult {
     pub scan_time: Duration,
     /// Status of the scan task
     pub scan_status: ScanStatus,
+    /// Probe and capture counters of the scan
+    #[serde(default)]
+    pub stats: ScanStats,
+    /// Degradations of the scan, e.g. a scan type replaced for lack of privileges
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Errors met during the scan; the reason of an `Error` status
+    #[serde(default)]
+    pub errors: Vec<String>,
+    /// Targets left out of the scan for being out of scope
+    #[serde(default)]
+    pub scope_violations: Vec<ScopeViolation>,
+}
+
+impl Default for ScanResult {
+    fn default() -> ScanResult {
+        ScanResult::new()
+    }
 }
 
 impl ScanResult {
@@ -82,8 +128,31 @@ impl ScanResult {
             ip_with_port: HashMap::new(),
             scan_time: Duration::from_millis(0),
             scan_status: ScanStatus::Ready,
+            stats: ScanStats::default(),
+            warnings: vec![],
+            errors: vec![],
+            scope_violations: vec![],
+        }
+    }
+    /// Result of a scan that could not run
+    pub(crate) fn failed(error: String) -> ScanResult {
+        ScanResult {
+            scan_status: ScanStatus::Error,
+            errors: vec![error],
+            ..ScanResult::new()
         }
     }
+    /// Returns true if the host answered: to the host scan, or with an open or closed port
+    ///
+    /// Filtered ports are recorded for hosts that never answered, they do not prove a host up.
+    pub fn is_host_up(&self, ip: IpAddr) -> bool {
+        self.ips.iter().any(|host| host.ip_addr == ip)
+            || self.ip_with_port.get(&ip).map_or(false, |ports| {
+                ports
+                    .iter()
+                    .any(|p| matches!(p.status, PortStatus::Open | PortStatus::Closed))
+            })
+    }
     /// Returns IP addresses from the scan result
     pub fn get_hosts(&self) -> Vec<IpAddr> {
         self.ips
@@ -91,13 +160,17 @@ impl ScanResult {
             .map(|info| info.ip_addr)
             .collect::<Vec<IpAddr>>()
     }
-    /// Get open ports of the specified IP address from the scan results
+    /// Get open TCP ports of the specified IP address from the scan results
     pub fn get_open_ports(&self, ip_addr: IpAddr) -> Vec<u16> {
+        self.get_open_ports_of(ip_addr, Protocol::Tcp)
+    }
+    /// Get open ports of the protocol of the specified IP address from the scan results
+    pub fn get_open_ports_of(&self, ip_addr: IpAddr, protocol: Protocol) -> Vec<u16> {
         let mut open_ports: Vec<u16> = vec![];
         if let Some(ports) = self.ip_with_port.get(&ip_addr) {
             for port_info in ports {
                 match port_info.status {
-                    PortStatus::Open => {
+                    PortStatus::Open if port_info.protocol == protocol => {
                         open_ports.push(port_info.port);
                     }
                     _ => {}
@@ -108,11 +181,192 @@ impl ScanResult {
     }
 }
 
+/// Probe and capture counters of a scan
+///
+/// Filled in by the sync and async scanners; other scanners leave it zeroed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanStats {
+    /// Probes sent: packets, connection attempts and UDP datagrams
+    pub probes_sent: u64,
+    /// Probes the socket refused to send
+    pub send_errors: u64,
+    /// Frames read from the datalink channel
+    pub frames_received: u64,
+    /// Frames answering a probe of the scan
+    pub frames_matched: u64,
+    /// Probes repeated after local sockets or ports ran out
+    pub retries: u64,
+    /// Answers about hosts or ports already in the result
+    pub duplicates: u64,
+    /// Frames the kernel dropped on the packet socket of the scan, counted when the scan ends(Linux only)
+    pub dropped: u64,
+    /// Probes per second from the start of the scan to the last probe
+    pub packet_rate: f64,
+}
+
+impl ScanStats {
+    /// Add the counters of another scan; the packet rate becomes the one of `other`
+    pub fn add(&mut self, other: &ScanStats) {
+        self.probes_sent += other.probes_sent;
+        self.send_errors += other.send_errors;
+        self.frames_received += other.frames_received;
+        self.frames_matched += other.frames_matched;
+        self.retries += other.retries;
+        self.duplicates += other.duplicates;
+        self.dropped += other.dropped;
+        self.packet_rate = other.packet_rate;
+    }
+}
+
+impl fmt::Display for ScanStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sent {} ({} errors, {} retries) at {:.1}/s, received {} frames ({} matched, {} duplicates, {} dropped)",
+            self.probes_sent,
+            self.send_errors,
+            self.retries,
+            self.packet_rate,
+            self.frames_received,
+            self.frames_matched,
+            self.duplicates,
+            self.dropped
+        )
+    }
+}
+
+/// Live counters of a running scan, shared by its threads
+///
+/// A scanner resets them when a scan starts; `snapshot` may be called from
+/// any thread while the scan is running.
+#[derive(Debug)]
+pub struct ScanCounters {
+    probes_sent: AtomicU64,
+    send_errors: AtomicU64,
+    frames_received: AtomicU64,
+    frames_matched: AtomicU64,
+    retries: AtomicU64,
+    duplicates: AtomicU64,
+    dropped: AtomicU64,
+    /// Nanoseconds from the start to the last probe
+    last_probe: AtomicU64,
+    start: Mutex<Instant>,
+}
+
+impl Default for ScanCounters {
+    fn default() -> ScanCounters {
+        ScanCounters {
+            probes_sent: AtomicU64::new(0),
+            send_errors: AtomicU64::new(0),
+            frames_received: AtomicU64::new(0),
+            frames_matched: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            duplicates: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            last_probe: AtomicU64::new(0),
+            start: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl ScanCounters {
+    /// Create zeroed counters
+    pub fn new() -> ScanCounters {
+        ScanCounters::default()
+    }
+    /// Zero the counters and restart the clock of the packet rate
+    pub(crate) fn reset(&self) {
+        for counter in [
+            &self.probes_sent,
+            &self.send_errors,
+            &self.frames_received,
+            &self.frames_matched,
+            &self.retries,
+            &self.duplicates,
+            &self.dropped,
+            &self.last_probe,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        *self.start.lock().unwrap() = Instant::now();
+    }
+    /// Count the outcome of sending a probe
+    pub(crate) fn sent<T>(&self, result: &io::Result<T>) {
+        match result {
+            Ok(_) => self.probe_sent(),
+            Err(_) => self.send_error(),
+        }
+    }
+    /// Count a probe sent
+    pub(crate) fn probe_sent(&self) {
+        self.probes_sent.fetch_add(1, Ordering::Relaxed);
+        let elapsed = self.start.lock().unwrap().elapsed().as_nanos() as u64;
+        self.last_probe.fetch_max(elapsed, Ordering::Relaxed);
+    }
+    /// Count a probe that could not be sent
+    pub(crate) fn send_error(&self) {
+        self.send_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Count a frame read from the datalink channel
+    pub(crate) fn frame_received(&self) {
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Count a frame answering a probe; `new` is false if the answer was already known
+    pub(crate) fn frame_matched(&self, new: bool) {
+        self.frames_matched.fetch_add(1, Ordering::Relaxed);
+        if !new {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    /// Count a repeated probe
+    pub(crate) fn retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Count frames dropped by the datalink channel
+    pub(crate) fn add_dropped(&self, dropped: u64) {
+        self.dropped.fetch_add(dropped, Ordering::Relaxed);
+    }
+    /// Current values
+    pub fn snapshot(&self) -> ScanStats {
+        let probes_sent = self.probes_sent.load(Ordering::Relaxed);
+        let sending = Duration::from_nanos(self.last_probe.load(Ordering::Relaxed));
+        ScanStats {
+            probes_sent,
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_matched: self.frames_matched.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            packet_rate: if sending.is_zero() {
+                0.0
+            } else {
+                probes_sent as f64 / sending.as_secs_f64()
+            },
+        }
+    }
+}
+
+/// Event emitted while a scan is running
+#[derive(Clone, Debug)]
+pub enum ScanEvent {
+    /// Probe has been sent to the destination
+    Probe(SocketAddr),
+    /// Host is up
+    Host(HostInfo),
+    /// Port status of the host
+    Port(IpAddr, PortInfo),
+    /// Non fatal error during the scan
+    Error(String),
+    /// Scan finished; carries the final result
+    Done(ScanResult),
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct ScanResults {
     pub(crate) result: ScanResult,
     pub(crate) ip_set: HashSet<IpAddr>,
-    pub(crate) socket_set: HashSet<SocketAddr>,
+    pub(crate) socket_set: HashSet<(SocketAddr, Protocol)>,
 }
 
 impl ScanResults {
@@ -124,4 +378,54 @@ impl ScanResults {
             socket_set: HashSet::new(),
         }
     }
+    /// Merge event into the result; returns false if it carries nothing new
+    #[allow(dead_code)]
+    pub(crate) fn apply(&mut self, event: &ScanEvent) -> bool {
+        match event {
+            ScanEvent::Host(host_info) => {
+                if self.ip_set.insert(host_info.ip_addr) {
+                    self.result.ips.push(*host_info);
+                    true
+                } else {
+                    false
+                }
+            }
+            ScanEvent::Port(ip_addr, port_info) => {
+                if self.socket_set.insert((
+                    SocketAddr::new(*ip_addr, port_info.port),
+                    port_info.protocol,
+                )) {
+                    self.result
+                        .ip_with_port
+                        .entry(*ip_addr)
+                        .or_default()
+                        .push(port_info.clone());
+                    true
+                } else {
+                    false
+                }
+            }
+            ScanEvent::Error(e) => {
+                self.result.errors.push(e.clone());
+                true
+            }
+            _ => true,
+        }
+    }
+    /// Events for the probed UDP ports that got no answer
+    ///
+    /// Silence is all a UDP scan gets from open ports and from filtered ones alike.
+    /// Ports whose probe was never sent are left out.
+    pub(crate) fn unanswered_udp(&self, probed: &[SocketAddr]) -> Vec<ScanEvent> {
+        probed
+            .iter()
+            .filter(|socket_addr| !self.socket_set.contains(&(**socket_addr, Protocol::Udp)))
+            .map(|socket_addr| {
+                ScanEvent::Port(
+                    socket_addr.ip(),
+                    PortInfo::new(socket_addr.port(), Protocol::Udp, PortStatus::OpenFiltered),
+                )
+            })
+            .collect()
+    }
 }
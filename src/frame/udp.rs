@@ -0,0 +1,201 @@
+use super::connect::{backoff, is_exhausted, CONNECT_RETRIES};
+use super::result::{PortStatus, ScanCounters};
+use super::Destination;
+use pnet_packet::Packet;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Poll interval while waiting for answers to UDP probes
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// UDP socket addresses of the destinations
+pub(crate) fn udp_targets(destinations: &[Destination]) -> Vec<SocketAddr> {
+    destinations
+        .iter()
+        .flat_map(|dst| {
+            dst.dst_udp_ports
+                .iter()
+                .map(move |port| SocketAddr::new(dst.dst_ip, *port))
+        })
+        .collect()
+}
+
+/// Unspecified local address of the same family as the destination
+pub(crate) fn bind_addr(socket_addr: &SocketAddr) -> SocketAddr {
+    match socket_addr {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    }
+}
+
+/// Classify the read on a connected UDP socket
+///
+/// A datagram means Open; ICMP port unreachable surfaces as ECONNREFUSED
+/// (WSAECONNRESET on windows) and means Closed; no answer means Open|Filtered;
+/// any other ICMP error means Filtered.
+pub(crate) fn status_of<T>(result: &io::Result<T>) -> PortStatus {
+    match result {
+        Ok(_) => PortStatus::Open,
+        Err(e) => match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => PortStatus::OpenFiltered,
+            io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => PortStatus::Closed,
+            _ => PortStatus::Filtered,
+        },
+    }
+}
+
+/// Status of a UDP port from the code of an ICMP destination unreachable
+fn unreachable_status_v4(code: u8) -> Option<PortStatus> {
+    match code {
+        3 => Some(PortStatus::Closed),
+        0 | 1 | 2 | 9 | 10 | 13 => Some(PortStatus::Filtered),
+        _ => None,
+    }
+}
+
+/// Status of a UDP port from the code of an ICMPv6 destination unreachable
+fn unreachable_status_v6(code: u8) -> Option<PortStatus> {
+    match code {
+        4 => Some(PortStatus::Closed),
+        0 | 1 | 3 => Some(PortStatus::Filtered),
+        _ => None,
+    }
+}
+
+/// Parse ICMP destination unreachable quoting a UDP datagram
+///
+/// Returns destination and source port of the quoted datagram and the port status.
+pub(crate) fn parse_icmpv4_error(icmp: &[u8]) -> Option<(SocketAddr, u16, PortStatus)> {
+    let icmp_packet = pnet_packet::icmp::IcmpPacket::new(icmp)?;
+    if icmp_packet.get_icmp_type() != pnet_packet::icmp::IcmpTypes::DestinationUnreachable {
+        return None;
+    }
+    let status = unreachable_status_v4(icmp_packet.get_icmp_code().0)?;
+    // 4 unused bytes precede the quoted header.
+    let inner = pnet_packet::ipv4::Ipv4Packet::new(icmp_packet.payload().get(4..)?)?;
+    if inner.get_next_level_protocol() != pnet_packet::ip::IpNextHeaderProtocols::Udp {
+        return None;
+    }
+    let header_len = inner.get_header_length() as usize * 4;
+    let udp = pnet_packet::udp::UdpPacket::new(icmp_packet.payload().get(4 + header_len..)?)?;
+    Some((
+        SocketAddr::new(IpAddr::V4(inner.get_destination()), udp.get_destination()),
+        udp.get_source(),
+        status,
+    ))
+}
+
+/// Parse ICMPv6 destination unreachable quoting a UDP datagram
+///
+/// Returns destination and source port of the quoted datagram and the port status.
+pub(crate) fn parse_icmpv6_error(icmp: &[u8]) -> Option<(SocketAddr, u16, PortStatus)> {
+    let icmp_packet = pnet_packet::icmpv6::Icmpv6Packet::new(icmp)?;
+    if icmp_packet.get_icmpv6_type() != pnet_packet::icmpv6::Icmpv6Types::DestinationUnreachable {
+        return None;
+    }
+    let status = unreachable_status_v6(icmp_packet.get_icmpv6_code().0)?;
+    let inner = pnet_packet::ipv6::Ipv6Packet::new(icmp_packet.payload().get(4..)?)?;
+    if inner.get_next_header() != pnet_packet::ip::IpNextHeaderProtocols::Udp {
+        return None;
+    }
+    let udp = pnet_packet::udp::UdpPacket::new(inner.payload())?;
+    Some((
+        SocketAddr::new(IpAddr::V6(inner.get_destination()), udp.get_destination()),
+        udp.get_source(),
+        status,
+    ))
+}
+
+/// Send an empty datagram on a connected, non-blocking UDP socket
+pub(crate) fn udp_probe(socket_addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(bind_addr(&socket_addr))?;
+    socket.connect(socket_addr)?;
+    socket.set_nonblocking(true)?;
+    socket.send(&[])?;
+    Ok(socket)
+}
+
+/// Send a UDP probe, retried with back-off while local resources are exhausted
+///
+/// Counts the probe in `stats`. An error the target answered with at once(port
+/// unreachable) counts as sent and is left to `status_of`.
+pub(crate) fn try_udp_probe(
+    socket_addr: SocketAddr,
+    stats: &ScanCounters,
+) -> io::Result<UdpSocket> {
+    let mut retry = 0;
+    loop {
+        match udp_probe(socket_addr) {
+            Err(e) if is_exhausted(&e) => {
+                if retry >= CONNECT_RETRIES {
+                    stats.send_error();
+                    return Err(e);
+                }
+                stats.retry();
+                thread::sleep(backoff(retry));
+                retry += 1;
+            }
+            result => {
+                stats.probe_sent();
+                return result;
+            }
+        }
+    }
+}
+
+/// Blocking UDP scan over connected sockets
+///
+/// Probes go out in batches of `batch_size`; each batch waits up to `wait_time`
+/// for answers; `send_rate` paces the probes like the raw senders. `report`
+/// gets the status of every port, or the error if no probe could be sent to it.
+pub(crate) fn run_udp_probes<S, R>(
+    targets: &[SocketAddr],
+    batch_size: usize,
+    send_rate: Duration,
+    wait_time: Duration,
+    stats: &ScanCounters,
+    is_stopped: S,
+    mut report: R,
+) where
+    S: Fn() -> bool,
+    R: FnMut(SocketAddr, io::Result<PortStatus>),
+{
+    let mut buf = [0u8; 1500];
+    for batch in targets.chunks(batch_size.max(1)) {
+        let mut pending: Vec<(SocketAddr, UdpSocket)> = vec![];
+        for socket_addr in batch {
+            if is_stopped() {
+                break;
+            }
+            match try_udp_probe(*socket_addr, stats) {
+                Ok(socket) => pending.push((*socket_addr, socket)),
+                Err(e) if is_exhausted(&e) => report(*socket_addr, Err(e)),
+                Err(e) => report(*socket_addr, Ok(status_of::<()>(&Err(e)))),
+            }
+            thread::sleep(send_rate);
+        }
+        let deadline = Instant::now() + wait_time;
+        while !pending.is_empty() && Instant::now() < deadline && !is_stopped() {
+            pending.retain(|(socket_addr, socket)| {
+                let result = socket.recv(&mut buf);
+                match status_of(&result) {
+                    PortStatus::OpenFiltered => true,
+                    status => {
+                        report(*socket_addr, Ok(status));
+                        false
+                    }
+                }
+            });
+            thread::sleep(POLL_INTERVAL);
+        }
+        // Ports still waiting when the scan is stopped are left out.
+        if is_stopped() {
+            break;
+        }
+        for (socket_addr, _) in pending {
+            report(socket_addr, Ok(PortStatus::OpenFiltered));
+        }
+    }
+}
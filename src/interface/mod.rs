@@ -9,6 +9,8 @@ use win::get_interfaces;
 mod unix;
 #[cfg(any(target_os = "unix", target_os = "linux"))]
 use unix::get_interfaces;
+#[cfg(target_os = "linux")]
+use unix::{read_arp_entry, read_default_route};
 
 mod memalloc;
 /// Structure of Network Interface information
@@ -83,9 +85,37 @@ pub fn get_default_gateway() -> Result<Gateway, String> {
             }
         }
     }
+    #[cfg(target_os = "linux")]
+    if let Some((_, gateway_ip)) = read_default_route() {
+        return Ok(Gateway {
+            mac_addr: read_arp_entry(gateway_ip).unwrap_or(MacAddr::zero()),
+            ip_addr: IpAddr::V4(gateway_ip),
+        });
+    }
     Err(String::from("Default Gateway not found"))
 }
 
+/// Get source IP address the kernel selects to reach the destination
+pub fn get_route_source(dst_ip: IpAddr) -> Result<IpAddr, String> {
+    let bind_addr = match dst_ip {
+        IpAddr::V4(_) => "0.0.0.0:0",
+        IpAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(bind_addr).map_err(|e| e.to_string())?;
+    // Connecting a UDP socket only performs the route lookup, nothing is sent.
+    socket.connect((dst_ip, 9)).map_err(|e| e.to_string())?;
+    socket
+        .local_addr()
+        .map(|addr| addr.ip())
+        .map_err(|e| e.to_string())
+}
+
+/// Get resolved MAC address of a neighbour from the ARP cache(Linux only)
+#[cfg(target_os = "linux")]
+pub fn get_neighbour_macaddr(ip_addr: Ipv4Addr) -> Option<MacAddr> {
+    read_arp_entry(ip_addr)
+}
+
 /// Get IP address of the default Network Interface
 pub fn get_local_ipaddr() -> Result<IpAddr, String> {
     let socket = match UdpSocket::bind("0.0.0.0:0") {
@@ -1,6 +1,46 @@
 use super::Interface;
+#[cfg(target_os = "linux")]
+use super::MacAddr;
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::net::Ipv4Addr;
 
 pub(super) fn get_interfaces() -> Vec<Interface> {
     let interfaces = vec![];
     interfaces    
-}
\ No newline at end of file
+}
+
+/// Read default IPv4 route from /proc/net/route; returns interface name and gateway
+#[cfg(target_os = "linux")]
+pub(super) fn read_default_route() -> Option<(String, Ipv4Addr)> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+    for line in routes.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            continue;
+        }
+        // Addresses are printed as the raw network order u32 in host byte order.
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        return Some((fields[0].to_string(), Ipv4Addr::from(gateway.to_ne_bytes())));
+    }
+    None
+}
+
+/// Read resolved neighbour MAC address from /proc/net/arp
+#[cfg(target_os = "linux")]
+pub(super) fn read_arp_entry(ip_addr: Ipv4Addr) -> Option<MacAddr> {
+    let entries = fs::read_to_string("/proc/net/arp").ok()?;
+    for line in entries.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Flag 0x2 marks a completed entry.
+        if fields.len() < 4 || fields[0].parse::<Ipv4Addr>().ok()? != ip_addr {
+            continue;
+        }
+        let flags = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16).unwrap_or(0);
+        if flags & 0x2 != 0 {
+            return Some(MacAddr::from_hex_format(fields[3]));
+        }
+    }
+    None
+}
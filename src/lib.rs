@@ -128,6 +128,57 @@
 #[cfg_attr(doc_cfg, doc(cfg(feature = "async")))]
 pub mod async_scan;
 
+/// Tokio native Scanner
+/// # Example
+/// ```
+/// fn main() -> Result<(), String> {
+/// #[cfg(feature = "tokio")]
+/// {
+///     use e_libscanner::frame::result::ScanEvent;
+///     use e_libscanner::{tokio_scan, Opts};
+///     use tokio_stream::StreamExt;
+///     // more command information use: -h
+///     let scanner = Opts::new(Some(&[
+///         "e-libscanner",
+///         "--ips",
+///         "192.168.1.1",
+///         "--ports",
+///         "22",
+///         "80",
+///         "443",
+///         "--model",
+///         "tokio",
+///         "--scan",
+///         "TcpConnect",
+///         "--no-gui",
+///     ]))?
+///     .init()?
+///     .downcast::<tokio_scan::Scanner>()
+///     .unwrap();
+///     let rt = tokio::runtime::Builder::new_current_thread()
+///         .enable_all()
+///         .build()
+///         .unwrap();
+///     rt.block_on(async {
+///         let mut events = scanner.scan_stream(None)?;
+///         while let Some(event) = events.next().await {
+///             match event {
+///                 ScanEvent::Host(host) => println!("Host: {}", host),
+///                 ScanEvent::Port(ip, port) => println!("Port: {} {}", ip, port),
+///                 ScanEvent::Done(result) => println!("Scan Time: {:?}", result.scan_time),
+///                 _ => {}
+///             }
+///         }
+///         Ok::<(), String>(())
+///     })?;
+/// }
+/// Ok(())
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "tokio")))]
+pub mod tokio_scan;
+
 /// Host Scanner
 /// # Examples
 /// ```
@@ -1,6 +1,9 @@
+use crate::data::Protocol;
+use crate::frame::result::{PortStatus, ScanResult};
 use std::net::IpAddr;
 use std::time::Duration;
 use pnet_datalink::MacAddr;
+use serde_derive::{Deserialize, Serialize};
 
 /// Probes for fingerprinting
 #[doc(hidden)]
@@ -18,7 +21,7 @@ pub enum ProbeType {
 
 /// TCP Options
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum TcpOptionKind {
     Eol,
     Nop,
@@ -65,6 +68,38 @@ pub struct ProbeTarget {
     pub closed_udp_port: u16,
 }
 
+impl ProbeTarget {
+    /// Probe targets for the hosts with open TCP ports of a scan result
+    ///
+    /// Ports the result does not know fall back to the defaults of the os model.
+    pub fn from_result(result: &ScanResult) -> Vec<ProbeTarget> {
+        let mut ips: Vec<&IpAddr> = result.ip_with_port.keys().collect();
+        ips.sort();
+        ips.into_iter()
+            .filter_map(|ip| {
+                let ports = &result.ip_with_port[ip];
+                let find = |protocol: Protocol, status: PortStatus| {
+                    ports
+                        .iter()
+                        .find(|p| p.protocol == protocol && p.status == status)
+                        .map(|p| p.port)
+                };
+                let open_tcp_ports = result.get_open_ports(*ip);
+                if open_tcp_ports.is_empty() {
+                    return None;
+                }
+                Some(ProbeTarget {
+                    ip_addr: *ip,
+                    open_tcp_ports,
+                    closed_tcp_port: find(Protocol::Tcp, PortStatus::Closed).unwrap_or(443),
+                    open_udp_port: find(Protocol::Udp, PortStatus::Open).unwrap_or(123),
+                    closed_udp_port: find(Protocol::Udp, PortStatus::Closed).unwrap_or(33455),
+                })
+            })
+            .collect()
+    }
+}
+
 #[allow(unreachable_pub)]
 #[derive(Clone, Debug)]
 pub(crate) struct ProbeSetting {
@@ -1,9 +1,10 @@
 use super::frame::{ProbeType, TcpOptionKind};
+use serde_derive::{Deserialize, Serialize};
 use std::{fmt, net::IpAddr};
 
 /// Icmp echo result model
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct IcmpEchoResult {
     pub icmp_echo_reply: bool,
     pub icmp_echo_code: u8,
@@ -26,7 +27,7 @@ impl IcmpEchoResult {
 
 /// Icmp timestamp result model
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct IcmpTimestampResult {
     pub icmp_timestamp_reply: bool,
     pub ip_id: u16,
@@ -45,7 +46,7 @@ impl IcmpTimestampResult {
 
 /// Icmp address mask result
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct IcmpAddressMaskResult {
     pub icmp_address_mask_reply: bool,
     pub ip_id: u16,
@@ -64,7 +65,7 @@ impl IcmpAddressMaskResult {
 
 /// Icmp information result
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct IcmpInformationResult {
     pub icmp_information_reply: bool,
     pub ip_id: u16,
@@ -83,7 +84,7 @@ impl IcmpInformationResult {
 
 /// Icmp unreachable ip result
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct IcmpUnreachableIpResult {
     pub icmp_unreachable_reply: bool,
     pub icmp_unreachable_size: u16,
@@ -108,7 +109,7 @@ impl IcmpUnreachableIpResult {
 
 /// Icmp unreachable original data result
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
 pub struct IcmpUnreachableOriginalDataResult {
     pub udp_checksum: u16,
     pub udp_header_length: u16,
@@ -121,7 +122,7 @@ pub struct IcmpUnreachableOriginalDataResult {
 }
 
 #[doc(hidden)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TcpHeaderResult {
     pub tcp_window_size: u16,
     pub tcp_option_order: Vec<TcpOptionKind>,
@@ -136,7 +137,7 @@ impl TcpHeaderResult {
 }
 
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct TcpSynAckResult {
     pub syn_ack_response: bool,
     pub ip_id: u16,
@@ -156,7 +157,7 @@ impl TcpSynAckResult {
 }
 
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct TcpRstAckResult {
     pub rst_ack_response: bool,
     pub tcp_payload_size: u16,
@@ -178,7 +179,7 @@ impl TcpRstAckResult {
 }
 
 #[doc(hidden)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct TcpEcnResult {
     pub syn_ack_ece_response: bool,
     pub tcp_payload_size: u16,
@@ -200,7 +201,7 @@ impl TcpEcnResult {
 }
 
 #[doc(hidden)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProbeResult {
     pub ip_addr: IpAddr,
     pub icmp_echo_result: Option<IcmpEchoResult>,
@@ -1,5 +1,7 @@
+use crate::frame::result::ScanResult;
 use native_tls::TlsConnector;
 use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::io::{prelude::*, BufReader, BufWriter};
@@ -86,6 +88,20 @@ impl ServiceDetector {
             accept_invalid_certs: false,
         }
     }
+    /// Service detectors for the hosts with open TCP ports of a scan result
+    pub fn from_result(result: &ScanResult) -> Vec<ServiceDetector> {
+        let mut ips: Vec<&IpAddr> = result.ip_with_port.keys().collect();
+        ips.sort();
+        ips.into_iter()
+            .map(|ip| {
+                let mut detector = ServiceDetector::new();
+                detector.set_dst_ip(*ip);
+                detector.set_open_ports(result.get_open_ports(*ip));
+                detector
+            })
+            .filter(|detector| !detector.open_ports.is_empty())
+            .collect()
+    }
     /// Set Destination IP address
     pub fn set_dst_ip(&mut self, dst_ip: IpAddr) {
         self.dst_ip = dst_ip;
@@ -270,7 +286,7 @@ impl PortDatabase {
 
 /// Service Result
 #[doc(hidden)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanServiceResult {
     pub dst_ip: IpAddr,
     pub dst_name: String,
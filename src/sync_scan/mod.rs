@@ -10,6 +10,8 @@ mod receiver;
 mod unix;
 #[cfg(not(target_os="windows"))]
 use unix::*;
+#[cfg(target_os="linux")]
+mod unprivileged;
 
 #[cfg(target_os="windows")]
 #[path = "./windows.rs"]
@@ -1,7 +1,7 @@
-use crate::data::DATA;
+use crate::data::Protocol;
 use crate::frame::{
-    result::{HostInfo, PortInfo, PortStatus, ScanResults},
-    ScanSetting, ScanType,
+    result::{HostInfo, PortInfo, PortStatus, ScanEvent, ScanResults},
+    udp, ScanSetting, ScanType,
 };
 use pnet_packet::Packet;
 use std::net::{IpAddr, SocketAddr};
@@ -18,6 +18,7 @@ pub(crate) fn receive_packets(
     loop {
         match rx.next() {
             Ok(frame) => {
+                scan_setting.stats.frame_received();
                 let frame = pnet_packet::ethernet::EthernetPacket::new(frame).unwrap();
                 match frame.get_ethertype() {
                     pnet_packet::ethernet::EtherTypes::Ipv4 => {
@@ -46,6 +47,15 @@ fn ipv4_handler(
     scan_result: &Arc<Mutex<ScanResults>>,
 ) {
     if let Some(packet) = pnet_packet::ipv4::Ipv4Packet::new(ethernet.payload()) {
+        // ICMP errors about UDP probes may come from any router on the path
+        if let (ScanType::TcpSynScan, pnet_packet::ip::IpNextHeaderProtocols::Icmp) =
+            (&scan_setting.scan_type, packet.get_next_level_protocol())
+        {
+            if let Some(error) = udp::parse_icmpv4_error(packet.payload()) {
+                handle_udp_error(error, scan_setting, scan_result);
+                return;
+            }
+        }
         if scan_setting
             .ip_set
             .contains(&IpAddr::V4(packet.get_source()))
@@ -72,6 +82,15 @@ fn ipv6_handler(
     scan_result: &Arc<Mutex<ScanResults>>,
 ) {
     if let Some(packet) = pnet_packet::ipv6::Ipv6Packet::new(ethernet.payload()) {
+        // ICMP errors about UDP probes may come from any router on the path
+        if let (ScanType::TcpSynScan, pnet_packet::ip::IpNextHeaderProtocols::Icmpv6) =
+            (&scan_setting.scan_type, packet.get_next_header())
+        {
+            if let Some(error) = udp::parse_icmpv6_error(packet.payload()) {
+                handle_udp_error(error, scan_setting, scan_result);
+                return;
+            }
+        }
         if scan_setting
             .ip_set
             .contains(&IpAddr::V6(packet.get_source()))
@@ -129,7 +148,7 @@ fn udp_handler_v4(
 ) {
     let udp = pnet_packet::udp::UdpPacket::new(packet.payload());
     if let Some(udp) = udp {
-        handle_udp_packet(udp, &scan_setting, scan_result);
+        handle_udp_packet(udp, IpAddr::V4(packet.get_source()), scan_setting, scan_result);
     }
 }
 
@@ -140,23 +159,24 @@ fn udp_handler_v6(
 ) {
     let udp = pnet_packet::udp::UdpPacket::new(packet.payload());
     if let Some(udp) = udp {
-        handle_udp_packet(udp, &scan_setting, scan_result);
+        handle_udp_packet(udp, IpAddr::V6(packet.get_source()), scan_setting, scan_result);
     }
 }
 
 fn icmp_handler_v4(
     packet: &pnet_packet::ipv4::Ipv4Packet<'_>,
-    _scan_setting: &ScanSetting,
+    scan_setting: &ScanSetting,
     scan_result: &Arc<Mutex<ScanResults>>,
 ) {
     let icmp_packet = pnet_packet::icmp::IcmpPacket::new(packet.payload());
     if let Some(_icmp) = icmp_packet {
-        if !scan_result
+        let new = !scan_result
             .lock()
             .unwrap()
             .ip_set
-            .contains(&IpAddr::V4(packet.get_source()))
-        {
+            .contains(&IpAddr::V4(packet.get_source()));
+        scan_setting.stats.frame_matched(new);
+        if new {
             scan_result.lock().unwrap().result.ips.push(HostInfo {
                 ip_addr: IpAddr::V4(packet.get_source()),
                 ttl: packet.get_ttl(),
@@ -172,17 +192,18 @@ fn icmp_handler_v4(
 
 fn icmp_handler_v6(
     packet: &pnet_packet::ipv6::Ipv6Packet<'_>,
-    _scan_setting: &ScanSetting,
+    scan_setting: &ScanSetting,
     scan_result: &Arc<Mutex<ScanResults>>,
 ) {
     let icmp_packet = pnet_packet::icmp::IcmpPacket::new(packet.payload());
     if let Some(_icmp) = icmp_packet {
-        if !scan_result
+        let new = !scan_result
             .lock()
             .unwrap()
             .ip_set
-            .contains(&IpAddr::V6(packet.get_source()))
-        {
+            .contains(&IpAddr::V6(packet.get_source()));
+        scan_setting.stats.frame_matched(new);
+        if new {
             scan_result.lock().unwrap().result.ips.push(HostInfo {
                 ip_addr: IpAddr::V6(packet.get_source()),
                 ttl: packet.get_hop_limit(),
@@ -206,17 +227,15 @@ fn handle_tcp_packet(
     if tcp_packet.get_flags() == pnet_packet::tcp::TcpFlags::SYN | pnet_packet::tcp::TcpFlags::ACK {
         match scan_setting.scan_type {
             ScanType::TcpSynScan => {
-                if !scan_result
+                let new = !scan_result
                     .lock()
                     .unwrap()
                     .socket_set
-                    .contains(&socket_addr)
-                {
-                    let port_info = PortInfo {
-                        port: socket_addr.port(),
-                        status: PortStatus::Open,
-                        describe: DATA.portmap.get(&socket_addr.port()).unwrap().to_string(),
-                    };
+                    .contains(&(socket_addr, Protocol::Tcp));
+                scan_setting.stats.frame_matched(new);
+                if new {
+                    let port_info =
+                        PortInfo::new(socket_addr.port(), Protocol::Tcp, PortStatus::Open);
                     // Avoid deadlock.
                     let exists: bool = if let Some(r) = scan_result
                         .lock()
@@ -238,16 +257,21 @@ fn handle_tcp_packet(
                             .ip_with_port
                             .insert(socket_addr.ip(), vec![port_info]);
                     }
-                    scan_result.lock().unwrap().socket_set.insert(socket_addr);
+                    scan_result
+                        .lock()
+                        .unwrap()
+                        .socket_set
+                        .insert((socket_addr, Protocol::Tcp));
                 }
             }
             _ => {
-                if !scan_result
+                let new = !scan_result
                     .lock()
                     .unwrap()
                     .ip_set
-                    .contains(&host_info.ip_addr)
-                {
+                    .contains(&host_info.ip_addr);
+                scan_setting.stats.frame_matched(new);
+                if new {
                     scan_result.lock().unwrap().result.ips.push(host_info);
                     match host_info.ip_addr {
                         IpAddr::V4(ip) => {
@@ -265,17 +289,15 @@ fn handle_tcp_packet(
     {
         match scan_setting.scan_type {
             ScanType::TcpSynScan => {
-                if !scan_result
+                let new = !scan_result
                     .lock()
                     .unwrap()
                     .socket_set
-                    .contains(&socket_addr)
-                {
-                    let port_info = PortInfo {
-                        port: socket_addr.port(),
-                        status: PortStatus::Closed,
-                        describe: DATA.portmap.get(&socket_addr.port()).unwrap().to_string(),
-                    };
+                    .contains(&(socket_addr, Protocol::Tcp));
+                scan_setting.stats.frame_matched(new);
+                if new {
+                    let port_info =
+                        PortInfo::new(socket_addr.port(), Protocol::Tcp, PortStatus::Closed);
                     // Avoid deadlock.
                     let exists: bool = if let Some(r) = scan_result
                         .lock()
@@ -297,16 +319,21 @@ fn handle_tcp_packet(
                             .ip_with_port
                             .insert(socket_addr.ip(), vec![port_info]);
                     }
-                    scan_result.lock().unwrap().socket_set.insert(socket_addr);
+                    scan_result
+                        .lock()
+                        .unwrap()
+                        .socket_set
+                        .insert((socket_addr, Protocol::Tcp));
                 }
             }
             _ => {
-                if !scan_result
+                let new = !scan_result
                     .lock()
                     .unwrap()
                     .ip_set
-                    .contains(&host_info.ip_addr)
-                {
+                    .contains(&host_info.ip_addr);
+                scan_setting.stats.frame_matched(new);
+                if new {
                     scan_result.lock().unwrap().result.ips.push(host_info);
                     match host_info.ip_addr {
                         IpAddr::V4(ip) => {
@@ -323,8 +350,35 @@ fn handle_tcp_packet(
 }
 
 fn handle_udp_packet(
-    _udp_packet: pnet_packet::udp::UdpPacket<'_>,
-    _scan_setting: &ScanSetting,
-    _scan_result: &Arc<Mutex<ScanResults>>,
+    udp_packet: pnet_packet::udp::UdpPacket<'_>,
+    ip_addr: IpAddr,
+    scan_setting: &ScanSetting,
+    scan_result: &Arc<Mutex<ScanResults>>,
+) {
+    // any answer to a UDP probe means the port is open
+    if let ScanType::TcpSynScan = scan_setting.scan_type {
+        if udp_packet.get_destination() == scan_setting.src_port {
+            let port_info = PortInfo::new(udp_packet.get_source(), Protocol::Udp, PortStatus::Open);
+            let new = scan_result
+                .lock()
+                .unwrap()
+                .apply(&ScanEvent::Port(ip_addr, port_info));
+            scan_setting.stats.frame_matched(new);
+        }
+    }
+}
+
+fn handle_udp_error(
+    (socket_addr, src_port, status): (SocketAddr, u16, PortStatus),
+    scan_setting: &ScanSetting,
+    scan_result: &Arc<Mutex<ScanResults>>,
 ) {
+    if src_port == scan_setting.src_port && scan_setting.ip_set.contains(&socket_addr.ip()) {
+        let port_info = PortInfo::new(socket_addr.port(), Protocol::Udp, status);
+        let new = scan_result
+            .lock()
+            .unwrap()
+            .apply(&ScanEvent::Port(socket_addr.ip(), port_info));
+        scan_setting.stats.frame_matched(new);
+    }
 }
@@ -1,8 +1,13 @@
 use super::scan_target;
-use crate::data::id::{DEFAULT_HOSTS_CONCURRENCY, DEFAULT_PORTS_CONCURRENCY, DEFAULT_SRC_PORT};
-use crate::frame::result::ScanResult;
+use crate::data::id::{
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_HOSTS_CONCURRENCY, DEFAULT_PORTS_CONCURRENCY, DEFAULT_SRC_PORT,
+};
+use crate::frame::result::{ScanCounters, ScanResult, ScanStats};
 use crate::frame::{result::ScanStatus, Destination, ScanSetting, ScanType};
 use crate::interface;
+use crate::netns;
+use crate::exclude::Exclusions;
+use crate::scope::{Scope, ScopeViolation};
 use std::collections::HashSet;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::mpsc::{channel, Receiver, Sender};
@@ -81,8 +86,22 @@ pub struct Scanner {
     pub wait_time: Duration,
     /// Packet sending interval(0 for unlimited)
     pub send_rate: Duration,
+    /// Timeout of a single TCP connection attempt
+    pub connect_timeout: Duration,
+    /// Scan without raw sockets(Linux only)
+    pub unprivileged: bool,
+    /// Network namespace the scan runs in(Linux only)
+    pub netns: Option<String>,
+    /// Authorized scope of destinations
+    pub scope: Option<Scope>,
+    /// Out of scope targets dropped before reaching the scanner
+    pub scope_violations: Vec<ScopeViolation>,
+    /// Hosts and ports left out of the scan
+    pub exclusions: Exclusions,
     /// Scan Result
     pub scan_result: ScanResult,
+    /// Counters of the running scan
+    pub stats: Arc<ScanCounters>,
     /// Sender for progress messaging
     pub tx: Arc<Mutex<Sender<SocketAddr>>>,
     /// Receiver for progress messaging
@@ -125,12 +144,27 @@ impl Scanner {
             timeout: Duration::from_millis(300_000),
             wait_time: Duration::from_millis(200),
             send_rate: Duration::from_millis(0),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            unprivileged: false,
+            netns: None,
+            scope: None,
+            scope_violations: vec![],
+            exclusions: Exclusions::new(),
             scan_result: ScanResult::new(),
+            stats: Arc::new(ScanCounters::new()),
             tx: Arc::new(Mutex::new(tx)),
             rx: Arc::new(Mutex::new(rx)),
         };
         Ok(scanner)
     }
+    /// Create new HostScanner inside the named network namespace(`/var/run/netns/<name>`)
+    ///
+    /// Interface and gateway lookup and the scan itself run inside the namespace. Linux only.
+    pub fn new_in_netns(src_ip: IpAddr, netns: &str) -> Result<Scanner, String> {
+        let mut scanner = netns::run_in(Some(netns), move || Scanner::new(src_ip))??;
+        scanner.netns = Some(netns.to_string());
+        Ok(scanner)
+    }
     /// get scan count
     pub fn len(&self) -> usize {
         let mut len = 0;
@@ -209,18 +243,90 @@ impl Scanner {
     pub fn get_send_rate(&self) -> Duration {
         self.send_rate.clone()
     }
+    /// Set timeout of a single TCP connection attempt
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+    /// Get timeout of a single TCP connection attempt
+    pub fn get_connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+    /// Set unprivileged mode
+    ///
+    /// Hosts are discovered with ICMP datagram sockets and connected UDP sockets,
+    /// port scans fall back to TCP connect. Linux only.
+    pub fn set_unprivileged(&mut self, unprivileged: bool) {
+        self.unprivileged = unprivileged;
+    }
+    /// Get unprivileged mode
+    pub fn get_unprivileged(&self) -> bool {
+        self.unprivileged
+    }
+    /// Get network namespace
+    pub fn get_netns(&self) -> Option<String> {
+        self.netns.clone()
+    }
+    /// Set authorized scope
+    ///
+    /// Out of scope destinations are skipped, in strict mode the scan fails without sending.
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = Some(scope);
+    }
+    /// Get authorized scope
+    pub fn get_scope(&self) -> Option<Scope> {
+        self.scope.clone()
+    }
+    /// Set out of scope targets dropped before reaching the scanner
+    ///
+    /// They are reported in the scan result along with the destinations refused by the scope.
+    pub fn set_scope_violations(&mut self, violations: Vec<ScopeViolation>) {
+        self.scope_violations = violations;
+    }
+    /// Get out of scope targets dropped before reaching the scanner
+    pub fn get_scope_violations(&self) -> Vec<ScopeViolation> {
+        self.scope_violations.clone()
+    }
+    /// Set hosts and ports left out of the scan
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) {
+        self.exclusions = exclusions;
+    }
+    /// Get hosts and ports left out of the scan
+    pub fn get_exclusions(&self) -> Exclusions {
+        self.exclusions.clone()
+    }
     /// Get scan result
     pub fn get_scan_result(&self) -> ScanResult {
         self.scan_result.clone()
     }
+    /// Get counters of the running scan, readable from other threads during `scan`
+    pub fn get_stats_counters(&self) -> Arc<ScanCounters> {
+        self.stats.clone()
+    }
+    /// Get current counters of the scan
+    pub fn get_stats(&self) -> ScanStats {
+        self.stats.snapshot()
+    }
     /// Get progress receiver
     pub fn get_progress_receiver(&self) -> Arc<Mutex<Receiver<SocketAddr>>> {
         self.rx.clone()
     }
     /// Run Scan
     pub fn run_scan(&mut self, pstop: Option<Arc<Mutex<bool>>>) {
+        self.stats.reset();
+        // out of scope and excluded destinations never reach the scan
+        let destinations = self.exclusions.filter_destinations(self.destinations.clone());
+        let (destinations, violations) = match &self.scope {
+            Some(scope) => match scope.filter_destinations(destinations) {
+                Ok(scoped) => scoped,
+                Err(e) => {
+                    self.scan_result = ScanResult::failed(e);
+                    return;
+                }
+            },
+            None => (destinations, vec![]),
+        };
         let mut ip_set: HashSet<IpAddr> = HashSet::new();
-        for dst in self.destinations.clone() {
+        for dst in destinations.iter() {
             ip_set.insert(dst.dst_ip);
         }
         let scan_setting: ScanSetting = ScanSetting {
@@ -229,7 +335,7 @@ impl Scanner {
             dst_mac: pnet_datalink::MacAddr::from(self.dst_mac),
             src_ip: self.src_ip.clone(),
             src_port: self.src_port.clone(),
-            destinations: self.destinations.clone(),
+            destinations,
             ip_set,
             timeout: self.timeout.clone(),
             wait_time: self.wait_time.clone(),
@@ -237,14 +343,36 @@ impl Scanner {
             scan_type: self.scan_type.clone(),
             hosts_concurrency: DEFAULT_HOSTS_CONCURRENCY,
             ports_concurrency: DEFAULT_PORTS_CONCURRENCY,
+            connect_timeout: self.connect_timeout,
+            unprivileged: self.unprivileged,
+            stats: Arc::clone(&self.stats),
         };
         let start_time = Instant::now();
-        let mut result: ScanResult = scan_target(scan_setting, &self.tx, pstop);
+        let ptx = Arc::clone(&self.tx);
+        let scan = move || scan_target(scan_setting, &ptx, pstop);
+        let mut result: ScanResult = match self.netns.as_deref() {
+            // rayon workers open sockets too, spawn them inside the namespace
+            Some(name) => netns::run_in(Some(name), move || {
+                rayon::ThreadPoolBuilder::new()
+                    .build()
+                    .map(|pool| pool.install(scan))
+                    .map_err(|e| e.to_string())
+            })
+            .and_then(|r| r)
+            .unwrap_or_else(ScanResult::failed),
+            None => scan(),
+        };
         result.scan_time = Instant::now().duration_since(start_time);
-        if result.scan_time > self.timeout {
-            result.scan_status = ScanStatus::Timeout;
-        } else {
-            result.scan_status = ScanStatus::Done;
+        result.stats = self.stats.snapshot();
+        result.scope_violations = self.scope_violations.clone();
+        result.scope_violations.extend(violations);
+        // Keep the error reported by the scan
+        if !matches!(result.scan_status, ScanStatus::Error) {
+            if result.scan_time > self.timeout {
+                result.scan_status = ScanStatus::Timeout;
+            } else {
+                result.scan_status = ScanStatus::Done;
+            }
         }
         self.scan_result = result;
     }
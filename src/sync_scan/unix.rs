@@ -1,9 +1,10 @@
 use super::receiver;
 use crate::{
-    data::DATA,
+    data,
     frame::{
-        result::{PortInfo, PortStatus, ScanResult, ScanResults},
-        ScanSetting, ScanType,
+        connect, datalink,
+        result::{PortInfo, ScanEvent, ScanResult, ScanResults},
+        udp, ScanSetting, ScanType,
     },
     packet,
 };
@@ -14,7 +15,7 @@ use std::{
     net::{IpAddr, SocketAddr},
     sync::{mpsc::Sender, Arc, Mutex},
     thread,
-    time::{Duration, Instant},
+    time::Instant,
 };
 
 fn build_icmpv4_echo_packet() -> Vec<u8> {
@@ -36,11 +37,8 @@ fn build_tcp_syn_packet(src_ip: IpAddr, src_port: u16, dst_ip: IpAddr, dst_port:
 }
 
 fn build_udp_packet(src_ip: IpAddr, src_port: u16, dst_ip: IpAddr, dst_port: u16) -> Vec<u8> {
-    let mut vec: Vec<u8> = vec![0; 66];
-    let mut udp_packet = pnet_packet::udp::MutableUdpPacket::new(
-        &mut vec[(packet::ethernet::ETHERNET_HEADER_LEN + packet::ipv4::IPV4_HEADER_LEN)..],
-    )
-    .unwrap();
+    let mut vec: Vec<u8> = vec![0; 8];
+    let mut udp_packet = pnet_packet::udp::MutableUdpPacket::new(&mut vec[..]).unwrap();
     packet::udp::build_udp_packet(&mut udp_packet, src_ip, src_port, dst_ip, dst_port);
     udp_packet.packet().to_vec()
 }
@@ -54,10 +52,7 @@ fn send_icmp_echo_packets(
         let socket_addr = SocketAddr::new(dst.dst_ip, 0);
         let sock_addr = SockAddr::from(socket_addr);
         let mut icmp_packet: Vec<u8> = build_icmpv4_echo_packet();
-        match socket.send_to(&mut icmp_packet, &sock_addr) {
-            Ok(_) => {}
-            Err(_) => {}
-        }
+        scan_setting.stats.sent(&socket.send_to(&mut icmp_packet, &sock_addr));
         match ptx.lock() {
             Ok(lr) => match lr.send(socket_addr) {
                 Ok(_) => {}
@@ -80,10 +75,7 @@ fn send_tcp_syn_packets(
             let sock_addr = SockAddr::from(socket_addr);
             let mut tcp_packet: Vec<u8> =
                 build_tcp_syn_packet(scan_setting.src_ip, scan_setting.src_port, dst.dst_ip, port);
-            match socket.send_to(&mut tcp_packet, &sock_addr) {
-                Ok(_) => {}
-                Err(_) => {}
-            }
+            scan_setting.stats.sent(&socket.send_to(&mut tcp_packet, &sock_addr));
             match ptx.lock() {
                 Ok(lr) => match lr.send(socket_addr) {
                     Ok(_) => {}
@@ -107,10 +99,7 @@ fn send_udp_packets(
             let sock_addr = SockAddr::from(socket_addr);
             let mut udp_packet: Vec<u8> =
                 build_udp_packet(scan_setting.src_ip, scan_setting.src_port, dst.dst_ip, port);
-            match socket.send_to(&mut udp_packet, &sock_addr) {
-                Ok(_) => {}
-                Err(_) => {}
-            }
+            scan_setting.stats.sent(&socket.send_to(&mut udp_packet, &sock_addr));
             match ptx.lock() {
                 Ok(lr) => match lr.send(socket_addr) {
                     Ok(_) => {}
@@ -123,57 +112,36 @@ fn send_udp_packets(
     }
 }
 
-fn run_connect_scan(
+pub(crate) fn run_connect_scan(
     scan_setting: ScanSetting,
     scan_result: &Arc<Mutex<ScanResults>>,
     pstop: &Arc<Mutex<bool>>,
 ) {
     let start_time = Instant::now();
-    let conn_timeout = Duration::from_millis(200);
     for dst in scan_setting.destinations.clone() {
-        if pstop.lock().unwrap() {
+        if *pstop.lock().unwrap() {
             break;
         } else {
             let ip_addr: IpAddr = dst.dst_ip;
             dst.dst_ports.into_par_iter().for_each(|port| {
-                let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP)).unwrap();
                 let socket_addr: SocketAddr = SocketAddr::new(ip_addr, port);
-                let sock_addr = SockAddr::from(socket_addr);
-                match socket.connect_timeout(&sock_addr, conn_timeout) {
-                    Ok(_) => {
-                        let port_info = PortInfo {
-                            port: socket_addr.port(),
-                            status: PortStatus::Open,
-                            describe: DATA.portmap.get(&socket_addr.port()).unwrap().to_string(),
-                        };
-                        // Avoid deadlock.
-                        let exists: bool = if let Some(r) = scan_result
-                            .lock()
-                            .unwrap()
-                            .result
-                            .ip_with_port
-                            .get_mut(&socket_addr.ip())
-                        {
-                            r.push(port_info.clone());
-                            true
-                        } else {
-                            false
-                        };
-                        if !exists {
-                            scan_result
-                                .lock()
-                                .unwrap()
-                                .result
-                                .ip_with_port
-                                .insert(socket_addr.ip(), vec![port_info]);
-                        }
-                    }
-                    Err(_) => {}
+                // Ports are skipped when local sockets stay exhausted after all retries.
+                if let Ok(status) = connect::try_connect(
+                    socket_addr,
+                    scan_setting.connect_timeout,
+                    &scan_setting.stats,
+                ) {
+                    let port_info =
+                        PortInfo::new(socket_addr.port(), data::Protocol::Tcp, status);
+                    scan_result
+                        .lock()
+                        .unwrap()
+                        .apply(&ScanEvent::Port(ip_addr, port_info));
                 }
                 if Instant::now().duration_since(start_time) > scan_setting.timeout {
-                    *stop.lock().unwrap() = true;
+                    *pstop.lock().unwrap() = true;
                     return;
-                } else if pstop.lock().unwrap() {
+                } else if *pstop.lock().unwrap() {
                     return;
                 } else {
                     thread::sleep(scan_setting.send_rate);
@@ -181,6 +149,38 @@ fn run_connect_scan(
             });
         }
     }
+    run_udp_connect_scan(&scan_setting, scan_result, pstop, start_time);
+}
+
+/// UDP ports of a connect scan, probed over connected UDP sockets
+fn run_udp_connect_scan(
+    scan_setting: &ScanSetting,
+    scan_result: &Arc<Mutex<ScanResults>>,
+    pstop: &Arc<Mutex<bool>>,
+    start_time: Instant,
+) {
+    let targets = udp::udp_targets(&scan_setting.destinations);
+    udp::run_udp_probes(
+        &targets,
+        scan_setting.ports_concurrency,
+        scan_setting.send_rate,
+        scan_setting.wait_time,
+        &scan_setting.stats,
+        || {
+            *pstop.lock().unwrap()
+                || Instant::now().duration_since(start_time) > scan_setting.timeout
+        },
+        |socket_addr, status| {
+            let event = match status {
+                Ok(status) => ScanEvent::Port(
+                    socket_addr.ip(),
+                    PortInfo::new(socket_addr.port(), data::Protocol::Udp, status),
+                ),
+                Err(e) => ScanEvent::Error(format!("{}: {}", socket_addr, e)),
+            };
+            scan_result.lock().unwrap().apply(&event);
+        },
+    );
 }
 
 fn send_ping_packet(
@@ -204,18 +204,58 @@ fn send_ping_packet(
     }
 }
 
+/// Send raw UDP probes, returns the probes that were sent
+fn send_udp_port_packets(
+    socket: &Socket,
+    scan_setting: &ScanSetting,
+    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+) -> Vec<SocketAddr> {
+    let mut probed = vec![];
+    for socket_addr in udp::udp_targets(&scan_setting.destinations) {
+        let sock_addr = SockAddr::from(socket_addr);
+        let udp_packet: Vec<u8> = build_udp_packet(
+            scan_setting.src_ip,
+            scan_setting.src_port,
+            socket_addr.ip(),
+            socket_addr.port(),
+        );
+        let sent = socket.send_to(&udp_packet, &sock_addr);
+        scan_setting.stats.sent(&sent);
+        if sent.is_ok() {
+            probed.push(socket_addr);
+        }
+        if let Ok(lr) = ptx.lock() {
+            let _ = lr.send(socket_addr);
+        }
+        thread::sleep(scan_setting.send_rate);
+    }
+    probed
+}
+
+/// Send SYN probes, then raw UDP probes of the UDP ports
+///
+/// Returns the UDP probes that were sent, or the error of the raw UDP socket.
 fn send_tcp_packets(
     socket: &Socket,
     scan_setting: &ScanSetting,
     ptx: &Arc<Mutex<Sender<SocketAddr>>>,
-) {
+) -> Result<Vec<SocketAddr>, String> {
     match scan_setting.scan_type {
         ScanType::TcpSynScan => {
             send_tcp_syn_packets(socket, scan_setting, ptx);
+            // UDP ports share the receiver with the SYN scan
+            if scan_setting.destinations.iter().any(|dst| !dst.dst_udp_ports.is_empty()) {
+                let domain = match scan_setting.src_ip {
+                    IpAddr::V4(_) => Domain::IPV4,
+                    IpAddr::V6(_) => Domain::IPV6,
+                };
+                let udp_socket = Socket::new(domain, Type::RAW, Some(Protocol::UDP))
+                    .map_err(|e| format!("raw UDP socket: {}", e))?;
+                return Ok(send_udp_port_packets(&udp_socket, scan_setting, ptx));
+            }
+            Ok(vec![])
         }
-        _ => {
-            return;
-        }
+        _ => Ok(vec![]),
     }
 }
 
@@ -224,6 +264,11 @@ pub(crate) fn scan_target(
     ptx: &Arc<Mutex<Sender<SocketAddr>>>,
     pstop: Option<Arc<Mutex<bool>>>,
 ) -> ScanResult {
+    #[cfg(target_os = "linux")]
+    if scan_setting.unprivileged {
+        let stop = pstop.unwrap_or_else(|| Arc::new(Mutex::new(false)));
+        return super::unprivileged::scan_target(scan_setting, ptx, &stop);
+    }
     let socket = match scan_setting.src_ip {
         IpAddr::V4(_) => match scan_setting.scan_type {
             ScanType::IcmpPingScan => {
@@ -281,9 +326,8 @@ pub(crate) fn scan_target(
         linux_fanout: None,
         promiscuous: false,
     };
-    let (mut _tx, mut rx) = match pnet_datalink::channel(&interface, config) {
-        Ok(pnet_datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
-        Ok(_) => panic!("Unknown channel type"),
+    let (mut rx, drops) = match datalink::receiver(&interface, config) {
+        Ok(receiver) => receiver,
         Err(e) => panic!("Error happened {}", e),
     };
     let scan_result: Arc<Mutex<ScanResults>> = Arc::new(Mutex::new(ScanResults::new()));
@@ -295,10 +339,16 @@ pub(crate) fn scan_target(
     let receive_stop = Arc::clone(&stop);
     let receive_result: Arc<Mutex<ScanResults>> = Arc::clone(&scan_result);
     let receive_setting: ScanSetting = scan_setting.clone();
+    let stats = Arc::clone(&scan_setting.stats);
     match scan_setting.scan_type {
         ScanType::IcmpPingScan | ScanType::UdpPingScan | ScanType::TcpPingScan => {
             thread::spawn(move || {
-                receiver::receive_packets(&mut rx, receive_setting, &receive_result, &receive_stop);
+                receiver::receive_packets(
+                    &mut rx,
+                    receive_setting,
+                    &receive_result,
+                    &receive_stop,
+                );
             });
             send_ping_packet(&socket, &scan_setting, ptx);
             thread::sleep(scan_setting.wait_time);
@@ -306,16 +356,41 @@ pub(crate) fn scan_target(
         }
         ScanType::TcpSynScan => {
             thread::spawn(move || {
-                receiver::receive_packets(&mut rx, receive_setting, &receive_result, &receive_stop);
+                receiver::receive_packets(
+                    &mut rx,
+                    receive_setting,
+                    &receive_result,
+                    &receive_stop,
+                );
             });
-            send_tcp_packets(&socket, &scan_setting, ptx);
+            let start_time = Instant::now();
+            let probed = match send_tcp_packets(&socket, &scan_setting, ptx) {
+                Ok(probed) => probed,
+                Err(e) => {
+                    // UDP ports are probed over connected UDP sockets instead
+                    scan_result.lock().unwrap().result.warnings.push(format!(
+                        "{}, UDP ports probed over connected UDP sockets",
+                        e
+                    ));
+                    run_udp_connect_scan(&scan_setting, &scan_result, &stop, start_time);
+                    vec![]
+                }
+            };
             thread::sleep(scan_setting.wait_time);
             *stop.lock().unwrap() = true;
+            let mut results = scan_result.lock().unwrap();
+            for event in results.unanswered_udp(&probed) {
+                results.apply(&event);
+            }
         }
         ScanType::TcpConnectScan => {
             run_connect_scan(scan_setting, &receive_result, &stop);
         }
     }
+    // read once the receiver is stopped, the kernel zeroes the count on every read
+    if let Some(drops) = &drops {
+        stats.add_dropped(drops.take());
+    }
     let result: ScanResult = scan_result.lock().unwrap().result.clone();
     return result;
 }
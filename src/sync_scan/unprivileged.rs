@@ -0,0 +1,423 @@
+//! Scans that work without CAP_NET_RAW on Linux
+//!
+//! Host discovery uses ICMP datagram sockets(allowed by `net.ipv4.ping_group_range`),
+//! port scans fall back to TCP connect and UDP probes use connected sockets with
+//! `IP_RECVERR` so the ICMP errors they trigger can be read back.
+use super::run_connect_scan;
+use crate::frame::{
+    connect::{self, TCP_PING_PORTS},
+    result::{HostInfo, PortStatus, ScanEvent, ScanResult, ScanResults, ScanStatus},
+    ScanSetting, ScanType,
+};
+use crate::packet;
+use crate::privilege;
+use pnet_packet::Packet;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io;
+use std::mem;
+use std::net::{IpAddr, SocketAddr};
+use std::os::raw::c_int;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Destination port of UDP probes when the destination has no ports
+const UDP_PROBE_PORT: u16 = 33434;
+/// ICMP type and code of port unreachable
+const ICMP_PORT_UNREACHABLE: (u8, u8) = (3, 3);
+/// ICMPv6 type and code of port unreachable
+const ICMPV6_PORT_UNREACHABLE: (u8, u8) = (1, 4);
+/// Poll interval while waiting for answers
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// ICMP error queued on a socket with `IP_RECVERR`
+#[derive(Clone, Copy, Debug)]
+struct IcmpError {
+    icmp_type: u8,
+    icmp_code: u8,
+}
+
+/// Datagram read by `recv_msg`
+#[derive(Debug)]
+struct Received {
+    from: Option<SocketAddr>,
+    ttl: Option<u8>,
+    icmp_error: Option<IcmpError>,
+}
+
+/// Control message buffer, aligned for `cmsghdr`
+#[repr(C, align(8))]
+struct ControlBuf([u8; 256]);
+
+/// recvmsg(2) collecting the hop limit and extended ICMP errors
+fn recv_msg(socket: &Socket, buf: &mut [u8], flags: c_int) -> io::Result<Received> {
+    // SAFETY: sockaddr_storage and msghdr are plain C structs, all zero is valid.
+    let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let mut control = ControlBuf([0; 256]);
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut addr as *mut libc::sockaddr_storage as *mut libc::c_void;
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.0.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control.0.len() as _;
+    // SAFETY: every pointer in msg refers to a live buffer of the advertised size.
+    let n = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: recvmsg filled in at most msg_namelen bytes of addr.
+    let from = unsafe { SockAddr::new(addr, msg.msg_namelen) }.as_socket();
+    let mut received = Received {
+        from,
+        ttl: None,
+        icmp_error: None,
+    };
+    // SAFETY: msg was filled in by recvmsg, the CMSG macros stay inside msg_controllen.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg);
+            match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_TTL) | (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                    received.ttl = Some(ptr::read_unaligned(data as *const c_int) as u8);
+                }
+                (libc::IPPROTO_IP, libc::IP_RECVERR) | (libc::IPPROTO_IPV6, libc::IPV6_RECVERR) => {
+                    let err = ptr::read_unaligned(data as *const libc::sock_extended_err);
+                    if err.ee_origin == libc::SO_EE_ORIGIN_ICMP
+                        || err.ee_origin == libc::SO_EE_ORIGIN_ICMP6
+                    {
+                        received.icmp_error = Some(IcmpError {
+                            icmp_type: err.ee_type,
+                            icmp_code: err.ee_code,
+                        });
+                    }
+                }
+                _ => {}
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    Ok(received)
+}
+
+fn set_int_option(socket: &Socket, level: c_int, name: c_int) -> io::Result<()> {
+    let value: c_int = 1;
+    // SAFETY: value outlives the call and the length matches its type.
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const c_int as *const libc::c_void,
+            mem::size_of::<c_int>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn report_progress(ptx: &Arc<Mutex<Sender<SocketAddr>>>, socket_addr: SocketAddr) {
+    if let Ok(lr) = ptx.lock() {
+        let _ = lr.send(socket_addr);
+    }
+}
+
+fn is_stopped(scan_setting: &ScanSetting, start_time: Instant, stop: &Arc<Mutex<bool>>) -> bool {
+    *stop.lock().unwrap() || Instant::now().duration_since(start_time) > scan_setting.timeout
+}
+
+/// Open an ICMP datagram socket for the family
+fn icmp_socket(domain: Domain) -> io::Result<Socket> {
+    let socket = if domain == Domain::IPV4 {
+        let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::ICMPV4))?;
+        set_int_option(&socket, libc::IPPROTO_IP, libc::IP_RECVTTL)?;
+        socket
+    } else {
+        let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::ICMPV6))?;
+        set_int_option(&socket, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT)?;
+        socket
+    };
+    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+    Ok(socket)
+}
+
+fn build_echo_request(ip_addr: IpAddr) -> Vec<u8> {
+    match ip_addr {
+        IpAddr::V4(_) => {
+            let mut buf = [0u8; 16];
+            let mut icmp_packet =
+                pnet_packet::icmp::echo_request::MutableEchoRequestPacket::new(&mut buf[..])
+                    .unwrap();
+            packet::icmp::build_icmp_packet(&mut icmp_packet);
+            icmp_packet.packet().to_vec()
+        }
+        // Echo request; the kernel fills in identifier and checksum.
+        IpAddr::V6(_) => vec![128, 0, 0, 0, 0, 0, 0, 1],
+    }
+}
+
+/// ICMP echo host discovery over datagram sockets
+fn run_icmp_ping_scan(
+    scan_setting: &ScanSetting,
+    scan_result: &Arc<Mutex<ScanResults>>,
+    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    stop: &Arc<Mutex<bool>>,
+) -> io::Result<()> {
+    let start_time = Instant::now();
+    let mut sockets: Vec<(Domain, Arc<Socket>)> = vec![];
+    for dst in scan_setting.destinations.iter() {
+        let domain = Domain::for_address(SocketAddr::new(dst.dst_ip, 0));
+        if !sockets.iter().any(|(d, _)| *d == domain) {
+            sockets.push((domain, Arc::new(icmp_socket(domain)?)));
+        }
+    }
+    let sending = Arc::new(Mutex::new(true));
+    let receivers: Vec<thread::JoinHandle<()>> = sockets
+        .iter()
+        .map(|(_, socket)| {
+            let socket = Arc::clone(socket);
+            let scan_setting = scan_setting.clone();
+            let scan_result = Arc::clone(scan_result);
+            let stop = Arc::clone(stop);
+            let sending = Arc::clone(&sending);
+            thread::spawn(move || {
+                let mut buf = [0u8; 1500];
+                let mut deadline: Option<Instant> = None;
+                loop {
+                    if let Ok(received) = recv_msg(&socket, &mut buf, 0) {
+                        scan_setting.stats.frame_received();
+                        // Echo reply: type 0 for ICMP, 129 for ICMPv6.
+                        if buf[0] == 0 || buf[0] == 129 {
+                            if let Some(from) = received.from {
+                                if scan_setting.ip_set.contains(&from.ip()) {
+                                    let new = scan_result.lock().unwrap().apply(&ScanEvent::Host(
+                                        HostInfo {
+                                            ip_addr: from.ip(),
+                                            ttl: received.ttl.unwrap_or(0),
+                                        },
+                                    ));
+                                    scan_setting.stats.frame_matched(new);
+                                }
+                            }
+                        }
+                    }
+                    if deadline.is_none() && !*sending.lock().unwrap() {
+                        deadline = Some(Instant::now() + scan_setting.wait_time);
+                    }
+                    if deadline.map_or(false, |d| Instant::now() > d)
+                        || is_stopped(&scan_setting, start_time, &stop)
+                    {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+    for dst in scan_setting.destinations.iter() {
+        if is_stopped(scan_setting, start_time, stop) {
+            break;
+        }
+        let socket_addr = SocketAddr::new(dst.dst_ip, 0);
+        let domain = Domain::for_address(socket_addr);
+        if let Some((_, socket)) = sockets.iter().find(|(d, _)| *d == domain) {
+            scan_setting.stats.sent(&socket.send_to(
+                &build_echo_request(dst.dst_ip),
+                &SockAddr::from(socket_addr),
+            ));
+        }
+        report_progress(ptx, socket_addr);
+        thread::sleep(scan_setting.send_rate);
+    }
+    *sending.lock().unwrap() = false;
+    for receiver in receivers {
+        let _ = receiver.join();
+    }
+    Ok(())
+}
+
+/// TCP host discovery; any answer from the port, open or refused, means the host is up
+fn run_tcp_ping_scan(
+    scan_setting: &ScanSetting,
+    scan_result: &Arc<Mutex<ScanResults>>,
+    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    stop: &Arc<Mutex<bool>>,
+) {
+    let start_time = Instant::now();
+    for dst in scan_setting.destinations.iter() {
+        for port in connect::ping_ports(dst) {
+            if is_stopped(scan_setting, start_time, stop) {
+                return;
+            }
+            let socket_addr = SocketAddr::new(dst.dst_ip, *port);
+            let status = connect::try_connect(
+                socket_addr,
+                scan_setting.connect_timeout,
+                &scan_setting.stats,
+            );
+            report_progress(ptx, socket_addr);
+            if let Ok(PortStatus::Open) | Ok(PortStatus::Closed) = status {
+                scan_result
+                    .lock()
+                    .unwrap()
+                    .apply(&ScanEvent::Host(HostInfo {
+                        ip_addr: dst.dst_ip,
+                        ttl: 0,
+                    }));
+                break;
+            }
+            thread::sleep(scan_setting.send_rate);
+        }
+    }
+}
+
+/// Open a connected UDP socket reporting ICMP errors through its error queue
+fn udp_probe(socket_addr: SocketAddr) -> io::Result<Socket> {
+    let socket = Socket::new(
+        Domain::for_address(socket_addr),
+        Type::DGRAM,
+        Some(Protocol::UDP),
+    )?;
+    match socket_addr {
+        SocketAddr::V4(_) => set_int_option(&socket, libc::IPPROTO_IP, libc::IP_RECVERR)?,
+        SocketAddr::V6(_) => set_int_option(&socket, libc::IPPROTO_IPV6, libc::IPV6_RECVERR)?,
+    }
+    socket.connect(&SockAddr::from(socket_addr))?;
+    socket.set_nonblocking(true)?;
+    socket.send(&[])?;
+    Ok(socket)
+}
+
+/// Check the UDP probe; Some(true) if the host answered, Some(false) if it is unreachable
+fn poll_udp_probe(socket: &Socket, buf: &mut [u8]) -> Option<bool> {
+    match recv_msg(socket, buf, 0) {
+        Ok(_) => Some(true),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => None,
+        Err(_) => {
+            // The ICMP error that failed the read is waiting in the error queue.
+            let error = recv_msg(socket, buf, libc::MSG_ERRQUEUE)
+                .ok()
+                .and_then(|received| received.icmp_error)?;
+            let kind = (error.icmp_type, error.icmp_code);
+            Some(kind == ICMP_PORT_UNREACHABLE || kind == ICMPV6_PORT_UNREACHABLE)
+        }
+    }
+}
+
+/// UDP host discovery over connected sockets
+fn run_udp_ping_scan(
+    scan_setting: &ScanSetting,
+    scan_result: &Arc<Mutex<ScanResults>>,
+    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    stop: &Arc<Mutex<bool>>,
+) {
+    let start_time = Instant::now();
+    let targets: Vec<SocketAddr> = scan_setting
+        .destinations
+        .iter()
+        .flat_map(|dst| {
+            let ports = if dst.dst_ports.is_empty() {
+                vec![UDP_PROBE_PORT]
+            } else {
+                dst.dst_ports.clone()
+            };
+            ports
+                .into_iter()
+                .map(move |port| SocketAddr::new(dst.dst_ip, port))
+        })
+        .collect();
+    let mut buf = [0u8; 1500];
+    // Probes are sent in batches so the number of open sockets stays bounded.
+    for batch in targets.chunks(scan_setting.ports_concurrency.max(1)) {
+        let mut pending: Vec<(SocketAddr, Socket)> = vec![];
+        for socket_addr in batch {
+            if is_stopped(scan_setting, start_time, stop) {
+                break;
+            }
+            let probe = udp_probe(*socket_addr);
+            scan_setting.stats.sent(&probe);
+            if let Ok(socket) = probe {
+                pending.push((*socket_addr, socket));
+            }
+            report_progress(ptx, *socket_addr);
+            thread::sleep(scan_setting.send_rate);
+        }
+        let deadline = Instant::now() + scan_setting.wait_time;
+        while !pending.is_empty() && Instant::now() < deadline {
+            pending.retain(
+                |(socket_addr, socket)| match poll_udp_probe(socket, &mut buf) {
+                    Some(true) => {
+                        scan_result
+                            .lock()
+                            .unwrap()
+                            .apply(&ScanEvent::Host(HostInfo {
+                                ip_addr: socket_addr.ip(),
+                                ttl: 0,
+                            }));
+                        false
+                    }
+                    Some(false) => false,
+                    None => true,
+                },
+            );
+            thread::sleep(POLL_INTERVAL);
+        }
+        if is_stopped(scan_setting, start_time, stop) {
+            break;
+        }
+    }
+}
+
+pub(crate) fn scan_target(
+    scan_setting: ScanSetting,
+    ptx: &Arc<Mutex<Sender<SocketAddr>>>,
+    stop: &Arc<Mutex<bool>>,
+) -> ScanResult {
+    let scan_result: Arc<Mutex<ScanResults>> = Arc::new(Mutex::new(ScanResults::new()));
+    // ICMP datagram sockets are refused outside of ping_group_range
+    let icmp = privilege::has_icmp_datagram_socket();
+    let degraded = match scan_setting.scan_type {
+        ScanType::TcpSynScan | ScanType::TcpConnectScan => String::from("TcpConnectScan"),
+        ScanType::IcmpPingScan if icmp => String::from("ICMP datagram socket ping"),
+        ScanType::IcmpPingScan => format!(
+            "TCP connect ping on ports {:?}, ICMP datagram sockets are not permitted(net.ipv4.ping_group_range)",
+            TCP_PING_PORTS
+        ),
+        ScanType::TcpPingScan => String::from("TCP connect ping"),
+        ScanType::UdpPingScan => String::from("connected UDP socket ping"),
+    };
+    scan_result.lock().unwrap().result.warnings.push(format!(
+        "unprivileged mode, {:?} degraded to {}",
+        scan_setting.scan_type, degraded
+    ));
+    match scan_setting.scan_type {
+        ScanType::IcmpPingScan if !icmp => {
+            run_tcp_ping_scan(&scan_setting, &scan_result, ptx, stop)
+        }
+        ScanType::IcmpPingScan => {
+            if let Err(e) = run_icmp_ping_scan(&scan_setting, &scan_result, ptx, stop) {
+                let mut result = scan_result.lock().unwrap().result.clone();
+                result.scan_status = ScanStatus::Error;
+                result.errors.push(format!("ICMP datagram socket: {}", e));
+                return result;
+            }
+        }
+        ScanType::TcpPingScan => run_tcp_ping_scan(&scan_setting, &scan_result, ptx, stop),
+        ScanType::UdpPingScan => run_udp_ping_scan(&scan_setting, &scan_result, ptx, stop),
+        ScanType::TcpSynScan | ScanType::TcpConnectScan => {
+            run_connect_scan(scan_setting, &scan_result, stop)
+        }
+    }
+    let result: ScanResult = scan_result.lock().unwrap().result.clone();
+    result
+}
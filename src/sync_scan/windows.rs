@@ -1,20 +1,20 @@
 use super::receiver;
-use crate::data::DATA;
+use crate::data::Protocol;
 use crate::frame::{
-    result::{PortInfo, PortStatus, ScanResult, ScanResults},
-    ScanSetting, ScanType,
+    connect,
+    result::{PortInfo, ScanEvent, ScanResult, ScanResults},
+    udp, ScanSetting, ScanType,
 };
 use crate::packet;
 
 use pnet_packet::ethernet::EtherTypes;
 use pnet_packet::ip::IpNextHeaderProtocols;
 use rayon::prelude::*;
-use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use std::net::{IpAddr, SocketAddr};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 fn build_tcp_syn_packet(
     scan_setting: &ScanSetting,
@@ -158,13 +158,31 @@ fn build_icmp_echo_packet(scan_setting: &ScanSetting, tmp_packet: &mut [u8], dst
     packet::icmp::build_icmp_packet(&mut icmp_packet);
 }
 
+/// Count the outcome of a datalink send; None means no buffer was available
+///
+/// Returns true if the packet was sent.
+fn count_sent(scan_setting: &ScanSetting, sent: Option<std::io::Result<()>>) -> bool {
+    match sent {
+        Some(result) => {
+            scan_setting.stats.sent(&result);
+            result.is_ok()
+        }
+        None => {
+            scan_setting.stats.send_error();
+            false
+        }
+    }
+}
+
+/// Send the probes of the scan, returns the UDP probes of a SYN scan that were sent
 fn send_packets(
     tx: &mut Box<dyn pnet_datalink::DataLinkSender>,
     scan_setting: &ScanSetting,
     pstop: &Arc<Mutex<bool>>,
     ptx: &Arc<Mutex<Sender<SocketAddr>>>,
-) {
+) -> Vec<SocketAddr> {
     let mut stop = false;
+    let mut probed = vec![];
     match scan_setting.scan_type {
         ScanType::TcpSynScan | ScanType::TcpPingScan => {
             for dst in scan_setting.destinations.clone() {
@@ -173,9 +191,12 @@ fn send_packets(
                 } else {
                     let dst_ip: IpAddr = dst.dst_ip;
                     for port in dst.dst_ports {
-                        tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
-                            build_tcp_syn_packet(scan_setting, packet, dst_ip, port);
-                        });
+                        count_sent(
+                            scan_setting,
+                            tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
+                                build_tcp_syn_packet(scan_setting, packet, dst_ip, port);
+                            }),
+                        );
                         let socket_addr = SocketAddr::new(dst.dst_ip, port);
                         match ptx.lock() {
                             Ok(lr) => match lr.send(socket_addr) {
@@ -193,6 +214,30 @@ fn send_packets(
                     }
                 }
             }
+            // UDP ports share the receiver with the SYN scan
+            if let ScanType::TcpSynScan = scan_setting.scan_type {
+                for socket_addr in udp::udp_targets(&scan_setting.destinations) {
+                    if stop {
+                        break;
+                    }
+                    if count_sent(
+                        scan_setting,
+                        tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
+                            build_udp_packet(scan_setting, packet, socket_addr.ip(), socket_addr.port());
+                        }),
+                    ) {
+                        probed.push(socket_addr);
+                    }
+                    if let Ok(lr) = ptx.lock() {
+                        let _ = lr.send(socket_addr);
+                    }
+                    if *pstop.lock().unwrap() {
+                        stop = true;
+                    } else {
+                        thread::sleep(scan_setting.send_rate);
+                    }
+                }
+            }
         }
         ScanType::UdpPingScan => {
             for dst in scan_setting.destinations.clone() {
@@ -201,9 +246,12 @@ fn send_packets(
                 } else {
                     let dst_ip: IpAddr = dst.dst_ip;
                     for port in dst.dst_ports {
-                        tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
-                            build_udp_packet(scan_setting, packet, dst_ip, port);
-                        });
+                        count_sent(
+                            scan_setting,
+                            tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
+                                build_udp_packet(scan_setting, packet, dst_ip, port);
+                            }),
+                        );
                         let socket_addr = SocketAddr::new(dst.dst_ip, port);
                         match ptx.lock() {
                             Ok(lr) => match lr.send(socket_addr) {
@@ -224,9 +272,12 @@ fn send_packets(
         }
         ScanType::IcmpPingScan => {
             for dst in scan_setting.destinations.clone() {
-                tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
-                    build_icmp_echo_packet(scan_setting, packet, dst.dst_ip);
-                });
+                count_sent(
+                    scan_setting,
+                    tx.build_and_send(1, 66, &mut |packet: &mut [u8]| {
+                        build_icmp_echo_packet(scan_setting, packet, dst.dst_ip);
+                    }),
+                );
                 let socket_addr = SocketAddr::new(dst.dst_ip, 0);
                 match ptx.lock() {
                     Ok(lr) => match lr.send(socket_addr) {
@@ -246,6 +297,7 @@ fn send_packets(
     }
     thread::sleep(scan_setting.wait_time);
     *pstop.lock().unwrap() = true;
+    probed
 }
 
 fn run_connect_scan(
@@ -255,46 +307,24 @@ fn run_connect_scan(
     ptx: &Arc<Mutex<Sender<SocketAddr>>>,
 ) {
     let start_time = Instant::now();
-    let conn_timeout = Duration::from_millis(200);
     for dst in scan_setting.destinations.clone() {
         if *pstop.lock().unwrap() {
             break;
         } else {
             let ip_addr: IpAddr = dst.dst_ip;
             dst.dst_ports.into_par_iter().for_each(|port| {
-                let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP)).unwrap();
                 let socket_addr: SocketAddr = SocketAddr::new(ip_addr, port);
-                let sock_addr = SockAddr::from(socket_addr);
-                match socket.connect_timeout(&sock_addr, conn_timeout) {
-                    Ok(_) => {
-                        let port_info = PortInfo {
-                            port: socket_addr.port(),
-                            status: PortStatus::Open,
-                            describe: DATA.portmap.get(&socket_addr.port()).unwrap().to_string(),
-                        };
-                        // Avoid deadlock.
-                        let exists: bool = if let Some(r) = scan_result
-                            .lock()
-                            .unwrap()
-                            .result
-                            .ip_with_port
-                            .get_mut(&socket_addr.ip())
-                        {
-                            r.push(port_info.clone());
-                            true
-                        } else {
-                            false
-                        };
-                        if !exists {
-                            scan_result
-                                .lock()
-                                .unwrap()
-                                .result
-                                .ip_with_port
-                                .insert(socket_addr.ip(), vec![port_info]);
-                        }
-                    }
-                    Err(_) => {}
+                // Ports are skipped when local sockets stay exhausted after all retries.
+                if let Ok(status) = connect::try_connect(
+                    socket_addr,
+                    scan_setting.connect_timeout,
+                    &scan_setting.stats,
+                ) {
+                    let port_info = PortInfo::new(socket_addr.port(), Protocol::Tcp, status);
+                    scan_result
+                        .lock()
+                        .unwrap()
+                        .apply(&ScanEvent::Port(ip_addr, port_info));
                 }
                 match ptx.lock() {
                     Ok(lr) => match lr.send(socket_addr) {
@@ -314,6 +344,32 @@ fn run_connect_scan(
             });
         }
     }
+    // UDP ports, probed over connected UDP sockets
+    let targets = udp::udp_targets(&scan_setting.destinations);
+    udp::run_udp_probes(
+        &targets,
+        scan_setting.ports_concurrency,
+        scan_setting.send_rate,
+        scan_setting.wait_time,
+        &scan_setting.stats,
+        || {
+            *pstop.lock().unwrap()
+                || Instant::now().duration_since(start_time) > scan_setting.timeout
+        },
+        |socket_addr, status| {
+            let event = match status {
+                Ok(status) => ScanEvent::Port(
+                    socket_addr.ip(),
+                    PortInfo::new(socket_addr.port(), Protocol::Udp, status),
+                ),
+                Err(e) => ScanEvent::Error(format!("{}: {}", socket_addr, e)),
+            };
+            scan_result.lock().unwrap().apply(&event);
+            if let Ok(lr) = ptx.lock() {
+                let _ = lr.send(socket_addr);
+            }
+        },
+    );
 }
 
 pub(crate) fn scan_target(
@@ -342,7 +398,8 @@ pub(crate) fn scan_target(
         linux_fanout: None,
         promiscuous: false,
     };
-    let (mut tx, mut rx) = match pnet_datalink::channel(&interface, config) {
+    let channel = pnet_datalink::channel(&interface, config);
+    let (mut tx, mut rx) = match channel {
         Ok(pnet_datalink::Channel::Ethernet(tx, rx)) => (tx, rx),
         Ok(_) => panic!("Unknown channel type"),
         Err(e) => panic!("Error happened {}", e),
@@ -360,10 +417,23 @@ pub(crate) fn scan_target(
         | ScanType::IcmpPingScan
         | ScanType::TcpPingScan
         | ScanType::UdpPingScan => {
-            rayon::join(
+            let (probed, _) = rayon::join(
                 || send_packets(&mut tx, &scan_setting, &stop, ptx),
-                || receiver::receive_packets(&mut rx, receive_setting, &scan_result, &stop),
+                || {
+                    receiver::receive_packets(
+                        &mut rx,
+                        receive_setting,
+                        &scan_result,
+                        &stop,
+                    )
+                },
             );
+            if let ScanType::TcpSynScan = scan_setting.scan_type {
+                let mut results = scan_result.lock().unwrap();
+                for event in results.unanswered_udp(&probed) {
+                    results.apply(&event);
+                }
+            }
         }
         ScanType::TcpConnectScan => {
             run_connect_scan(scan_setting, &scan_result, &stop, ptx);
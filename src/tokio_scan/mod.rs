@@ -0,0 +1,7 @@
+mod scan;
+mod scanner;
+#[cfg(not(target_os = "windows"))]
+mod socket;
+
+use scan::scan_target;
+pub use scanner::*;
@@ -0,0 +1,483 @@
+use super::Scanner;
+use crate::data::Protocol;
+use crate::frame::connect::{backoff, is_exhausted, ConnectOutcome, CONNECT_RETRIES};
+use crate::frame::result::{PortInfo, PortStatus, ScanCounters, ScanEvent};
+use crate::frame::{udp, ScanType};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::{mpsc::Sender, Semaphore};
+use tokio::time::{self, Interval, MissedTickBehavior};
+
+fn build_pacer(send_rate: Duration) -> Option<Interval> {
+    if send_rate.is_zero() {
+        return None;
+    }
+    let mut interval = time::interval(send_rate);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    Some(interval)
+}
+
+fn port_info(port: u16, status: PortStatus) -> PortInfo {
+    PortInfo::new(port, Protocol::Tcp, status)
+}
+
+fn is_stopped(scanner: &Scanner, start_time: Instant, stop: &Arc<Mutex<bool>>) -> bool {
+    *stop.lock().unwrap() || Instant::now().duration_since(start_time) > scanner.timeout
+}
+
+/// Connect on a tokio socket; attempts failing on local resources are retried with back-off
+async fn try_connect(
+    socket_addr: SocketAddr,
+    conn_timeout: Duration,
+    stats: &ScanCounters,
+) -> io::Result<PortStatus> {
+    let mut retry = 0;
+    loop {
+        let result = match time::timeout(conn_timeout, TcpStream::connect(socket_addr)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::ErrorKind::TimedOut.into()),
+        };
+        match ConnectOutcome::from_result(result) {
+            ConnectOutcome::Status(status) => return Ok(status),
+            ConnectOutcome::Exhausted(e) => {
+                if retry >= CONNECT_RETRIES {
+                    return Err(e);
+                }
+                stats.retry();
+                time::sleep(backoff(retry)).await;
+                retry += 1;
+            }
+        }
+    }
+}
+
+/// Send an empty datagram on a connected UDP socket and wait for the answer
+///
+/// Only a failure to open the socket is returned as error.
+async fn try_udp(socket_addr: SocketAddr, wait_time: Duration) -> io::Result<PortStatus> {
+    let socket = UdpSocket::bind(udp::bind_addr(&socket_addr)).await?;
+    let result = match socket.connect(socket_addr).await {
+        Ok(_) => socket.send(&[]).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        return if is_exhausted(&e) {
+            Err(e)
+        } else {
+            Ok(udp::status_of::<()>(&Err(e)))
+        };
+    }
+    let mut buf = [0u8; 1500];
+    let result = match time::timeout(wait_time, socket.recv(&mut buf)).await {
+        Ok(result) => result,
+        Err(_) => Err(io::ErrorKind::TimedOut.into()),
+    };
+    Ok(udp::status_of(&result))
+}
+
+/// UDP ports of a port scan, probed over connected UDP sockets
+async fn run_udp_scan(scanner: &Scanner, tx: &Sender<ScanEvent>, stop: &Arc<Mutex<bool>>) {
+    let start_time = Instant::now();
+    let semaphore = Arc::new(Semaphore::new(scanner.concurrency));
+    let mut pacer = build_pacer(scanner.send_rate);
+    for socket_addr in udp::udp_targets(&scanner.destinations) {
+        if is_stopped(scanner, start_time, stop) {
+            break;
+        }
+        if let Some(pacer) = pacer.as_mut() {
+            pacer.tick().await;
+        }
+        let permit = match Arc::clone(&semaphore).acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => break,
+        };
+        let wait_time = scanner.wait_time;
+        let stats = Arc::clone(&scanner.stats);
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let _ = tx.send(ScanEvent::Probe(socket_addr)).await;
+            let status = try_udp(socket_addr, wait_time).await;
+            // only running out of local sockets or ports fails a probe
+            stats.sent(&status);
+            let event = match status {
+                Ok(status) => ScanEvent::Port(
+                    socket_addr.ip(),
+                    PortInfo::new(socket_addr.port(), Protocol::Udp, status),
+                ),
+                Err(e) => ScanEvent::Error(format!("{}: {}", socket_addr, e)),
+            };
+            let _ = tx.send(event).await;
+            drop(permit);
+        });
+    }
+    // Every task holds a permit until its events are sent.
+    let _ = semaphore.acquire_many(scanner.concurrency as u32).await;
+}
+
+async fn run_connect_scan(scanner: &Scanner, tx: &Sender<ScanEvent>, stop: &Arc<Mutex<bool>>) {
+    let start_time = Instant::now();
+    let semaphore = Arc::new(Semaphore::new(scanner.concurrency));
+    let mut pacer = build_pacer(scanner.send_rate);
+    'hosts: for dst in scanner.destinations.iter() {
+        for port in dst.dst_ports.iter() {
+            if is_stopped(scanner, start_time, stop) {
+                break 'hosts;
+            }
+            if let Some(pacer) = pacer.as_mut() {
+                pacer.tick().await;
+            }
+            let permit = match Arc::clone(&semaphore).acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => break 'hosts,
+            };
+            let socket_addr = SocketAddr::new(dst.dst_ip, *port);
+            let conn_timeout = scanner.connect_timeout;
+            let stats = Arc::clone(&scanner.stats);
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _ = tx.send(ScanEvent::Probe(socket_addr)).await;
+                let status = try_connect(socket_addr, conn_timeout, &stats).await;
+                stats.sent(&status);
+                let event = match status {
+                    Ok(status) => {
+                        ScanEvent::Port(socket_addr.ip(), port_info(socket_addr.port(), status))
+                    }
+                    Err(e) => ScanEvent::Error(format!("{}: {}", socket_addr, e)),
+                };
+                let _ = tx.send(event).await;
+                drop(permit);
+            });
+        }
+    }
+    // Every task holds a permit until its events are sent.
+    let _ = semaphore.acquire_many(scanner.concurrency as u32).await;
+}
+
+/// TCP host discovery; any answer from a port, open or refused, means the host is up
+///
+/// Stands in for raw socket pings in unprivileged mode.
+#[cfg(target_os = "linux")]
+async fn run_connect_ping(scanner: &Scanner, tx: &Sender<ScanEvent>, stop: &Arc<Mutex<bool>>) {
+    let start_time = Instant::now();
+    let semaphore = Arc::new(Semaphore::new(scanner.concurrency));
+    let mut pacer = build_pacer(scanner.send_rate);
+    for dst in scanner.destinations.iter() {
+        if is_stopped(scanner, start_time, stop) {
+            break;
+        }
+        let permit = match Arc::clone(&semaphore).acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => break,
+        };
+        let (scanner, tx, stop) = (scanner.clone(), tx.clone(), Arc::clone(stop));
+        let dst = dst.clone();
+        // the pacer spaces the hosts, the ports of a host are tried one after another
+        if let Some(pacer) = pacer.as_mut() {
+            pacer.tick().await;
+        }
+        tokio::spawn(async move {
+            for port in crate::frame::connect::ping_ports(&dst) {
+                if is_stopped(&scanner, start_time, &stop) {
+                    break;
+                }
+                let socket_addr = SocketAddr::new(dst.dst_ip, *port);
+                let _ = tx.send(ScanEvent::Probe(socket_addr)).await;
+                let status =
+                    try_connect(socket_addr, scanner.connect_timeout, &scanner.stats).await;
+                scanner.stats.sent(&status);
+                if let Ok(PortStatus::Open) | Ok(PortStatus::Closed) = status {
+                    let host_info = crate::frame::result::HostInfo {
+                        ip_addr: dst.dst_ip,
+                        ttl: 0,
+                    };
+                    let _ = tx.send(ScanEvent::Host(host_info)).await;
+                    break;
+                }
+            }
+            drop(permit);
+        });
+    }
+    // Every task holds a permit until its events are sent.
+    let _ = semaphore.acquire_many(scanner.concurrency as u32).await;
+}
+
+#[cfg(not(target_os = "windows"))]
+mod raw {
+    use super::{build_pacer, is_stopped, port_info};
+    use crate::frame::result::{HostInfo, PortStatus, ScanEvent, ScanResults};
+    use crate::frame::ScanType;
+    use crate::packet;
+    use crate::tokio_scan::socket::RawSocket;
+    use crate::tokio_scan::Scanner;
+    use pnet_packet::icmp::IcmpTypes;
+    use pnet_packet::ip::IpNextHeaderProtocols;
+    use pnet_packet::tcp::TcpFlags;
+    use pnet_packet::Packet;
+    use socket2::{Protocol, SockAddr};
+    use std::collections::HashSet;
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::sync::mpsc::Sender;
+    use tokio::time;
+
+    /// How often the receiver wakes up to check for the end of the scan
+    const RECV_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    fn build_icmpv4_echo_packet() -> Vec<u8> {
+        let mut buf = [0; 16];
+        let mut icmp_packet =
+            pnet_packet::icmp::echo_request::MutableEchoRequestPacket::new(&mut buf[..]).unwrap();
+        packet::icmp::build_icmp_packet(&mut icmp_packet);
+        icmp_packet.packet().to_vec()
+    }
+
+    fn build_tcp_syn_packet(
+        src_ip: IpAddr,
+        src_port: u16,
+        dst_ip: IpAddr,
+        dst_port: u16,
+    ) -> Vec<u8> {
+        let mut vec = [0u8; 32];
+        let mut tcp_packet = pnet_packet::tcp::MutableTcpPacket::new(&mut vec[..]).unwrap();
+        packet::tcp::build_tcp_packet(&mut tcp_packet, src_ip, src_port, dst_ip, dst_port);
+        tcp_packet.packet().to_vec()
+    }
+
+    fn build_udp_packet(src_ip: IpAddr, src_port: u16, dst_ip: IpAddr, dst_port: u16) -> Vec<u8> {
+        let mut vec = [0u8; 8];
+        let mut udp_packet = pnet_packet::udp::MutableUdpPacket::new(&mut vec[..]).unwrap();
+        packet::udp::build_udp_packet(&mut udp_packet, src_ip, src_port, dst_ip, dst_port);
+        udp_packet.packet().to_vec()
+    }
+
+    async fn send_packets(
+        scanner: Scanner,
+        socket: Arc<RawSocket>,
+        tx: Sender<ScanEvent>,
+        stop: Arc<Mutex<bool>>,
+    ) {
+        let start_time = Instant::now();
+        let mut pacer = build_pacer(scanner.send_rate);
+        let mut probes: Vec<(SocketAddr, Vec<u8>)> = vec![];
+        for dst in scanner.destinations.iter() {
+            match scanner.scan_type {
+                ScanType::IcmpPingScan => {
+                    probes.push((SocketAddr::new(dst.dst_ip, 0), build_icmpv4_echo_packet()));
+                }
+                ScanType::TcpPingScan | ScanType::TcpSynScan => {
+                    for port in dst.dst_ports.iter() {
+                        let tcp_packet = build_tcp_syn_packet(
+                            scanner.src_ip,
+                            scanner.src_port,
+                            dst.dst_ip,
+                            *port,
+                        );
+                        probes.push((SocketAddr::new(dst.dst_ip, *port), tcp_packet));
+                    }
+                }
+                ScanType::UdpPingScan => {
+                    for port in dst.dst_ports.iter() {
+                        let udp_packet =
+                            build_udp_packet(scanner.src_ip, scanner.src_port, dst.dst_ip, *port);
+                        probes.push((SocketAddr::new(dst.dst_ip, *port), udp_packet));
+                    }
+                }
+                ScanType::TcpConnectScan => {}
+            }
+            for (socket_addr, probe) in probes.drain(..) {
+                if is_stopped(&scanner, start_time, &stop) {
+                    return;
+                }
+                if let Some(pacer) = pacer.as_mut() {
+                    pacer.tick().await;
+                }
+                let sent = socket.send_to(&probe, &SockAddr::from(socket_addr)).await;
+                scanner.stats.sent(&sent);
+                if let Err(e) = sent {
+                    let _ = tx
+                        .send(ScanEvent::Error(format!(
+                            "send to {} failed: {}",
+                            socket_addr, e
+                        )))
+                        .await;
+                }
+                if tx.send(ScanEvent::Probe(socket_addr)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn handle_packet(buf: &[u8], scanner: &Scanner, ip_set: &HashSet<IpAddr>) -> Option<ScanEvent> {
+        let packet = pnet_packet::ipv4::Ipv4Packet::new(buf)?;
+        let host_info = HostInfo {
+            ip_addr: IpAddr::V4(packet.get_source()),
+            ttl: packet.get_ttl(),
+        };
+        if !ip_set.contains(&host_info.ip_addr) {
+            return None;
+        }
+        match packet.get_next_level_protocol() {
+            IpNextHeaderProtocols::Tcp => {
+                let tcp_packet = pnet_packet::tcp::TcpPacket::new(packet.payload())?;
+                if tcp_packet.get_destination() != scanner.src_port {
+                    return None;
+                }
+                let status = if tcp_packet.get_flags() == TcpFlags::SYN | TcpFlags::ACK {
+                    PortStatus::Open
+                } else if tcp_packet.get_flags() == TcpFlags::RST | TcpFlags::ACK {
+                    PortStatus::Closed
+                } else {
+                    return None;
+                };
+                match scanner.scan_type {
+                    ScanType::TcpSynScan => Some(ScanEvent::Port(
+                        host_info.ip_addr,
+                        port_info(tcp_packet.get_source(), status),
+                    )),
+                    _ => Some(ScanEvent::Host(host_info)),
+                }
+            }
+            IpNextHeaderProtocols::Icmp => {
+                let icmp_packet = pnet_packet::icmp::IcmpPacket::new(packet.payload())?;
+                match (&scanner.scan_type, icmp_packet.get_icmp_type()) {
+                    (ScanType::IcmpPingScan, IcmpTypes::EchoReply)
+                    | (ScanType::UdpPingScan, IcmpTypes::DestinationUnreachable) => {
+                        Some(ScanEvent::Host(host_info))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) async fn run_raw_scan(
+        scanner: &Scanner,
+        tx: &Sender<ScanEvent>,
+        stop: &Arc<Mutex<bool>>,
+    ) {
+        if scanner.src_ip.is_ipv6() {
+            let _ = tx
+                .send(ScanEvent::Error(String::from(
+                    "Raw socket scan over IPv6 is not supported, use TcpConnect",
+                )))
+                .await;
+            return;
+        }
+        let (send_protocol, recv_protocol) = match scanner.scan_type {
+            ScanType::IcmpPingScan => (Protocol::ICMPV4, Protocol::ICMPV4),
+            ScanType::UdpPingScan => (Protocol::UDP, Protocol::ICMPV4),
+            _ => (Protocol::TCP, Protocol::TCP),
+        };
+        let send_socket = match RawSocket::new(scanner.src_ip, send_protocol) {
+            Ok(socket) => Arc::new(socket),
+            Err(e) => {
+                let _ = tx
+                    .send(ScanEvent::Error(format!(
+                        "Failed to create raw socket: {}",
+                        e
+                    )))
+                    .await;
+                return;
+            }
+        };
+        let recv_socket = if send_protocol == recv_protocol {
+            Arc::clone(&send_socket)
+        } else {
+            match RawSocket::new(scanner.src_ip, recv_protocol) {
+                Ok(socket) => Arc::new(socket),
+                Err(e) => {
+                    let _ = tx
+                        .send(ScanEvent::Error(format!(
+                            "Failed to create raw socket: {}",
+                            e
+                        )))
+                        .await;
+                    return;
+                }
+            }
+        };
+        let ip_set: HashSet<IpAddr> = scanner.destinations.iter().map(|dst| dst.dst_ip).collect();
+        let start_time = Instant::now();
+        let sender = tokio::spawn(send_packets(
+            scanner.clone(),
+            send_socket,
+            tx.clone(),
+            Arc::clone(stop),
+        ));
+        let mut deadline: Option<Instant> = None;
+        let mut buf = vec![0u8; 65535];
+        // answers already seen, to count duplicates
+        let mut seen = ScanResults::new();
+        loop {
+            if is_stopped(scanner, start_time, stop) {
+                break;
+            }
+            match deadline {
+                Some(deadline) if Instant::now() >= deadline => break,
+                None if sender.is_finished() => {
+                    deadline = Some(Instant::now() + scanner.wait_time);
+                }
+                _ => {}
+            }
+            if let Ok(Ok(len)) = time::timeout(RECV_POLL_INTERVAL, recv_socket.recv(&mut buf)).await
+            {
+                scanner.stats.frame_received();
+                if let Some(event) = handle_packet(&buf[..len], scanner, &ip_set) {
+                    scanner.stats.frame_matched(seen.apply(&event));
+                    if tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        sender.abort();
+    }
+}
+
+pub(crate) async fn scan_target(scanner: Scanner, tx: Sender<ScanEvent>, stop: Arc<Mutex<bool>>) {
+    // UDP ports of port scans go along with the TCP ports
+    let udp_scan = match scanner.scan_type {
+        ScanType::TcpSynScan | ScanType::TcpConnectScan => {
+            let (scanner, tx, stop) = (scanner.clone(), tx.clone(), Arc::clone(&stop));
+            Some(tokio::spawn(async move {
+                run_udp_scan(&scanner, &tx, &stop).await;
+            }))
+        }
+        _ => None,
+    };
+    scan_tcp_target(&scanner, &tx, &stop).await;
+    if let Some(udp_scan) = udp_scan {
+        let _ = udp_scan.await;
+    }
+}
+
+async fn scan_tcp_target(scanner: &Scanner, tx: &Sender<ScanEvent>, stop: &Arc<Mutex<bool>>) {
+    #[cfg(target_os = "linux")]
+    if scanner.unprivileged {
+        return match scanner.scan_type {
+            ScanType::TcpSynScan | ScanType::TcpConnectScan => {
+                run_connect_scan(scanner, tx, stop).await
+            }
+            _ => run_connect_ping(scanner, tx, stop).await,
+        };
+    }
+    match scanner.scan_type {
+        ScanType::TcpConnectScan => run_connect_scan(scanner, tx, stop).await,
+        #[cfg(not(target_os = "windows"))]
+        _ => raw::run_raw_scan(scanner, tx, stop).await,
+        #[cfg(target_os = "windows")]
+        _ => {
+            let _ = tx
+                .send(ScanEvent::Error(String::from(
+                    "Raw socket scan is not supported on windows in tokio model, use TcpConnect",
+                )))
+                .await;
+        }
+    }
+}
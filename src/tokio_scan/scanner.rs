@@ -0,0 +1,399 @@
+use super::scan_target;
+use crate::data::id::{
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_PORTS_CONCURRENCY, DEFAULT_SRC_PORT, EVENT_CHANNEL_SIZE,
+};
+use crate::exclude::Exclusions;
+#[cfg(target_os = "linux")]
+use crate::frame::connect::degraded_warning;
+use crate::frame::result::{
+    ScanCounters, ScanEvent, ScanResult, ScanResults, ScanStats, ScanStatus,
+};
+use crate::frame::{Destination, ScanType};
+use crate::netns;
+use crate::scope::{Scope, ScopeViolation};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Tokio native Scanner
+/// # Example
+/// ```
+/// fn main() -> Result<(), String> {
+/// #[cfg(feature = "tokio")]
+/// {
+///     use e_libscanner::frame::result::ScanEvent;
+///     use e_libscanner::{tokio_scan, Opts};
+///     use tokio_stream::StreamExt;
+///     // more command information use: -h
+///     let scanner = Opts::new(Some(&[
+///         "e-libscanner",
+///         "--ips",
+///         "192.168.1.1",
+///         "--ports",
+///         "22",
+///         "80",
+///         "443",
+///         "--model",
+///         "tokio",
+///         "--scan",
+///         "TcpConnect",
+///         "--no-gui",
+///     ]))?
+///     .init()?
+///     .downcast::<tokio_scan::Scanner>()
+///     .unwrap();
+///     let rt = tokio::runtime::Builder::new_current_thread()
+///         .enable_all()
+///         .build()
+///         .unwrap();
+///     rt.block_on(async {
+///         let mut events = scanner.scan_stream(None)?;
+///         while let Some(event) = events.next().await {
+///             match event {
+///                 ScanEvent::Host(host) => println!("Host: {}", host),
+///                 ScanEvent::Port(ip, port) => println!("Port: {} {}", ip, port),
+///                 ScanEvent::Done(result) => println!("Scan Time: {:?}", result.scan_time),
+///                 _ => {}
+///             }
+///         }
+///         Ok::<(), String>(())
+///     })?;
+/// }
+/// Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scanner {
+    /// Source IP address
+    pub src_ip: IpAddr,
+    /// Source port
+    pub src_port: u16,
+    /// Destinations
+    pub destinations: Vec<Destination>,
+    /// Scan Type
+    pub scan_type: ScanType,
+    /// Timeout setting for entire scan task
+    pub timeout: Duration,
+    /// Waiting time after packet sending task is completed
+    pub wait_time: Duration,
+    /// Packet sending interval(0 for unlimited)
+    pub send_rate: Duration,
+    /// Maximum number of TCP connections in flight(at least 1)
+    pub concurrency: usize,
+    /// Timeout of a single TCP connection attempt
+    pub connect_timeout: Duration,
+    /// Scan over TCP connections instead of raw sockets(Linux only)
+    pub unprivileged: bool,
+    /// Network namespace the scan runs in(Linux only)
+    pub netns: Option<String>,
+    /// Authorized scope of destinations
+    pub scope: Option<Scope>,
+    /// Out of scope targets dropped before reaching the scanner
+    pub scope_violations: Vec<ScopeViolation>,
+    /// Hosts and ports left out of the scan
+    pub exclusions: Exclusions,
+    /// Counters of the running scan
+    pub stats: Arc<ScanCounters>,
+    /// Scan Result
+    pub scan_result: ScanResult,
+}
+
+impl Scanner {
+    /// Create new Scanner with source IP address
+    ///
+    /// Initialized with default value based on the specified IP address
+    pub fn new(src_ip: IpAddr) -> Result<Scanner, String> {
+        if !pnet_datalink::interfaces()
+            .iter()
+            .any(|iface| iface.ips.iter().any(|ip| ip.ip() == src_ip))
+        {
+            return Err(String::from(
+                "Failed to create Scanner. Network Interface not found.",
+            ));
+        }
+        Ok(Scanner {
+            src_ip,
+            src_port: DEFAULT_SRC_PORT,
+            destinations: vec![],
+            scan_type: ScanType::IcmpPingScan,
+            timeout: Duration::from_millis(300_000),
+            wait_time: Duration::from_millis(200),
+            send_rate: Duration::from_millis(0),
+            concurrency: DEFAULT_PORTS_CONCURRENCY,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            unprivileged: false,
+            netns: None,
+            scope: None,
+            scope_violations: vec![],
+            exclusions: Exclusions::new(),
+            stats: Arc::new(ScanCounters::new()),
+            scan_result: ScanResult::new(),
+        })
+    }
+    /// Create new Scanner inside the named network namespace(`/var/run/netns/<name>`)
+    ///
+    /// The scan runs on its own runtime thread inside the namespace. Linux only.
+    pub fn new_in_netns(src_ip: IpAddr, netns: &str) -> Result<Scanner, String> {
+        let mut scanner = netns::run_in(Some(netns), move || Scanner::new(src_ip))??;
+        scanner.netns = Some(netns.to_string());
+        Ok(scanner)
+    }
+    /// get scan count
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        for dst in self.destinations.iter() {
+            if !dst.dst_ports.is_empty() {
+                len += dst.dst_ports.len();
+            } else {
+                len += 1;
+            }
+        }
+        len
+    }
+    /// Returns true if there is nothing to scan
+    pub fn is_empty(&self) -> bool {
+        self.destinations.is_empty()
+    }
+    /// Set source IP address
+    pub fn set_src_ip(&mut self, src_ip: IpAddr) {
+        self.src_ip = src_ip;
+    }
+    /// Get source IP address
+    pub fn get_src_ip(&self) -> IpAddr {
+        self.src_ip
+    }
+    /// Add Destination
+    pub fn add_destination(&mut self, dst: Destination) {
+        self.destinations.push(dst);
+    }
+    /// Set Destinations
+    pub fn set_destinations(&mut self, dst: Vec<Destination>) {
+        self.destinations = dst;
+    }
+    /// Get Destinations
+    pub fn get_destinations(&self) -> Vec<Destination> {
+        self.destinations.clone()
+    }
+    /// Set ScanType
+    pub fn set_scan_type(&mut self, scan_type: ScanType) {
+        self.scan_type = scan_type;
+    }
+    /// Get ScanType
+    pub fn get_scan_type(&self) -> ScanType {
+        self.scan_type.clone()
+    }
+    /// Set timeout
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+    /// Get timeout
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+    /// Set wait time
+    pub fn set_wait_time(&mut self, wait_time: Duration) {
+        self.wait_time = wait_time;
+    }
+    /// Get wait time
+    pub fn get_wait_time(&self) -> Duration {
+        self.wait_time
+    }
+    /// Set send rate
+    pub fn set_send_rate(&mut self, send_rate: Duration) {
+        self.send_rate = send_rate;
+    }
+    /// Get send rate
+    pub fn get_send_rate(&self) -> Duration {
+        self.send_rate
+    }
+    /// Set maximum number of TCP connections in flight
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+    /// Get maximum number of TCP connections in flight
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency
+    }
+    /// Set timeout of a single TCP connection attempt
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+    /// Get timeout of a single TCP connection attempt
+    pub fn get_connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+    /// Set unprivileged mode
+    ///
+    /// Port scans fall back to TCP connect, host discovery to TCP connect pings.
+    /// Linux only.
+    pub fn set_unprivileged(&mut self, unprivileged: bool) {
+        self.unprivileged = unprivileged;
+    }
+    /// Get unprivileged mode
+    pub fn get_unprivileged(&self) -> bool {
+        self.unprivileged
+    }
+    /// Get network namespace
+    pub fn get_netns(&self) -> Option<String> {
+        self.netns.clone()
+    }
+    /// Set authorized scope
+    ///
+    /// Out of scope destinations are skipped, in strict mode the scan fails without sending.
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = Some(scope);
+    }
+    /// Get authorized scope
+    pub fn get_scope(&self) -> Option<Scope> {
+        self.scope.clone()
+    }
+    /// Set out of scope targets dropped before reaching the scanner
+    ///
+    /// They are reported in the scan result along with the destinations refused by the scope.
+    pub fn set_scope_violations(&mut self, violations: Vec<ScopeViolation>) {
+        self.scope_violations = violations;
+    }
+    /// Get out of scope targets dropped before reaching the scanner
+    pub fn get_scope_violations(&self) -> Vec<ScopeViolation> {
+        self.scope_violations.clone()
+    }
+    /// Set hosts and ports left out of the scan
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) {
+        self.exclusions = exclusions;
+    }
+    /// Get hosts and ports left out of the scan
+    pub fn get_exclusions(&self) -> Exclusions {
+        self.exclusions.clone()
+    }
+    /// Get scan result
+    pub fn get_scan_result(&self) -> ScanResult {
+        self.scan_result.clone()
+    }
+    /// Get counters of the running scan, readable from other tasks during `scan`
+    pub fn get_stats_counters(&self) -> Arc<ScanCounters> {
+        self.stats.clone()
+    }
+    /// Get current counters of the scan
+    pub fn get_stats(&self) -> ScanStats {
+        self.stats.snapshot()
+    }
+    /// Run scan on the current tokio runtime and stream its events
+    ///
+    /// The scan runs on the current runtime and fails when called outside of one.
+    /// With a network namespace it cannot: a namespace is entered per thread and the
+    /// sockets must be opened inside it, so the scan then starts a second,
+    /// current-thread runtime on a dedicated thread inside the namespace.
+    ///
+    /// Host and port events are deduplicated; the last event is always `ScanEvent::Done`.
+    /// Dropping the stream stops the scan.
+    pub fn scan_stream(
+        &self,
+        pstop: Option<Arc<Mutex<bool>>>,
+    ) -> Result<impl Stream<Item = ScanEvent> + Send + Unpin + 'static, String> {
+        let runtime = match &self.netns {
+            Some(_) => None,
+            None => Some(Handle::try_current().map_err(|e| e.to_string())?),
+        };
+        self.stats.reset();
+        let (tx, rx) = mpsc::channel(EVENT_CHANNEL_SIZE);
+        let error_tx = tx.clone();
+        let mut scanner = self.clone();
+        // a public field may bypass `set_concurrency`, no permit would ever be handed out
+        scanner.concurrency = scanner.concurrency.max(1);
+        let stop = pstop.unwrap_or_else(|| Arc::new(Mutex::new(false)));
+        let netns = scanner.netns.clone();
+        // out of scope and excluded destinations never reach the scan
+        let destinations = self
+            .exclusions
+            .filter_destinations(self.destinations.clone());
+        let scoped = match &self.scope {
+            Some(scope) => scope.filter_destinations(destinations),
+            None => Ok((destinations, vec![])),
+        };
+        let task = async move {
+            let scope_violations = match scoped {
+                Ok((destinations, violations)) => {
+                    scanner.destinations = destinations;
+                    let mut scope_violations = scanner.scope_violations.clone();
+                    scope_violations.extend(violations);
+                    for violation in scope_violations.iter() {
+                        let _ = tx.send(ScanEvent::Error(violation.to_string())).await;
+                    }
+                    scope_violations
+                }
+                Err(e) => {
+                    let _ = tx.send(ScanEvent::Error(e.clone())).await;
+                    let _ = tx.send(ScanEvent::Done(ScanResult::failed(e))).await;
+                    return;
+                }
+            };
+            let (probe_tx, mut probe_rx) = mpsc::channel(EVENT_CHANNEL_SIZE);
+            let start_time = Instant::now();
+            tokio::spawn(scan_target(scanner.clone(), probe_tx, Arc::clone(&stop)));
+            let mut results = ScanResults::new();
+            while let Some(event) = probe_rx.recv().await {
+                if results.apply(&event) && tx.send(event).await.is_err() {
+                    *stop.lock().unwrap() = true;
+                }
+            }
+            let mut result = results.result;
+            result.scope_violations = scope_violations;
+            result.scan_time = Instant::now().duration_since(start_time);
+            result.stats = scanner.stats.snapshot();
+            #[cfg(target_os = "linux")]
+            if scanner.unprivileged {
+                result.warnings.extend(degraded_warning(&scanner.scan_type));
+            }
+            if result.scan_time > scanner.timeout {
+                result.scan_status = ScanStatus::Timeout;
+            } else {
+                result.scan_status = ScanStatus::Done;
+            }
+            let _ = tx.send(ScanEvent::Done(result)).await;
+        };
+        match netns {
+            // sockets are opened by the runtime threads, run a runtime inside the namespace
+            Some(name) => {
+                thread::spawn(move || {
+                    let runtime = netns::enter(&name).and_then(|_| {
+                        tokio::runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()
+                            .map_err(|e| e.to_string())
+                    });
+                    match runtime {
+                        Ok(runtime) => runtime.block_on(task),
+                        Err(e) => {
+                            let _ = error_tx.blocking_send(ScanEvent::Error(e.clone()));
+                            let _ = error_tx.blocking_send(ScanEvent::Done(ScanResult::failed(e)));
+                        }
+                    }
+                });
+            }
+            None => {
+                if let Some(runtime) = runtime {
+                    runtime.spawn(task);
+                }
+            }
+        }
+        Ok(ReceiverStream::new(rx))
+    }
+    /// Run scan and return result
+    pub async fn scan(&mut self, pstop: Option<Arc<Mutex<bool>>>) -> ScanResult {
+        let mut events = match self.scan_stream(pstop) {
+            Ok(events) => events,
+            Err(e) => return ScanResult::failed(e),
+        };
+        while let Some(event) = events.next().await {
+            if let ScanEvent::Done(result) = event {
+                self.scan_result = result;
+            }
+        }
+        self.scan_result.clone()
+    }
+}
@@ -0,0 +1,40 @@
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io::{self, Read};
+use std::net::IpAddr;
+use tokio::io::unix::AsyncFd;
+
+/// Raw socket driven by the tokio reactor
+#[derive(Debug)]
+pub(crate) struct RawSocket {
+    inner: AsyncFd<Socket>,
+}
+
+impl RawSocket {
+    pub(crate) fn new(addr: IpAddr, protocol: Protocol) -> io::Result<RawSocket> {
+        let socket = match addr {
+            IpAddr::V4(_) => Socket::new(Domain::IPV4, Type::RAW, Some(protocol))?,
+            IpAddr::V6(_) => Socket::new(Domain::IPV6, Type::RAW, Some(protocol))?,
+        };
+        socket.set_nonblocking(true)?;
+        let inner = AsyncFd::new(socket)?;
+        Ok(RawSocket { inner })
+    }
+    pub(crate) async fn send_to(&self, buf: &[u8], target: &SockAddr) -> io::Result<usize> {
+        loop {
+            let mut guard = self.inner.writable().await?;
+            match guard.try_io(|inner| inner.get_ref().send_to(buf, target)) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+    pub(crate) async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+            match guard.try_io(|inner| inner.get_ref().read(buf)) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
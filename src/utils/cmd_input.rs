@@ -4,8 +4,11 @@ use crate::async_scan;
 use crate::os;
 #[cfg(feature = "sync")]
 use crate::sync_scan;
+#[cfg(feature = "tokio")]
+use crate::tokio_scan;
 
 use crate::{
+    data::{Protocol, DATA},
     frame::{Destination, ScanType},
     interface,
     traceroute::Tracert,
@@ -15,13 +18,25 @@ use ipnet::{self, IpNet};
 use serde_derive::Deserialize;
 use std::{
     any::Any,
+    collections::HashSet,
     ffi::OsString,
     net::{IpAddr, Ipv4Addr},
     time::Duration,
 };
 use structopt::{clap::arg_enum, StructOpt};
 
+use super::diagnose::diagnose;
+use super::diff::{ScanDiff, ScanSnapshot};
+use super::monitor::Monitor;
+use super::policy::{Policy, PolicyReport};
 use super::dns::{DnsResult, DnsResultType, DnsResults};
+use super::exclude::Exclusions;
+use super::input::read_targets_file;
+use super::ports::parse_port_spec;
+use super::netns;
+use super::scope::{Scope, ScopeViolation};
+#[cfg(target_os = "linux")]
+use super::privilege;
 
 arg_enum! {
     /// Script
@@ -40,10 +55,16 @@ arg_enum! {
     pub enum ScanModelType {
         Sync,
         Async,
+        Tokio,
         Os,
         Service,
         Dns,
         Traceroute,
+        Diagnose,
+        Diff,
+        Monitor,
+        Policy,
+        Exporter,
         None
     }
 }
@@ -99,7 +120,7 @@ arg_enum! {
 ///     Ok(())
 /// }
 /// ```
-#[derive(StructOpt, Debug)]
+#[derive(StructOpt, Debug, Clone)]
 #[structopt(name = "", setting = structopt::clap::AppSettings::TrailingVarArg)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Opts {
@@ -107,7 +128,23 @@ pub struct Opts {
     #[structopt(short, long, use_delimiter = true)]
     pub ips: Vec<String>,
 
-    /// port list; Example: 80,443,8080,100-1000.
+    /// scan the N most frequently open ports in addition to --ports, over UDP for a UDP scan and for U: ports too; N is at most 295(TCP) or 88(UDP); default is 0
+    #[structopt(long, default_value = "0")]
+    pub top_ports: usize,
+
+    /// load service names of the operating system(/etc/services) over the embedded ones
+    #[structopt(long)]
+    pub system_services: bool,
+
+    /// services file("name port/protocol" per line) overriding and extending service names;
+    #[structopt(long, default_value = "")]
+    pub services_file: String,
+
+    /// file of targets, one per line("-" for stdin); lines may carry ports: "host:80,443", "[fe80::1]:22"
+    #[structopt(long, default_value = "")]
+    pub input_file: String,
+
+    /// port list; Example: 80,443,8080,100-1000, -(all), ssh,https, T:80,U:53(U: adds a UDP scan).
     #[structopt(short, long, use_delimiter = true)]
     pub ports: Vec<String>,
 
@@ -115,6 +152,30 @@ pub struct Opts {
     #[structopt(long, default_value = "")]
     pub src_ip: String,
 
+    /// run inside the named network namespace(/var/run/netns/<name>, Linux only);
+    #[structopt(long, default_value = "")]
+    pub netns: String,
+
+    /// file of authorized networks; lines `allow <cidr>` or `exclude <cidr>`;
+    #[structopt(long, default_value = "")]
+    pub scope_file: String,
+
+    /// refuse the whole scan if any target is out of scope
+    #[structopt(long)]
+    pub scope_strict: bool,
+
+    /// hosts left out of the scan; example: "192.168.1.1", "10.0.0.0/24", "router.lan"
+    #[structopt(long, use_delimiter = true)]
+    pub exclude: Vec<String>,
+
+    /// file of hosts left out of the scan, one host or network per line;
+    #[structopt(long, default_value = "")]
+    pub exclude_file: String,
+
+    /// ports left out of the scan; Example: 9100,6000-6063.
+    #[structopt(long, use_delimiter = true)]
+    pub exclude_ports: Vec<String>,
+
     /// The timeout in milliseconds before a port is assumed to be closed; default is 3_600_000ms
     #[structopt(short, long, default_value = "3600000")]
     pub timeout: u64,
@@ -127,14 +188,59 @@ pub struct Opts {
     #[structopt(short, long, default_value = "0")]
     pub rate: u64,
 
+    /// Number of hosts scanned concurrently in async model; default is 50
+    #[structopt(long, default_value = "50")]
+    pub hosts_concurrency: usize,
+
+    /// Number of ports scanned concurrently per host in async model,
+    /// or connections in flight for connect scan and tokio model; default is 100
+    #[structopt(long, default_value = "100")]
+    pub ports_concurrency: usize,
+
+    /// Timeout in milliseconds of a single TCP connection attempt; default is 200ms
+    #[structopt(long, default_value = "200")]
+    pub connect_timeout: u64,
+
     /// send type; [ Icmp, TcpConnect, Udp, Tcp, TcpSyn]; default: None;  
     #[structopt(short, long, possible_values = &ScanOrderType::variants(), case_insensitive = true, default_value = "none")]
     pub scan: ScanOrderType,
 
-    /// scan type; [ Sync, Async, Os, Service, Dns, Traceroute ]; default: sync
+    /// scan type; [ Sync, Async, Tokio, Os, Service, Dns, Traceroute, Diagnose, Diff, Monitor, Policy, Exporter ]; default: sync
     #[structopt(short, long, possible_values = &ScanModelType::variants(), case_insensitive = true, default_value = "none")]
     pub model: ScanModelType,
 
+    /// earlier and later result files (JSON) compared by the diff model; example: "monday.json,tuesday.json"
+    #[structopt(long, use_delimiter = true)]
+    pub diff: Vec<String>,
+
+    /// seconds between two runs of the monitor model or the scheduled scan of the exporter model; default: 3600
+    #[structopt(long, default_value = "3600")]
+    pub interval: u64,
+
+    /// history file of the monitor model; runs are kept in memory when not set
+    #[structopt(long, default_value = "")]
+    pub history: String,
+
+    /// change notifications of the monitor model; [ stdout, jsonl:<path>, webhook:<url> ]; default: stdout
+    #[structopt(long, use_delimiter = true)]
+    pub notify: Vec<String>,
+
+    /// policy file (TOML) checked by the policy model
+    #[structopt(long, default_value = "")]
+    pub policy: String,
+
+    /// result file (JSON) checked by the policy model; the targets are scanned when not set
+    #[structopt(long, default_value = "")]
+    pub results: String,
+
+    /// listen address of the exporter model
+    #[structopt(long, default_value = "127.0.0.1:9539")]
+    pub listen: String,
+
+    /// probe modules file (TOML) of the exporter model, added to the built-in modules
+    #[structopt(long, default_value = "")]
+    pub modules: String,
+
     /// scripts
     #[structopt(long, possible_values = &ScriptsRequired::variants(), case_insensitive = true, default_value = "default")]
     pub scripts: ScriptsRequired,
@@ -143,6 +249,10 @@ pub struct Opts {
     #[structopt(long)]
     pub no_gui: bool,
 
+    /// scan without raw sockets(Linux only); used automatically when privileges are missing
+    #[structopt(long)]
+    pub unprivileged: bool,
+
     // The number of occurrences of the `v/verbose` flag
     /// Verbose mode (-v, -vv, -vvv, etc.)
     #[allow(dead_code)]
@@ -205,7 +315,7 @@ impl Opts {
         match args {
             Some(arg) => match Opts::from_iter_safe(arg) {
                 Ok(opt) => Ok(opt),
-                Err(e) => Err(String::from(e.to_string())),
+                Err(e) => Err(e.to_string()),
             },
             None => Ok(Opts::from_args()),
         }
@@ -215,47 +325,167 @@ impl Opts {
 impl Opts {
     /// init opts data
     pub fn init(&self) -> Result<Box<dyn Any>, String> {
+        // diagnose must run even when no source address can be found
+        if self.model == ScanModelType::Diagnose {
+            let src_ip = if self.src_ip.is_empty() {
+                None
+            } else {
+                Some(
+                    self.src_ip
+                        .parse::<IpAddr>()
+                        .map_err(|e| e.to_string())?,
+                )
+            };
+            let ips =
+                parse_ip_range(&self.ips).map_err(|e| format!("cannot parse targets: {}", e))?;
+            return Ok(Box::new(netns::run_in(self.netns(), move || {
+                diagnose(src_ip, &ips)
+            })?));
+        }
+        // diff only compares saved results
+        if self.model == ScanModelType::Diff {
+            if self.diff.len() != 2 {
+                return Err(String::from(
+                    "diff model needs two result files: --diff <earlier>,<later>",
+                ));
+            }
+            let old = ScanSnapshot::from_file(&self.diff[0])?;
+            let new = ScanSnapshot::from_file(&self.diff[1])?;
+            return Ok(Box::new(ScanDiff::compare(&old, &new)?));
+        }
+        // monitor re-runs the sync scan of the same options
+        if self.model == ScanModelType::Monitor {
+            return Ok(Box::new(self.init_monitor()?));
+        }
+        // policy checks saved results or a sync scan of the same options
+        if self.model == ScanModelType::Policy {
+            return Ok(Box::new(self.init_policy()?));
+        }
+        // exporter serves probes and a scheduled sync scan of the same options
+        if self.model == ScanModelType::Exporter {
+            return self.init_exporter();
+        }
+        if self.netns().is_some() {
+            if let ScanModelType::Os | ScanModelType::Traceroute = self.model {
+                return Err(format!("--netns is not supported by {} model", self.model));
+            }
+        }
         // if not set interface ip, then throgh tcp find interface ip;
         let src_ip = if !self.src_ip.is_empty() {
             match self.src_ip.parse::<IpAddr>() {
                 Ok(ip) => ip,
-                Err(e) => return Err(String::from(e.to_string())),
+                Err(e) => return Err(e.to_string()),
             }
         } else {
-            interface::get_local_ipaddr()?
+            netns::run_in(self.netns(), interface::get_local_ipaddr)??
         };
+        // load service names before ports are resolved
+        if self.system_services {
+            DATA.load_system_services()?;
+        }
+        if !self.services_file.is_empty() {
+            DATA.load_services_file(&self.services_file)?;
+        }
         // parse ports
-        let ports = parse_str_ports(&self.ports);
-        // parse ips
-        let ips = parse_ip_range(&self.ips).unwrap_or(vec![]);
+        let exclusions = self.exclusions()?;
+        let port_spec = parse_port_spec(&self.ports)?;
+        let mut ports = port_spec.tcp.clone();
+        let mut udp_ports = port_spec.udp_to_scan();
+        if self.top_ports > 0 {
+            // a UDP ping probes the ports of the destination over UDP
+            let proto = match self.scan {
+                ScanOrderType::Udp => Protocol::Udp,
+                _ => Protocol::Tcp,
+            };
+            add_ports(&mut ports, DATA.top_ports(proto, self.top_ports)?);
+            if port_spec.udp_selected {
+                add_ports(&mut udp_ports, DATA.top_ports(Protocol::Udp, self.top_ports)?);
+            }
+        }
+        if !ports.is_empty() || !udp_ports.is_empty() {
+            ports.retain(|port| !exclusions.excludes_port(*port));
+            udp_ports.retain(|port| !exclusions.excludes_port(*port));
+            if ports.is_empty() && udp_ports.is_empty() {
+                return Err(String::from("all ports are excluded"));
+            }
+        }
+        // parse ips, out of scope targets are dropped before anything is sent
+        let scope = self.scope()?;
+        let (ips, mut scope_violations) = self.resolve_targets(&self.ips, &scope, &exclusions)?;
+        let mut destinations: Vec<Destination> = ips
+            .into_iter()
+            .map(|ip| {
+                let mut dst = Destination::new(ip, ports.clone());
+                dst.set_dst_udp_port(udp_ports.clone());
+                dst
+            })
+            .collect();
+        // targets of the input file keep their own ports
+        if !self.input_file.is_empty() {
+            for target in read_targets_file(&self.input_file)? {
+                let (mut target_ports, mut target_udp_ports) = (target.ports, target.udp_ports);
+                if target_ports.is_empty() && target_udp_ports.is_empty() {
+                    target_ports = ports.clone();
+                    target_udp_ports = udp_ports.clone();
+                } else {
+                    target_ports.retain(|port| !exclusions.excludes_port(*port));
+                    target_udp_ports.retain(|port| !exclusions.excludes_port(*port));
+                    if target_ports.is_empty() && target_udp_ports.is_empty() {
+                        continue;
+                    }
+                }
+                let (ips, mut violations) =
+                    self.resolve_targets(&vec![target.target], &scope, &exclusions)?;
+                scope_violations.append(&mut violations);
+                for ip in ips {
+                    let mut dst = Destination::new(ip, target_ports.clone());
+                    dst.set_dst_udp_port(target_udp_ports.clone());
+                    destinations.push(dst);
+                }
+            }
+        }
+        let ips: Vec<IpAddr> = destinations.iter().map(|dst| dst.dst_ip).collect();
+        let has_ports = destinations.iter().any(|dst| dst.has_ports());
         match self.model {
             ScanModelType::Sync => {
                 // sync scan
                 #[cfg(feature = "sync")]
                 {
-                    let mut scanner = sync_scan::Scanner::new(src_ip)?;
+                    let mut scanner = match self.netns() {
+                        Some(name) => sync_scan::Scanner::new_in_netns(src_ip, name)?,
+                        None => sync_scan::Scanner::new(src_ip)?,
+                    };
                     // set methods
-                    if self.command.len() > 0 {
-                        scanner.set_method(&&self.command)?;
-                    }
-                    for ip in ips {
-                        // add scan target
-                        scanner.add_destination(Destination::new(ip, ports.clone()));
+                    if !self.command.is_empty() {
+                        scanner.set_method(&self.command)?;
                     }
+                    // add scan target
+                    scanner.set_destinations(destinations.clone());
                     // set scan rate
                     scanner.set_send_rate(Duration::from_millis(self.rate));
                     // set timeout
                     scanner.set_timeout(Duration::from_millis(self.timeout));
                     // set wating for time of during
                     scanner.set_wait_time(Duration::from_millis(self.wait_time));
+                    // set connection timeout
+                    scanner.set_connect_timeout(Duration::from_millis(self.connect_timeout));
+                    // set scope
+                    if let Some(scope) = &scope {
+                        scanner.set_scope(scope.clone());
+                    }
+                    scanner.set_scope_violations(scope_violations.clone());
+                    // set exclusions
+                    scanner.set_exclusions(exclusions.clone());
                     // set scan type
                     if let Some(t) = parse_scan_type(&self.scan) {
                         scanner.set_scan_type(t);
-                    } else if ports.len() > 0 {
+                    } else if has_ports {
                         scanner.set_scan_type(ScanType::TcpConnectScan);
                     } else {
                         scanner.set_scan_type(ScanType::IcmpPingScan);
                     }
+                    // fall back to unprivileged mode
+                    scanner.set_unprivileged(self.unprivileged_mode());
                     Ok(Box::new(scanner))
                 }
                 #[cfg(not(feature = "sync"))]
@@ -265,39 +495,95 @@ impl Opts {
                 // async scan
                 #[cfg(feature = "async")]
                 {
-                    let mut scanner = async_scan::Scanner::new(src_ip)?;
-                    for ip in ips {
-                        // add scan target
-                        scanner.add_destination(Destination::new(ip, ports.clone()));
-                    }
+                    let mut scanner = match self.netns() {
+                        Some(name) => async_scan::Scanner::new_in_netns(src_ip, name)?,
+                        None => async_scan::Scanner::new(src_ip)?,
+                    };
+                    // add scan target
+                    scanner.set_destinations(destinations.clone());
                     // set scan rate
                     scanner.set_send_rate(Duration::from_millis(self.rate));
                     // set timeout
                     scanner.set_timeout(Duration::from_millis(self.timeout));
                     // set wating for time of during
                     scanner.set_wait_time(Duration::from_millis(self.wait_time));
-
+                    // set concurrency
+                    scanner.set_hosts_concurrency(self.hosts_concurrency);
+                    scanner.set_ports_concurrency(self.ports_concurrency);
+                    // set connection timeout
+                    scanner.set_connect_timeout(Duration::from_millis(self.connect_timeout));
+                    // set scope
+                    if let Some(scope) = &scope {
+                        scanner.set_scope(scope.clone());
+                    }
+                    scanner.set_scope_violations(scope_violations.clone());
+                    // set exclusions
+                    scanner.set_exclusions(exclusions.clone());
                     // set scan type
                     if let Some(t) = parse_scan_type(&self.scan) {
                         scanner.set_scan_type(t);
-                    } else if ports.len() > 0 {
+                    } else if has_ports {
                         scanner.set_scan_type(ScanType::TcpConnectScan);
                     } else {
                         scanner.set_scan_type(ScanType::IcmpPingScan);
                     }
+                    // fall back to unprivileged mode
+                    scanner.set_unprivileged(self.unprivileged_mode());
                     Ok(Box::new(scanner))
                 }
                 #[cfg(not(feature = "async"))]
                 Ok(Box::new(()))
             }
+            ScanModelType::Tokio => {
+                // tokio scan
+                #[cfg(feature = "tokio")]
+                {
+                    let mut scanner = match self.netns() {
+                        Some(name) => tokio_scan::Scanner::new_in_netns(src_ip, name)?,
+                        None => tokio_scan::Scanner::new(src_ip)?,
+                    };
+                    // add scan target
+                    scanner.set_destinations(destinations.clone());
+                    // set scan rate
+                    scanner.set_send_rate(Duration::from_millis(self.rate));
+                    // set timeout
+                    scanner.set_timeout(Duration::from_millis(self.timeout));
+                    // set wating for time of during
+                    scanner.set_wait_time(Duration::from_millis(self.wait_time));
+                    // set concurrency
+                    scanner.set_concurrency(self.ports_concurrency);
+                    // set connection timeout
+                    scanner.set_connect_timeout(Duration::from_millis(self.connect_timeout));
+                    // set scope
+                    if let Some(scope) = &scope {
+                        scanner.set_scope(scope.clone());
+                    }
+                    scanner.set_scope_violations(scope_violations.clone());
+                    // set exclusions
+                    scanner.set_exclusions(exclusions.clone());
+                    // set scan type
+                    if let Some(t) = parse_scan_type(&self.scan) {
+                        scanner.set_scan_type(t);
+                    } else if has_ports {
+                        scanner.set_scan_type(ScanType::TcpConnectScan);
+                    } else {
+                        scanner.set_scan_type(ScanType::IcmpPingScan);
+                    }
+                    // fall back to unprivileged mode
+                    scanner.set_unprivileged(self.unprivileged_mode());
+                    Ok(Box::new(scanner))
+                }
+                #[cfg(not(feature = "tokio"))]
+                Ok(Box::new(()))
+            }
             ScanModelType::Os => {
                 // init OS(osscan guess) data
                 #[cfg(feature = "os")]
                 {
                     let mut scanner = os::Scanner::new(src_ip)?;
                     // set methods
-                    if self.command.len() > 0 {
-                        scanner.set_method(&&self.command)?;
+                    if !self.command.is_empty() {
+                        scanner.set_method(&self.command)?;
                     }
                     // set scan rate
                     scanner.set_send_rate(Duration::from_millis(self.rate));
@@ -327,44 +613,234 @@ impl Opts {
                 // scan service
                 #[cfg(feature = "service")]
                 {
-                    let mut scanner = sync_scan::Scanner::new(src_ip)?;
+                    let mut scanner = match self.netns() {
+                        Some(name) => sync_scan::Scanner::new_in_netns(src_ip, name)?,
+                        None => sync_scan::Scanner::new(src_ip)?,
+                    };
                     // set scan rate
                     scanner.set_send_rate(Duration::from_millis(self.rate));
                     // set tiemout
                     scanner.set_timeout(Duration::from_millis(self.timeout));
                     // set scan watting for time
                     scanner.set_wait_time(Duration::from_millis(self.wait_time));
+                    // set scope
+                    if let Some(scope) = &scope {
+                        scanner.set_scope(scope.clone());
+                    }
+                    scanner.set_scope_violations(scope_violations.clone());
+                    // set exclusions
+                    scanner.set_exclusions(exclusions.clone());
                     // set scan type
                     if let Some(t) = parse_scan_type(&self.scan) {
                         scanner.set_scan_type(t);
                     } else {
                         scanner.set_scan_type(ScanType::TcpSynScan);
                     }
-                    for ip in ips {
-                        // add scan target
-                        scanner.add_destination(Destination::new(ip, ports.clone()));
-                    }
+                    // add scan target
+                    scanner.set_destinations(destinations.clone());
+                    // fall back to unprivileged mode
+                    scanner.set_unprivileged(self.unprivileged_mode());
                     Ok(Box::new(scanner))
                 }
                 #[cfg(not(feature = "service"))]
                 Ok(Box::new(()))
             }
-            ScanModelType::Dns => return Ok(Box::new(parse_dns(self.ips.clone()))),
+            ScanModelType::Dns => Ok(Box::new(parse_dns(self.ips.clone()))),
             ScanModelType::Traceroute => {
-                return Ok(Box::new(Tracert::new(
-                    self.ips.clone(),
+                // resolved addresses are traced once scope, exclusions or an input file are set
+                let targets = if self.scope_file.is_empty()
+                    && exclusions.hosts.is_empty()
+                    && self.input_file.is_empty()
+                {
+                    self.ips.clone()
+                } else {
+                    ips.iter().map(|ip| ip.to_string()).collect()
+                };
+                Ok(Box::new(Tracert::new(
+                    targets,
                     if self.src_ip.is_empty() {
                         None
                     } else {
-                        match self.src_ip.parse::<IpAddr>() {
-                            Ok(ip) => Some(ip),
-                            Err(_) => None,
-                        }
+                        self.src_ip.parse::<IpAddr>().ok()
                     },
                 )))
             }
-            ScanModelType::None => Ok(Box::new(())),
+            ScanModelType::Diagnose
+            | ScanModelType::Diff
+            | ScanModelType::Monitor
+            | ScanModelType::Policy
+            | ScanModelType::Exporter
+            | ScanModelType::None => Ok(Box::new(())),
+        }
+    }
+    /// Monitor of the sync scan of these options
+    fn init_monitor(&self) -> Result<Monitor, String> {
+        #[cfg(feature = "sync")]
+        {
+            use super::history::{FileStore, MemoryStore};
+            use super::monitor::{parse_notifier, StdoutNotifier};
+            let mut opts = self.clone();
+            opts.model = ScanModelType::Sync;
+            let mut scanner = match opts.init()?.downcast::<sync_scan::Scanner>() {
+                Ok(scanner) => scanner,
+                Err(_) => return Err(String::from("monitor model needs a sync scanner")),
+            };
+            let prx = scanner.get_progress_receiver();
+            let scan = move || {
+                let result = scanner.scan(None);
+                // nobody reads the progress of a monitor run
+                while prx.lock().unwrap().try_recv().is_ok() {}
+                Ok(ScanSnapshot::new(result))
+            };
+            let interval = Duration::from_secs(self.interval);
+            let mut monitor = if self.history.is_empty() {
+                Monitor::new(scan, MemoryStore::new(), interval)
+            } else {
+                Monitor::new(scan, FileStore::open(&self.history)?, interval)
+            };
+            monitor.set_param("ips", &self.ips.join(","));
+            monitor.set_param("ports", &self.ports.join(","));
+            monitor.set_param("scan", &self.scan.to_string());
+            if self.notify.is_empty() {
+                monitor.add_notifier(StdoutNotifier);
+            }
+            for spec in &self.notify {
+                monitor.add_notifier(parse_notifier(spec)?);
+            }
+            Ok(monitor)
+        }
+        #[cfg(not(feature = "sync"))]
+        Err(String::from("monitor model needs the sync feature"))
+    }
+    /// Policy report of `--results`, or of a sync scan of these options
+    fn init_policy(&self) -> Result<PolicyReport, String> {
+        if self.policy.is_empty() {
+            return Err(String::from("policy model needs a policy file: --policy <file>"));
+        }
+        let policy = Policy::from_file(&self.policy)?;
+        if !self.results.is_empty() {
+            return policy.evaluate_snapshot(&ScanSnapshot::from_file(&self.results)?);
+        }
+        #[cfg(feature = "sync")]
+        {
+            let mut opts = self.clone();
+            opts.model = ScanModelType::Sync;
+            let mut scanner = match opts.init()?.downcast::<sync_scan::Scanner>() {
+                Ok(scanner) => scanner,
+                Err(_) => return Err(String::from("policy model needs a sync scanner")),
+            };
+            let prx = scanner.get_progress_receiver();
+            let result = scanner.scan(None);
+            while prx.lock().unwrap().try_recv().is_ok() {}
+            let snapshot = ScanSnapshot::new(result);
+            // forbidden and required services are also looked up in banners
+            #[cfg(feature = "service")]
+            let snapshot = {
+                use crate::output::Banners;
+                use crate::service::ServiceDetector;
+                let mut snapshot = snapshot;
+                let results: Vec<_> = ServiceDetector::from_result(&snapshot.result)
+                    .iter()
+                    .map(|detector| detector.scan(None))
+                    .collect();
+                snapshot.set_banners(Banners::from_service_results(&results));
+                snapshot
+            };
+            policy.evaluate_snapshot(&snapshot)
+        }
+        #[cfg(not(feature = "sync"))]
+        Err(String::from("policy model needs --results or the sync feature"))
+    }
+    /// Exporter of these options; targets are scanned every `--interval` with service detection
+    fn init_exporter(&self) -> Result<Box<dyn Any>, String> {
+        #[cfg(feature = "exporter")]
+        {
+            use super::exporter::Exporter;
+            let listen = self
+                .listen
+                .parse()
+                .map_err(|e| format!("{}: {}", self.listen, e))?;
+            let src_ip = if self.src_ip.is_empty() {
+                interface::get_local_ipaddr()?
+            } else {
+                self.src_ip.parse::<IpAddr>().map_err(|e| e.to_string())?
+            };
+            let mut exporter = Exporter::new(listen, src_ip);
+            if !self.modules.is_empty() {
+                exporter.load_modules(&self.modules)?;
+            }
+            if let Some(scope) = self.scope()? {
+                exporter.set_scope(scope);
+            }
+            exporter.set_exclusions(self.exclusions()?);
+            if !self.ips.is_empty() || !self.input_file.is_empty() {
+                let mut opts = self.clone();
+                opts.model = ScanModelType::Sync;
+                let scanner = match opts.init()?.downcast::<sync_scan::Scanner>() {
+                    Ok(scanner) => scanner,
+                    Err(_) => return Err(String::from("exporter model needs a sync scanner")),
+                };
+                exporter.set_scanner(*scanner, Duration::from_secs(self.interval));
+                exporter.set_service(true);
+            }
+            Ok(Box::new(exporter))
+        }
+        #[cfg(not(feature = "exporter"))]
+        Err(String::from("exporter model needs the exporter feature"))
+    }
+    /// Resolve targets, dropping excluded and out of scope addresses
+    ///
+    /// Out of scope addresses that are not excluded are returned as violations.
+    fn resolve_targets(
+        &self,
+        input: &Vec<String>,
+        scope: &Option<Scope>,
+        exclusions: &Exclusions,
+    ) -> Result<(Vec<IpAddr>, Vec<ScopeViolation>), String> {
+        match scope {
+            Some(scope) => parse_ip_range_in_scope_except(input, scope, exclusions),
+            None => Ok((parse_ip_range_except(input, exclusions)?, vec![])),
+        }
+    }
+    /// Load exclusions from `--exclude`, `--exclude-file` and `--exclude-ports`
+    pub fn exclusions(&self) -> Result<Exclusions, String> {
+        let mut exclusions = Exclusions::new();
+        for host in self.exclude.iter() {
+            exclusions.add_host(host)?;
+        }
+        if !self.exclude_file.is_empty() {
+            exclusions.add_hosts_file(&self.exclude_file)?;
+        }
+        for ports in self.exclude_ports.iter() {
+            exclusions.add_ports(ports)?;
+        }
+        Ok(exclusions)
+    }
+    /// Load scope from `--scope-file`
+    pub fn scope(&self) -> Result<Option<Scope>, String> {
+        if self.scope_file.is_empty() {
+            return Ok(None);
         }
+        let mut scope = Scope::from_file(&self.scope_file)?;
+        scope.set_strict(self.scope_strict);
+        Ok(Some(scope))
+    }
+    /// Network namespace to run in
+    fn netns(&self) -> Option<&str> {
+        if self.netns.is_empty() {
+            None
+        } else {
+            Some(&self.netns)
+        }
+    }
+    /// Scan in unprivileged mode if asked to or raw sockets are not permitted(Linux only)
+    ///
+    /// Scanners report the degraded scan type in the warnings of the scan result.
+    fn unprivileged_mode(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.unprivileged || !privilege::has_raw_socket_privilege();
+        #[cfg(not(target_os = "linux"))]
+        false
     }
 }
 
@@ -399,13 +875,108 @@ fn parse_dns(target: Vec<String>) -> DnsResults {
 
 /// parse ip from string list
 pub fn parse_ip_range(input: &Vec<String>) -> Result<Vec<IpAddr>, String> {
+    parse_ip_range_except(input, &Exclusions::new())
+}
+
+/// parse ip from string list, skipping excluded addresses while ranges are generated
+pub fn parse_ip_range_except(
+    input: &Vec<String>,
+    exclusions: &Exclusions,
+) -> Result<Vec<IpAddr>, String> {
     let mut ips = vec![];
+    walk_ip_range(input, exclusions, |_, ip| {
+        ips.push(ip);
+        Ok(())
+    })?;
+    Ok(ips)
+}
+
+/// parse ip from string list and check the addresses against the scope
+///
+/// Host names are checked after they are resolved. In strict mode the first
+/// violation is returned as error, otherwise violating addresses are returned
+/// apart from the allowed ones. Out of scope parts of a network are single
+/// violations carrying their network address.
+pub fn parse_ip_range_in_scope(
+    input: &Vec<String>,
+    scope: &Scope,
+) -> Result<(Vec<IpAddr>, Vec<ScopeViolation>), String> {
+    parse_ip_range_in_scope_except(input, scope, &Exclusions::new())
+}
+
+/// parse ip from string list, checking the addresses against the scope and
+/// skipping excluded addresses while ranges are generated
+pub fn parse_ip_range_in_scope_except(
+    input: &Vec<String>,
+    scope: &Scope,
+    exclusions: &Exclusions,
+) -> Result<(Vec<IpAddr>, Vec<ScopeViolation>), String> {
+    let mut ips = vec![];
+    let mut violations = vec![];
+    // out of scope parts of networks are refused as a whole instead of address by address
+    let mut walked = vec![];
+    for s in input {
+        let ipnet = match s.parse::<IpNet>() {
+            Ok(ipnet) if exclusions.excludes_net(&ipnet) => continue,
+            Ok(ipnet) => ipnet,
+            Err(_) => {
+                walked.push(s.clone());
+                continue;
+            }
+        };
+        let refused = scope.refused_nets(&ipnet);
+        for (net, reason) in refused.iter() {
+            if exclusions.excludes_net(net) {
+                continue;
+            }
+            let violation = ScopeViolation {
+                target: s.clone(),
+                ip: net.network(),
+                reason: if net.prefix_len() < net.max_prefix_len() {
+                    format!("{} {}", net, reason)
+                } else {
+                    reason.clone()
+                },
+            };
+            if scope.get_strict() {
+                return Err(violation.to_string());
+            }
+            violations.push(violation);
+        }
+        if !refused.iter().any(|(net, _)| *net == ipnet) {
+            walked.push(s.clone());
+        }
+    }
+    walk_ip_range(&walked, exclusions, |target, ip| {
+        match scope.violation(target, ip) {
+            None => ips.push(ip),
+            // already refused with its network
+            Some(_) if target.contains('/') => {}
+            Some(violation) if scope.get_strict() => return Err(violation.to_string()),
+            Some(violation) => violations.push(violation),
+        }
+        Ok(())
+    })?;
+    Ok((ips, violations))
+}
+
+/// walk the addresses of a string list without collecting them, skipping excluded addresses
+fn walk_ip_range<F>(input: &Vec<String>, exclusions: &Exclusions, mut f: F) -> Result<(), String>
+where
+    F: FnMut(&str, IpAddr) -> Result<(), String>,
+{
     for s in input {
         if s.contains('/') {
             // ipv4 parse example: 192.168.8.0/24 -> [192.168.8.1..192.168.8.254];
             // ipv6 parse fd00::/32 -> [fd00::..]
             match s.parse::<IpNet>() {
-                Ok(ipnet) => ips.append(&mut ipnet.hosts().collect::<Vec<IpAddr>>()),
+                // fully excluded networks are not walked at all
+                Ok(ipnet) if exclusions.excludes_net(&ipnet) => {}
+                Ok(ipnet) => {
+                    for ip in ipnet.hosts().filter(|ip| !exclusions.excludes_host(ip)) {
+                        f(s, ip)?;
+                    }
+                }
                 Err(e) => return Err(e.to_string()),
             }
         } else if s.contains('-') {
@@ -425,7 +996,10 @@ pub fn parse_ip_range(input: &Vec<String>) -> Result<Vec<IpAddr>, String> {
                     for v2 in range_ip[1].0..range_ip[1].1 + 1 {
                         for v3 in range_ip[2].0..range_ip[2].1 + 1 {
                             for v4 in range_ip[3].0..range_ip[3].1 + 1 {
-                                ips.push(IpAddr::V4(Ipv4Addr::new(v1, v2, v3, v4)))
+                                let ip = IpAddr::V4(Ipv4Addr::new(v1, v2, v3, v4));
+                                if !exclusions.excludes_host(&ip) {
+                                    f(s, ip)?;
+                                }
                             }
                         }
                     }
@@ -437,45 +1011,38 @@ pub fn parse_ip_range(input: &Vec<String>) -> Result<Vec<IpAddr>, String> {
             // ipv4 parse exmaple: baidu.com -> [110.242.68.66, 110.242.68.67]
             // ipv6 parse exmaple:
             match dns::lookup_host(s) {
-                Ok(mut addrs) => ips.append(&mut addrs),
+                Ok(addrs) => {
+                    for ip in addrs.into_iter().filter(|ip| !exclusions.excludes_host(ip)) {
+                        f(s, ip)?;
+                    }
+                }
                 Err(_) => {
                     // ipv4 parse example: 192.168.8.1
                     // ipv6 parse exmaple: fe80::ac47:a2d1:c566:2c6d
                     match s.parse::<IpAddr>() {
-                        Ok(ipnet) => ips.push(ipnet),
+                        Ok(ip) if exclusions.excludes_host(&ip) => {}
+                        Ok(ip) => f(s, ip)?,
                         Err(e) => return Err(e.to_string()),
                     }
                 }
             }
         }
     }
-    Ok(ips)
+    Ok(())
 }
 
-/// parse port from string
-fn parse_str_ports(input: &Vec<String>) -> Vec<u16> {
-    let mut ports = vec![];
-    for s in input {
-        if s.contains('-') {
-            if let Some(r) = s.split_once('-') {
-                if let Ok(start) = r.0.parse::<u16>() {
-                    if let Ok(end) = r.1.parse::<u16>() {
-                        if start < end {
-                            ports.append(&mut (start..end).collect::<Vec<u16>>());
-                        }
-                    }
-                };
-            }
-        } else {
-            if let Ok(port) = s.parse::<u16>() {
-                ports.push(port)
-            }
+/// append ports that are not in the list yet
+fn add_ports(ports: &mut Vec<u16>, more: Vec<u16>) {
+    let mut seen: HashSet<u16> = ports.iter().copied().collect();
+    for port in more {
+        if seen.insert(port) {
+            ports.push(port);
         }
     }
-    ports
 }
+
 /// parse scan type
-fn parse_scan_type(scan_type: &ScanOrderType) -> Option<ScanType> {
+pub(crate) fn parse_scan_type(scan_type: &ScanOrderType) -> Option<ScanType> {
     match scan_type {
         ScanOrderType::None => None,
         ScanOrderType::Icmp => Some(ScanType::IcmpPingScan),
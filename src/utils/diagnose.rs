@@ -0,0 +1,471 @@
+use super::privilege;
+use crate::interface;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+/// Status of a single diagnostic check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Check passed
+    Pass,
+    /// Scans may work but results can be incomplete
+    Warn,
+    /// Scans that depend on it will return nothing
+    Fail,
+    /// Check does not apply to this platform or setup
+    Skip,
+    /// Check could not be run, the state is not known
+    Unknown,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+                CheckStatus::Skip => "SKIP",
+                CheckStatus::Unknown => "UNKNOWN",
+            }
+        )
+    }
+}
+
+/// Result of a single diagnostic check
+#[derive(Clone, Debug)]
+pub struct Check {
+    /// Name of the check
+    pub name: String,
+    /// Status of the check
+    pub status: CheckStatus,
+    /// What was found
+    pub message: String,
+    /// What to do about it
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, message: String, hint: Option<&str>) -> Check {
+        Check {
+            name: name.to_string(),
+            status,
+            message,
+            hint: hint.map(|h| h.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.name, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       -> {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Preflight diagnostics report
+#[derive(Clone, Debug, Default)]
+pub struct DiagnoseReport {
+    /// Checks in the order they were run
+    pub checks: Vec<Check>,
+}
+
+impl DiagnoseReport {
+    /// Returns true if no check failed
+    pub fn is_ok(&self) -> bool {
+        !self.checks.iter().any(|c| c.status == CheckStatus::Fail)
+    }
+    /// Get failed checks
+    pub fn failures(&self) -> Vec<&Check> {
+        self.checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Fail)
+            .collect()
+    }
+    /// Get checks that failed or need attention
+    pub fn problems(&self) -> Vec<&Check> {
+        self.checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Fail || c.status == CheckStatus::Warn)
+            .collect()
+    }
+    fn push(&mut self, check: Check) {
+        self.checks.push(check);
+    }
+}
+
+impl fmt::Display for DiagnoseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in self.checks.iter() {
+            writeln!(f, "{}", check)?;
+        }
+        write!(
+            f,
+            "{} checks, {} problems",
+            self.checks.len(),
+            self.problems().len()
+        )
+    }
+}
+
+/// Read effective capability mask of this process
+#[cfg(target_os = "linux")]
+fn read_cap_eff() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("CapEff:"))?;
+    u64::from_str_radix(line.trim_start_matches("CapEff:").trim(), 16).ok()
+}
+
+fn check_capabilities(report: &mut DiagnoseReport) {
+    #[cfg(target_os = "linux")]
+    {
+        const CAP_NET_RAW: u64 = 1 << 13;
+        const CAP_NET_ADMIN: u64 = 1 << 12;
+        report.push(match read_cap_eff() {
+            Some(caps) if caps & CAP_NET_RAW != 0 => Check::new(
+                "capabilities",
+                CheckStatus::Pass,
+                format!(
+                    "CAP_NET_RAW is effective{}",
+                    if caps & CAP_NET_ADMIN != 0 {
+                        ", CAP_NET_ADMIN too"
+                    } else {
+                        ""
+                    }
+                ),
+                None,
+            ),
+            Some(_) => Check::new(
+                "capabilities",
+                CheckStatus::Fail,
+                String::from("CAP_NET_RAW is not effective"),
+                Some("run as root or `setcap cap_net_raw,cap_net_admin+ep <binary>`; otherwise use --unprivileged"),
+            ),
+            None => Check::new(
+                "capabilities",
+                CheckStatus::Warn,
+                String::from("cannot read /proc/self/status"),
+                None,
+            ),
+        });
+    }
+    #[cfg(not(target_os = "linux"))]
+    report.push(Check::new(
+        "capabilities",
+        CheckStatus::Skip,
+        String::from("capabilities are Linux only"),
+        None,
+    ));
+}
+
+fn check_sockets(report: &mut DiagnoseReport) {
+    report.push(if privilege::has_raw_socket_privilege() {
+        Check::new(
+            "raw socket",
+            CheckStatus::Pass,
+            String::from("raw ICMP socket created"),
+            None,
+        )
+    } else {
+        Check::new(
+            "raw socket",
+            CheckStatus::Fail,
+            String::from("raw ICMP socket refused"),
+            Some("SYN, ICMP and UDP scans need raw sockets; run privileged or use --unprivileged"),
+        )
+    });
+    #[cfg(target_os = "linux")]
+    report.push(if privilege::has_icmp_datagram_socket() {
+        Check::new(
+            "icmp datagram socket",
+            CheckStatus::Pass,
+            String::from("unprivileged ICMP socket created"),
+            None,
+        )
+    } else {
+        Check::new(
+            "icmp datagram socket",
+            CheckStatus::Warn,
+            String::from("unprivileged ICMP socket refused"),
+            Some("unprivileged host discovery needs `sysctl -w net.ipv4.ping_group_range=\"0 2147483647\"`"),
+        )
+    });
+}
+
+fn check_interface(
+    report: &mut DiagnoseReport,
+    src_ip: IpAddr,
+) -> Option<pnet_datalink::NetworkInterface> {
+    let iface = pnet_datalink::interfaces()
+        .into_iter()
+        .find(|iface| iface.ips.iter().any(|ip| ip.ip() == src_ip));
+    match iface {
+        Some(iface) => {
+            let mac = iface.mac.unwrap_or(pnet_datalink::MacAddr::zero());
+            report.push(if !iface.is_up() {
+                Check::new(
+                    "interface",
+                    CheckStatus::Fail,
+                    format!("{} ({}) is down", iface.name, src_ip),
+                    Some("bring the interface up or pass another --src-ip"),
+                )
+            } else if mac == pnet_datalink::MacAddr::zero() {
+                Check::new(
+                    "interface",
+                    CheckStatus::Warn,
+                    format!("{} ({}) has no MAC address", iface.name, src_ip),
+                    Some("sync and async scanners need an Ethernet interface; use --unprivileged or the tokio model"),
+                )
+            } else {
+                Check::new(
+                    "interface",
+                    CheckStatus::Pass,
+                    format!("{} ({}) index {} mac {}", iface.name, src_ip, iface.index, mac),
+                    None,
+                )
+            });
+            Some(iface)
+        }
+        None => {
+            report.push(Check::new(
+                "interface",
+                CheckStatus::Fail,
+                format!("no interface carries {}", src_ip),
+                Some("pass --src-ip with an address of a local interface"),
+            ));
+            None
+        }
+    }
+}
+
+fn check_datalink(report: &mut DiagnoseReport, iface: &pnet_datalink::NetworkInterface) {
+    let config = pnet_datalink::Config {
+        read_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    report.push(match pnet_datalink::channel(iface, config) {
+        Ok(pnet_datalink::Channel::Ethernet(_, _)) => Check::new(
+            "datalink channel",
+            CheckStatus::Pass,
+            format!("ethernet channel opened on {}", iface.name),
+            None,
+        ),
+        Ok(_) => Check::new(
+            "datalink channel",
+            CheckStatus::Fail,
+            format!("{} does not provide an ethernet channel", iface.name),
+            None,
+        ),
+        Err(e) => Check::new(
+            "datalink channel",
+            CheckStatus::Fail,
+            format!("cannot open channel on {}: {}", iface.name, e),
+            Some("receiving replies needs CAP_NET_RAW(Linux) or Npcap/WinPcap(windows)"),
+        ),
+    });
+}
+
+fn check_targets(report: &mut DiagnoseReport, src_ip: IpAddr, targets: &[IpAddr]) {
+    for target in targets {
+        let name = format!("route {}", target);
+        report.push(match interface::get_route_source(*target) {
+            Ok(route_src) if route_src == src_ip => Check::new(
+                &name,
+                CheckStatus::Pass,
+                format!("reached through {}", route_src),
+                None,
+            ),
+            Ok(route_src) if route_src.is_loopback() && !src_ip.is_loopback() => Check::new(
+                &name,
+                CheckStatus::Warn,
+                format!("is local, routed through {} instead of {}", route_src, src_ip),
+                Some("replies to local targets never cross the scan interface; scan it through loopback"),
+            ),
+            Ok(route_src) => Check::new(
+                &name,
+                CheckStatus::Warn,
+                format!("routed through {} instead of {}", route_src, src_ip),
+                Some("replies arrive on another interface; pass --src-ip matching this route"),
+            ),
+            Err(e) => Check::new(
+                &name,
+                CheckStatus::Fail,
+                format!("no route: {}", e),
+                Some("add a route to the target network or check the default gateway"),
+            ),
+        });
+    }
+}
+
+fn check_gateway(report: &mut DiagnoseReport) {
+    let gateway = match interface::get_default_gateway() {
+        Ok(gateway) => gateway,
+        Err(e) => {
+            report.push(Check::new(
+                "gateway",
+                CheckStatus::Warn,
+                e,
+                Some("only on-link targets are reachable without a default gateway"),
+            ));
+            return;
+        }
+    };
+    report.push(Check::new(
+        "gateway",
+        CheckStatus::Pass,
+        format!("default gateway {}", gateway.ip_addr),
+        None,
+    ));
+    #[cfg(target_os = "linux")]
+    if let IpAddr::V4(gateway_ip) = gateway.ip_addr {
+        let mut mac = interface::get_neighbour_macaddr(gateway_ip);
+        if mac.is_none() {
+            // Any datagram to the gateway makes the kernel resolve it.
+            if let Ok(socket) = UdpSocket::bind("0.0.0.0:0") {
+                let _ = socket.send_to(&[], SocketAddr::new(gateway.ip_addr, 9));
+            }
+            thread::sleep(Duration::from_millis(500));
+            mac = interface::get_neighbour_macaddr(gateway_ip);
+        }
+        report.push(match mac {
+            Some(mac) => Check::new(
+                "gateway arp",
+                CheckStatus::Pass,
+                format!("{} is at {}", gateway_ip, mac.address()),
+                None,
+            ),
+            None => Check::new(
+                "gateway arp",
+                CheckStatus::Fail,
+                format!("{} did not answer ARP", gateway_ip),
+                Some("the gateway is unreachable on the link; check cabling, VLAN or the gateway address"),
+            ),
+        });
+    }
+    #[cfg(not(target_os = "linux"))]
+    report.push(Check::new(
+        "gateway arp",
+        CheckStatus::Pass,
+        format!("gateway mac {}", gateway.mac_addr.address()),
+        None,
+    ));
+}
+
+fn check_loopback(report: &mut DiagnoseReport) {
+    let connected = TcpListener::bind("127.0.0.1:0").and_then(|listener| {
+        let addr = listener.local_addr()?;
+        TcpStream::connect_timeout(&addr, Duration::from_millis(500))
+    });
+    report.push(match connected {
+        Ok(_) => Check::new(
+            "loopback",
+            CheckStatus::Pass,
+            String::from("TCP connect over loopback works"),
+            None,
+        ),
+        Err(e) => Check::new(
+            "loopback",
+            CheckStatus::Fail,
+            format!("TCP connect over loopback failed: {}", e),
+            Some("a local firewall is dropping traffic; check iptables/nftables or the host firewall"),
+        ),
+    });
+}
+
+/// Tables of the nftables ruleset, e.g. `inet filter`
+#[cfg(target_os = "linux")]
+fn nft_tables() -> Result<Vec<String>, String> {
+    let output = std::process::Command::new("nft")
+        .args(["list", "tables"])
+        .output()
+        .map_err(|e| format!("nft: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("table "))
+        .map(|table| table.to_string())
+        .collect())
+}
+
+fn check_firewall(report: &mut DiagnoseReport) {
+    #[cfg(target_os = "linux")]
+    {
+        let iptables = std::fs::read_to_string("/proc/net/ip_tables_names").unwrap_or_default();
+        let mut tables: Vec<String> = iptables
+            .lines()
+            .map(|table| format!("iptables {}", table))
+            .collect();
+        let nft = nft_tables();
+        if let Ok(nft) = &nft {
+            tables.extend(nft.iter().map(|table| format!("nftables {}", table)));
+        }
+        report.push(match nft {
+            _ if !tables.is_empty() => Check::new(
+                "local firewall",
+                CheckStatus::Warn,
+                format!("firewall tables loaded: {}", tables.join(",")),
+                Some("make sure OUTPUT allows the probes and INPUT accepts replies(RST, ICMP)"),
+            ),
+            Ok(_) => Check::new(
+                "local firewall",
+                CheckStatus::Pass,
+                String::from("no iptables or nftables tables loaded"),
+                None,
+            ),
+            Err(e) => Check::new(
+                "local firewall",
+                CheckStatus::Unknown,
+                format!(
+                    "no iptables tables loaded, nftables ruleset not readable: {}",
+                    e
+                ),
+                Some("run as root with nft installed, or inspect the firewall by hand"),
+            ),
+        });
+    }
+    #[cfg(not(target_os = "linux"))]
+    report.push(Check::new(
+        "local firewall",
+        CheckStatus::Skip,
+        String::from("firewall inspection is Linux only"),
+        None,
+    ));
+}
+
+/// Run preflight checks for a scan from `src_ip`(the default route source if None) to `targets`
+pub fn diagnose(src_ip: Option<IpAddr>, targets: &[IpAddr]) -> DiagnoseReport {
+    let mut report = DiagnoseReport::default();
+    check_capabilities(&mut report);
+    check_sockets(&mut report);
+    let src_ip = match src_ip.map_or_else(interface::get_local_ipaddr, Ok) {
+        Ok(ip) => Some(ip),
+        Err(e) => {
+            report.push(Check::new(
+                "source address",
+                CheckStatus::Fail,
+                format!("cannot select a source address: {}", e),
+                Some("no default route; pass --src-ip"),
+            ));
+            None
+        }
+    };
+    if let Some(src_ip) = src_ip {
+        if let Some(iface) = check_interface(&mut report, src_ip) {
+            check_datalink(&mut report, &iface);
+        }
+        check_targets(&mut report, src_ip, targets);
+    }
+    check_gateway(&mut report);
+    check_loopback(&mut report);
+    check_firewall(&mut report);
+    report
+}
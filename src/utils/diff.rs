@@ -0,0 +1,369 @@
+use crate::data::Protocol;
+use crate::frame::result::{PortInfo, PortStatus, ScanResult, ScanStatus};
+use crate::output::{Banners, OsMatch};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::IpAddr;
+
+/// Scan result together with the service banners and OS guesses found for it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanSnapshot {
+    /// Host and port scan result
+    pub result: ScanResult,
+    /// Service detection banners
+    #[serde(default)]
+    pub banners: Banners,
+    /// OS guesses by host
+    #[serde(default)]
+    pub os_matches: BTreeMap<IpAddr, Vec<OsMatch>>,
+}
+
+impl ScanSnapshot {
+    /// Snapshot of a scan result without banners or OS guesses
+    pub fn new(result: ScanResult) -> ScanSnapshot {
+        ScanSnapshot {
+            result,
+            banners: Banners::new(),
+            os_matches: BTreeMap::new(),
+        }
+    }
+    /// Set service detection banners
+    pub fn set_banners(&mut self, banners: Banners) {
+        self.banners = banners;
+    }
+    /// Add OS guess of a host
+    pub fn add_os_match(&mut self, ip: IpAddr, os_match: OsMatch) {
+        self.os_matches.entry(ip).or_default().push(os_match);
+    }
+    /// Parse a serialized snapshot or bare `ScanResult`
+    pub fn from_json(json: &str) -> Result<ScanSnapshot, String> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if value.get("result").is_some() {
+            serde_json::from_value(value).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_value(value)
+                .map(ScanSnapshot::new)
+                .map_err(|e| e.to_string())
+        }
+    }
+    /// Read a serialized snapshot or bare `ScanResult` from a file
+    pub fn from_file(path: &str) -> Result<ScanSnapshot, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        ScanSnapshot::from_json(&json).map_err(|e| format!("{}: {}", path, e))
+    }
+    /// Hosts that answered the scan, see `ScanResult::is_host_up`
+    fn hosts(&self) -> BTreeSet<IpAddr> {
+        self.result
+            .ips
+            .iter()
+            .map(|host| host.ip_addr)
+            .chain(self.result.ip_with_port.keys().copied())
+            .filter(|ip| self.result.is_host_up(*ip))
+            .collect()
+    }
+    /// Ports of a host by protocol and number
+    fn ports(&self, ip: IpAddr) -> BTreeMap<(Protocol, u16), &PortInfo> {
+        self.result
+            .ip_with_port
+            .get(&ip)
+            .map(|ports| ports.iter().map(|p| ((p.protocol, p.port), p)).collect())
+            .unwrap_or_default()
+    }
+    /// Most accurate OS guess of a host
+    fn best_os(&self, ip: IpAddr) -> Option<&str> {
+        self.os_matches
+            .get(&ip)?
+            .iter()
+            .max_by_key(|m| m.accuracy)
+            .map(|m| m.name.as_str())
+    }
+}
+
+impl From<ScanResult> for ScanSnapshot {
+    fn from(result: ScanResult) -> ScanSnapshot {
+        ScanSnapshot::new(result)
+    }
+}
+
+/// Difference between two scans of a host
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    /// Host is new in the later scan
+    HostUp {
+        /// IP address of the host
+        ip: IpAddr,
+    },
+    /// Host of the earlier scan is gone
+    HostDown {
+        /// IP address of the host
+        ip: IpAddr,
+    },
+    /// Port state changed; None when the port is not in one of the scans
+    Port {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Port number
+        port: u16,
+        /// Transport protocol
+        protocol: Protocol,
+        /// Port status in the earlier scan
+        old: Option<PortStatus>,
+        /// Port status in the later scan
+        new: Option<PortStatus>,
+    },
+    /// Service name of a port changed
+    Service {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Port number
+        port: u16,
+        /// Transport protocol
+        protocol: Protocol,
+        /// Service name in the earlier scan
+        old: String,
+        /// Service name in the later scan
+        new: String,
+    },
+    /// Service detection banner of a TCP port changed
+    Banner {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Port number
+        port: u16,
+        /// Banner in the earlier scan
+        old: Option<String>,
+        /// Banner in the later scan
+        new: Option<String>,
+    },
+    /// Best OS guess changed
+    Os {
+        /// IP address of the host
+        ip: IpAddr,
+        /// OS guess in the earlier scan
+        old: Option<String>,
+        /// OS guess in the later scan
+        new: Option<String>,
+    },
+}
+
+impl Change {
+    /// IP address of the host that changed
+    pub fn ip(&self) -> IpAddr {
+        match self {
+            Change::HostUp { ip }
+            | Change::HostDown { ip }
+            | Change::Port { ip, .. }
+            | Change::Service { ip, .. }
+            | Change::Banner { ip, .. }
+            | Change::Os { ip, .. } => *ip,
+        }
+    }
+}
+
+/// Changes between two scans, ordered by host
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanDiff {
+    /// Changes of the later scan against the earlier one
+    pub changes: Vec<Change>,
+}
+
+impl ScanDiff {
+    /// Compare a later scan with an earlier one, both must be completed
+    ///
+    /// A failed or timed out scan misses hosts, which would show as gone.
+    pub fn compare(old: &ScanSnapshot, new: &ScanSnapshot) -> Result<ScanDiff, String> {
+        for (name, snapshot) in [("Earlier", old), ("Later", new)] {
+            let result = &snapshot.result;
+            if result.scan_status != ScanStatus::Done {
+                let mut message = format!(
+                    "{} scan ended with status {:?}, cannot compare",
+                    name, result.scan_status
+                );
+                for error in result.errors.iter() {
+                    message.push_str(": ");
+                    message.push_str(error);
+                }
+                return Err(message);
+            }
+        }
+        Ok(ScanDiff::new(old, new))
+    }
+    /// Compare a later scan with an earlier one
+    pub fn new(old: &ScanSnapshot, new: &ScanSnapshot) -> ScanDiff {
+        let mut changes = vec![];
+        let (old_hosts, new_hosts) = (old.hosts(), new.hosts());
+        for ip in old_hosts.union(&new_hosts) {
+            let ip = *ip;
+            match (old_hosts.contains(&ip), new_hosts.contains(&ip)) {
+                (false, true) => changes.push(Change::HostUp { ip }),
+                (true, false) => changes.push(Change::HostDown { ip }),
+                _ => {}
+            }
+            let (old_ports, new_ports) = (old.ports(ip), new.ports(ip));
+            let keys: BTreeSet<&(Protocol, u16)> =
+                old_ports.keys().chain(new_ports.keys()).collect();
+            for key in keys {
+                let (protocol, port) = *key;
+                let (before, after) = (old_ports.get(key), new_ports.get(key));
+                let (old_status, new_status) = (before.map(|p| p.status), after.map(|p| p.status));
+                if old_status != new_status {
+                    changes.push(Change::Port {
+                        ip,
+                        port,
+                        protocol,
+                        old: old_status,
+                        new: new_status,
+                    });
+                }
+                if let (Some(before), Some(after)) = (before, after) {
+                    if before.describe != after.describe {
+                        changes.push(Change::Service {
+                            ip,
+                            port,
+                            protocol,
+                            old: before.describe.clone(),
+                            new: after.describe.clone(),
+                        });
+                    }
+                }
+            }
+            let empty = BTreeMap::new();
+            let old_banners = old.banners.get_host_banners(ip).unwrap_or(&empty);
+            let new_banners = new.banners.get_host_banners(ip).unwrap_or(&empty);
+            let ports: BTreeSet<&u16> = old_banners.keys().chain(new_banners.keys()).collect();
+            for port in ports {
+                let (before, after) = (old_banners.get(port), new_banners.get(port));
+                if before != after {
+                    changes.push(Change::Banner {
+                        ip,
+                        port: *port,
+                        old: before.cloned(),
+                        new: after.cloned(),
+                    });
+                }
+            }
+            let (before, after) = (old.best_os(ip), new.best_os(ip));
+            if before != after {
+                changes.push(Change::Os {
+                    ip,
+                    old: before.map(String::from),
+                    new: after.map(String::from),
+                });
+            }
+        }
+        ScanDiff { changes }
+    }
+    /// Nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Port status as shown in the text diff
+fn port_line(status: &Option<PortStatus>) -> String {
+    match status {
+        Some(status) => status.to_string().to_lowercase(),
+        None => String::from("not scanned"),
+    }
+}
+
+impl fmt::Display for Change {
+    /// One line, like `192.168.1.1 22/tcp closed -> open`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::HostUp { ip } => write!(f, "{} host up", ip),
+            Change::HostDown { ip } => write!(f, "{} host down", ip),
+            Change::Port {
+                ip,
+                port,
+                protocol,
+                old,
+                new,
+            } => write!(
+                f,
+                "{} {}/{} {} -> {}",
+                ip,
+                port,
+                protocol,
+                port_line(old),
+                port_line(new)
+            ),
+            Change::Service {
+                ip,
+                port,
+                protocol,
+                old,
+                new,
+            } => write!(f, "{} {}/{} service {} -> {}", ip, port, protocol, old, new),
+            Change::Banner { ip, port, old, new } => write!(
+                f,
+                "{} {}/tcp banner {:?} -> {:?}",
+                ip,
+                port,
+                old.as_deref().unwrap_or_default(),
+                new.as_deref().unwrap_or_default()
+            ),
+            Change::Os { ip, old, new } => write!(
+                f,
+                "{} OS {} -> {}",
+                ip,
+                old.as_deref().unwrap_or("unknown"),
+                new.as_deref().unwrap_or("unknown")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ScanDiff {
+    /// Text like nmap's ndiff, one block per host with `-` earlier and `+` later lines
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut last_ip = None;
+        for change in &self.changes {
+            if last_ip != Some(change.ip()) {
+                if last_ip.is_some() {
+                    writeln!(f)?;
+                }
+                writeln!(f, "{}:", change.ip())?;
+                last_ip = Some(change.ip());
+            }
+            match change {
+                Change::HostUp { .. } => writeln!(f, "-Host is down\n+Host is up")?,
+                Change::HostDown { .. } => writeln!(f, "-Host is up\n+Host is down")?,
+                Change::Port {
+                    port,
+                    protocol,
+                    old,
+                    new,
+                    ..
+                } => {
+                    let name = format!("{}/{}", port, protocol);
+                    writeln!(f, "-{:<10} {}", name, port_line(old))?;
+                    writeln!(f, "+{:<10} {}", name, port_line(new))?;
+                }
+                Change::Service {
+                    port,
+                    protocol,
+                    old,
+                    new,
+                    ..
+                } => {
+                    let name = format!("{}/{}", port, protocol);
+                    writeln!(f, "-{:<10} service {}", name, old)?;
+                    writeln!(f, "+{:<10} service {}", name, new)?;
+                }
+                Change::Banner { port, old, new, .. } => {
+                    let name = format!("{}/tcp", port);
+                    writeln!(f, "-{:<10} banner {}", name, old.as_deref().unwrap_or("-"))?;
+                    writeln!(f, "+{:<10} banner {}", name, new.as_deref().unwrap_or("-"))?;
+                }
+                Change::Os { old, new, .. } => {
+                    writeln!(f, "-OS: {}", old.as_deref().unwrap_or("unknown"))?;
+                    writeln!(f, "+OS: {}", new.as_deref().unwrap_or("unknown"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 
 /// DNS results
@@ -32,7 +33,7 @@ use std::fmt;
 /// ```
 pub type DnsResults = Vec<DnsResult>;
 /// Dns result model
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsResult {
     #[doc(hidden)]
     pub src: String,
@@ -45,7 +46,7 @@ impl fmt::Display for DnsResult {
     }
 }
 /// Dns result type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DnsResultType {
     /// Host like Dns name
     Host(String),
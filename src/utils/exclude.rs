@@ -0,0 +1,103 @@
+use crate::frame::Destination;
+use e_utils::dns;
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// Hosts and ports left out of a scan
+///
+/// Networks and port ranges are kept as ranges, so excluding a large network
+/// costs nothing.
+#[derive(Clone, Debug, Default)]
+pub struct Exclusions {
+    /// Excluded networks
+    pub hosts: Vec<IpNet>,
+    /// Excluded port ranges(inclusive)
+    pub ports: Vec<(u16, u16)>,
+}
+
+impl Exclusions {
+    /// Create empty exclusions
+    pub fn new() -> Exclusions {
+        Exclusions::default()
+    }
+    /// Returns true if nothing is excluded
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty() && self.ports.is_empty()
+    }
+    /// Exclude a host; example: "192.168.1.1", "10.0.0.0/16", "router.lan"
+    pub fn add_host(&mut self, host: &str) -> Result<(), String> {
+        if let Ok(net) = host.parse::<IpNet>() {
+            self.hosts.push(net);
+        } else if let Ok(ip) = host.parse::<IpAddr>() {
+            self.hosts.push(IpNet::from(ip));
+        } else {
+            let addrs = dns::lookup_host(host)
+                .map_err(|e| format!("cannot resolve excluded host {}: {}", host, e))?;
+            self.hosts.extend(addrs.into_iter().map(IpNet::from));
+        }
+        Ok(())
+    }
+    /// Exclude hosts listed in a file, one host or network per line
+    pub fn add_hosts_file(&mut self, path: &str) -> Result<(), String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if !line.is_empty() {
+                self.add_host(line)?;
+            }
+        }
+        Ok(())
+    }
+    /// Exclude ports; example: "9100", "6000-6063"
+    pub fn add_ports(&mut self, spec: &str) -> Result<(), String> {
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("cannot parse excluded port -> {}", spec))
+        };
+        let range = match spec.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(spec)?, parse(spec)?),
+        };
+        if range.0 > range.1 {
+            return Err(format!("empty excluded port range -> {}", spec));
+        }
+        self.ports.push(range);
+        Ok(())
+    }
+    /// Returns true if the host is excluded
+    pub fn excludes_host(&self, ip: &IpAddr) -> bool {
+        self.hosts.iter().any(|net| net.contains(ip))
+    }
+    /// Returns true if every address of the network is excluded
+    pub fn excludes_net(&self, target: &IpNet) -> bool {
+        self.hosts.iter().any(|net| net.contains(target))
+    }
+    /// Returns true if the port is excluded
+    pub fn excludes_port(&self, port: u16) -> bool {
+        self.ports
+            .iter()
+            .any(|(start, end)| *start <= port && port <= *end)
+    }
+    /// Remove excluded hosts and ports from destinations
+    ///
+    /// Destinations whose ports are all excluded are removed as well.
+    pub fn filter_destinations(&self, destinations: Vec<Destination>) -> Vec<Destination> {
+        destinations
+            .into_iter()
+            .filter(|dst| !self.excludes_host(&dst.dst_ip))
+            .filter_map(|mut dst| {
+                if !dst.has_ports() {
+                    return Some(dst);
+                }
+                dst.dst_ports.retain(|port| !self.excludes_port(*port));
+                dst.dst_udp_ports.retain(|port| !self.excludes_port(*port));
+                if dst.has_ports() {
+                    Some(dst)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,835 @@
+use super::cmd_input::parse_scan_type;
+use crate::data::{id::DEFAULT_CONNECT_TIMEOUT, Protocol, DATA};
+use crate::diff::ScanSnapshot;
+use crate::exclude::Exclusions;
+use crate::frame::result::{PortStatus, ScanCounters, ScanResult, ScanStats, ScanStatus};
+use crate::frame::{Destination, ScanType};
+use crate::input::parse_target_line;
+use crate::output::Banners;
+use crate::ports::{parse_port_spec, PortSpec};
+use crate::scope::Scope;
+use crate::service::ServiceDetector;
+use crate::sync_scan;
+use crate::{parse_ip_range_except, parse_ip_range_in_scope_except, ScanOrderType};
+use ipnet::IpNet;
+use serde_derive::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest sleep between checks of the stop flag
+const STOP_POLL: Duration = Duration::from_millis(100);
+/// Longest request head accepted
+const MAX_REQUEST: usize = 8192;
+/// Time a client has to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most connections answered at once, others get 503
+const MAX_CONNECTIONS: usize = 64;
+/// Default of the most probes running at once
+const DEFAULT_MAX_PROBES: usize = 4;
+
+/// Module as written in the modules file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModuleFile {
+    scan: String,
+    #[serde(default)]
+    ports: Vec<String>,
+    top_ports: Option<usize>,
+    timeout: Option<u64>,
+    wait_time: Option<u64>,
+    connect_timeout: Option<u64>,
+    #[serde(default)]
+    service: bool,
+}
+
+/// Modules file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModulesFile {
+    #[serde(default)]
+    module: BTreeMap<String, ModuleFile>,
+}
+
+/// Scan settings selected by the `module` parameter of `/probe`
+#[derive(Clone, Debug)]
+pub struct ProbeModule {
+    /// Scan type
+    pub scan_type: ScanType,
+    /// Ports scanned when the target has none
+    pub ports: PortSpec,
+    /// Most common TCP ports scanned when neither the target nor the module has ports
+    pub top_ports: usize,
+    /// Timeout of the scan
+    pub timeout: Duration,
+    /// Waiting time after the last probe
+    pub wait_time: Duration,
+    /// Timeout of a single TCP connection attempt
+    pub connect_timeout: Duration,
+    /// Run service detection on the open TCP ports
+    pub service: bool,
+}
+
+impl ProbeModule {
+    /// Module with default settings; fast enough for the default scrape timeout
+    pub fn new(scan_type: ScanType) -> ProbeModule {
+        ProbeModule {
+            scan_type,
+            ports: PortSpec::default(),
+            top_ports: 100,
+            timeout: Duration::from_secs(8),
+            wait_time: Duration::from_millis(1000),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            service: false,
+        }
+    }
+    /// Set ports, a port specification like `22,U:53`
+    pub fn set_ports(&mut self, spec: &[String]) -> Result<(), String> {
+        self.ports = parse_port_spec(spec)?;
+        Ok(())
+    }
+    /// Set service detection on the open TCP ports
+    pub fn set_service(&mut self, service: bool) {
+        self.service = service;
+    }
+    /// Module scans ports
+    fn scans_ports(&self) -> bool {
+        matches!(
+            self.scan_type,
+            ScanType::TcpConnectScan | ScanType::TcpSynScan
+        )
+    }
+}
+
+/// Built-in modules: `icmp`, `tcp_ping`, `udp_ping`, `tcp_connect`, `tcp_syn` and `service`
+pub fn default_modules() -> BTreeMap<String, ProbeModule> {
+    let mut service = ProbeModule::new(ScanType::TcpSynScan);
+    service.set_service(true);
+    // service detection reads banners after the scan
+    service.timeout = Duration::from_secs(5);
+    BTreeMap::from([
+        (
+            String::from("icmp"),
+            ProbeModule::new(ScanType::IcmpPingScan),
+        ),
+        (
+            String::from("tcp_ping"),
+            ProbeModule::new(ScanType::TcpPingScan),
+        ),
+        (
+            String::from("udp_ping"),
+            ProbeModule::new(ScanType::UdpPingScan),
+        ),
+        (
+            String::from("tcp_connect"),
+            ProbeModule::new(ScanType::TcpConnectScan),
+        ),
+        (
+            String::from("tcp_syn"),
+            ProbeModule::new(ScanType::TcpSynScan),
+        ),
+        (String::from("service"), service),
+    ])
+}
+
+/// Parse probe modules from TOML
+///
+/// # File format
+/// ```text
+/// [module.ssh]
+/// # scan type of --scan: icmp, tcp, udp, tcpconnect or tcpsyn
+/// scan = "tcpconnect"
+/// ports = ["22"]
+/// service = true
+/// # milliseconds
+/// timeout = 5000
+/// wait_time = 500
+/// ```
+pub fn parse_modules(content: &str) -> Result<BTreeMap<String, ProbeModule>, String> {
+    let file: ModulesFile = toml::from_str(content).map_err(|e| e.to_string())?;
+    let mut modules = BTreeMap::new();
+    for (name, module) in file.module {
+        let err = |e: String| format!("module {}: {}", name, e);
+        let scan_type = ScanOrderType::from_str(&module.scan)
+            .ok()
+            .and_then(|order| parse_scan_type(&order))
+            .ok_or_else(|| err(format!("invalid scan type {}", module.scan)))?;
+        let mut parsed = ProbeModule::new(scan_type);
+        parsed.set_ports(&module.ports).map_err(err)?;
+        if let Some(top_ports) = module.top_ports {
+            DATA.top_ports(Protocol::Tcp, top_ports).map_err(err)?;
+            parsed.top_ports = top_ports;
+        }
+        if let Some(timeout) = module.timeout {
+            parsed.timeout = Duration::from_millis(timeout);
+        }
+        if let Some(wait_time) = module.wait_time {
+            parsed.wait_time = Duration::from_millis(wait_time);
+        }
+        if let Some(connect_timeout) = module.connect_timeout {
+            parsed.connect_timeout = Duration::from_millis(connect_timeout);
+        }
+        parsed.set_service(module.service);
+        modules.insert(name, parsed);
+    }
+    Ok(modules)
+}
+
+/// Prometheus text format writer
+struct Metrics(String);
+
+impl Metrics {
+    /// Start a metric family
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+    /// Add a sample to the current family
+    fn sample<V: fmt::Display>(&mut self, name: &str, labels: &[(&str, &str)], value: V) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+                .collect();
+            let _ = write!(self.0, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.0, " {}", value);
+    }
+    /// Family with a single sample
+    fn single<V: fmt::Display>(&mut self, name: &str, kind: &str, help: &str, value: V) {
+        self.family(name, kind, help);
+        self.sample(name, &[], value);
+    }
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Add scan counters; `total` writes them as counters summed over many scans
+fn write_stats(m: &mut Metrics, prefix: &str, stats: &ScanStats, total: bool) {
+    let (kind, suffix) = if total {
+        ("counter", "_total")
+    } else {
+        ("gauge", "")
+    };
+    for (name, help, value) in [
+        ("packets_sent", "Probes sent", stats.probes_sent),
+        (
+            "send_errors",
+            "Probes that could not be sent",
+            stats.send_errors,
+        ),
+        (
+            "frames_received",
+            "Frames read from the datalink channel",
+            stats.frames_received,
+        ),
+        (
+            "frames_matched",
+            "Frames answering a probe",
+            stats.frames_matched,
+        ),
+        (
+            "retries",
+            "Probes repeated after local sockets or ports ran out",
+            stats.retries,
+        ),
+        (
+            "duplicates",
+            "Answers already in the result",
+            stats.duplicates,
+        ),
+        (
+            "frames_dropped",
+            "Frames dropped by the datalink channel",
+            stats.dropped,
+        ),
+    ] {
+        m.single(&format!("{}_{}{}", prefix, name, suffix), kind, help, value);
+    }
+    m.single(
+        &format!("{}_packets_per_second", prefix),
+        "gauge",
+        "Probes per second of the latest scan",
+        stats.packet_rate,
+    );
+}
+
+/// Add host, port and service metrics of a scan
+fn write_results(m: &mut Metrics, prefix: &str, snapshot: &ScanSnapshot, targets: &[IpAddr]) {
+    let result = &snapshot.result;
+    let hosts: BTreeSet<IpAddr> = targets
+        .iter()
+        .copied()
+        .chain(result.ips.iter().map(|host| host.ip_addr))
+        .chain(result.ip_with_port.keys().copied())
+        .collect();
+    let name = format!("{}_host_up", prefix);
+    m.family(&name, "gauge", "Host answered the scan");
+    for ip in &hosts {
+        m.sample(
+            &name,
+            &[("ip", &ip.to_string())],
+            result.is_host_up(*ip) as u8,
+        );
+    }
+    let name = format!("{}_port_open", prefix);
+    m.family(&name, "gauge", "Port was found open");
+    for ip in &hosts {
+        let mut ports: Vec<_> = result.ip_with_port.get(ip).into_iter().flatten().collect();
+        ports.sort_by_key(|p| (p.protocol, p.port));
+        for p in ports {
+            m.sample(
+                &name,
+                &[
+                    ("ip", &ip.to_string()),
+                    ("port", &p.port.to_string()),
+                    ("protocol", &p.protocol.to_string()),
+                    ("service", &p.describe),
+                ],
+                (p.status == PortStatus::Open) as u8,
+            );
+        }
+    }
+    let name = format!("{}_service_info", prefix);
+    m.family(
+        &name,
+        "gauge",
+        "Service detection banner of an open TCP port",
+    );
+    for ip in &hosts {
+        let banners = snapshot.banners.get_host_banners(*ip).into_iter().flatten();
+        for (port, banner) in banners.filter(|(_, banner)| !banner.is_empty()) {
+            m.sample(
+                &name,
+                &[
+                    ("ip", &ip.to_string()),
+                    ("port", &port.to_string()),
+                    ("protocol", &Protocol::Tcp.to_string()),
+                    ("banner", banner),
+                ],
+                1,
+            );
+        }
+    }
+    m.single(
+        &format!("{}_scan_duration_seconds", prefix),
+        "gauge",
+        "Time taken by the scan",
+        result.scan_time.as_secs_f64(),
+    );
+}
+
+/// Prometheus text of a scan, metric names start with `prefix`
+///
+/// `targets` are the scanned hosts, those missing in the result are reported down.
+pub fn snapshot_metrics(prefix: &str, snapshot: &ScanSnapshot, targets: &[IpAddr]) -> String {
+    let mut m = Metrics(String::new());
+    write_stats(&mut m, prefix, &snapshot.result.stats, false);
+    write_results(&mut m, prefix, snapshot, targets);
+    m.0
+}
+
+/// Service detection banners of the open TCP ports of a scan
+fn detect_banners(result: &ScanResult) -> Banners {
+    let results: Vec<_> = ServiceDetector::from_result(result)
+        .iter()
+        .map(|detector| detector.scan(None))
+        .collect();
+    Banners::from_service_results(&results)
+}
+
+/// Scan once and drain its progress, nobody reads it
+fn run_scan(scanner: &mut sync_scan::Scanner) -> ScanResult {
+    let prx = scanner.get_progress_receiver();
+    let result = scanner.scan(None);
+    while prx.lock().unwrap().try_recv().is_ok() {}
+    result
+}
+
+/// Counters and results shared by the server threads
+#[derive(Debug, Default)]
+struct ExporterState {
+    /// Counters of the finished scans
+    totals: ScanStats,
+    /// Finished scans
+    scans: u64,
+    /// Finished scans that failed
+    failed: u64,
+    /// Counters of the running scans
+    running: Vec<Arc<ScanCounters>>,
+    /// Latest scheduled scan with its targets
+    last: Option<(ScanSnapshot, Vec<IpAddr>)>,
+    /// Time the latest scheduled scan finished
+    last_time: Option<SystemTime>,
+}
+
+impl ExporterState {
+    /// Move a scan from running to finished
+    fn finish(&mut self, counters: &Arc<ScanCounters>, result: &ScanResult) {
+        self.running
+            .retain(|running| !Arc::ptr_eq(running, counters));
+        self.totals.add(&result.stats);
+        self.scans += 1;
+        if let ScanStatus::Error = result.scan_status {
+            self.failed += 1;
+        }
+    }
+}
+
+/// HTTP server exposing scanner metrics and scan results in Prometheus text format
+///
+/// - `/metrics`: packet and error counters of every scan so far, including the
+///   running ones, and the hosts and ports of the latest scheduled scan
+/// - `/probe?target=<target>&module=<module>`: scans the target on demand,
+///   like the blackbox exporter; the target takes the forms of an input file
+///   line, e.g. `10.0.0.1`, `web01.lan:80,443` or `10.0.0.0/30:T:22,U:53`
+///
+/// Probes go through the scope and exclusions of the exporter. Without a scope
+/// a probe targets a single address; networks and ranges are refused. At most
+/// `max_probes` probes run at once, further ones are answered with 503.
+#[derive(Clone, Debug)]
+pub struct Exporter {
+    /// Listen address of the HTTP server
+    pub listen: SocketAddr,
+    /// Source address of the probes
+    pub src_ip: IpAddr,
+    /// Probe modules by name
+    pub modules: BTreeMap<String, ProbeModule>,
+    /// Scope probe targets must be in
+    pub scope: Option<Scope>,
+    /// Hosts and ports never probed
+    pub exclusions: Exclusions,
+    /// Scan run on a schedule, its results are exposed on `/metrics`
+    pub scanner: Option<sync_scan::Scanner>,
+    /// Time between the starts of two scheduled scans
+    pub interval: Duration,
+    /// Run service detection after every scheduled scan
+    pub service: bool,
+    /// Most probes running at once
+    pub max_probes: usize,
+    state: Arc<Mutex<ExporterState>>,
+    probes: Arc<AtomicUsize>,
+}
+
+impl Exporter {
+    /// Exporter with the default modules and without a scheduled scan
+    pub fn new(listen: SocketAddr, src_ip: IpAddr) -> Exporter {
+        Exporter {
+            listen,
+            src_ip,
+            modules: default_modules(),
+            scope: None,
+            exclusions: Exclusions::new(),
+            scanner: None,
+            interval: Duration::from_secs(3600),
+            service: false,
+            max_probes: DEFAULT_MAX_PROBES,
+            state: Arc::new(Mutex::new(ExporterState::default())),
+            probes: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    /// Add or replace a probe module
+    pub fn set_module(&mut self, name: &str, module: ProbeModule) {
+        self.modules.insert(name.to_owned(), module);
+    }
+    /// Add or replace the probe modules of a TOML file
+    pub fn load_modules(&mut self, path: &str) -> Result<(), String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let modules = parse_modules(&content).map_err(|e| format!("{}: {}", path, e))?;
+        self.modules.extend(modules);
+        Ok(())
+    }
+    /// Set scope probe targets must be in
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = Some(scope);
+    }
+    /// Set hosts and ports never probed
+    pub fn set_exclusions(&mut self, exclusions: Exclusions) {
+        self.exclusions = exclusions;
+    }
+    /// Set scan run every `interval` while the server runs
+    pub fn set_scanner(&mut self, scanner: sync_scan::Scanner, interval: Duration) {
+        self.scanner = Some(scanner);
+        self.interval = interval;
+    }
+    /// Set service detection after every scheduled scan
+    pub fn set_service(&mut self, service: bool) {
+        self.service = service;
+    }
+    /// Set most probes running at once
+    pub fn set_max_probes(&mut self, max_probes: usize) {
+        self.max_probes = max_probes;
+    }
+    /// Prometheus text of `/metrics`
+    pub fn metrics(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut stats = state.totals.clone();
+        for running in &state.running {
+            stats.add(&running.snapshot());
+        }
+        let mut m = Metrics(String::new());
+        write_stats(&mut m, "e_libscanner", &stats, true);
+        m.single(
+            "e_libscanner_scans_total",
+            "counter",
+            "Finished scans and probes",
+            state.scans,
+        );
+        m.single(
+            "e_libscanner_scan_errors_total",
+            "counter",
+            "Finished scans and probes that failed",
+            state.failed,
+        );
+        m.single(
+            "e_libscanner_scans_running",
+            "gauge",
+            "Scans and probes running",
+            state.running.len(),
+        );
+        if let Some((snapshot, targets)) = &state.last {
+            write_results(&mut m, "e_libscanner", snapshot, targets);
+        }
+        if let Some(time) = state.last_time {
+            m.single(
+                "e_libscanner_last_scan_timestamp_seconds",
+                "gauge",
+                "Time the latest scheduled scan finished",
+                time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            );
+        }
+        m.0
+    }
+    /// Scan a target with a module and return the Prometheus text of `/probe`
+    pub fn probe(&self, target: &str, module: &str) -> Result<String, String> {
+        let module = self
+            .modules
+            .get(module)
+            .ok_or(format!("Unknown module {}", module))?;
+        let target = parse_target_line(target)?.ok_or(String::from("Missing target"))?;
+        let ips = match &self.scope {
+            Some(scope) => {
+                let (ips, violations) =
+                    parse_ip_range_in_scope_except(&vec![target.target], scope, &self.exclusions)?;
+                if let Some(violation) = violations.first() {
+                    return Err(violation.to_string());
+                }
+                ips
+            }
+            None => {
+                if is_range(&target.target) {
+                    return Err(String::from(
+                        "Probing a network or range needs a scope, target a single address",
+                    ));
+                }
+                // a host name may resolve to several addresses, probe the first one
+                let mut ips = parse_ip_range_except(&vec![target.target], &self.exclusions)?;
+                ips.truncate(1);
+                ips
+            }
+        };
+        if ips.is_empty() {
+            return Err(String::from("No target address to probe"));
+        }
+        let (mut ports, mut udp_ports) = if !target.ports.is_empty() || !target.udp_ports.is_empty()
+        {
+            (target.ports, target.udp_ports)
+        } else if !module.ports.is_empty() {
+            (module.ports.tcp.clone(), module.ports.udp_to_scan())
+        } else if module.scans_ports() {
+            (DATA.top_ports(Protocol::Tcp, module.top_ports)?, vec![])
+        } else {
+            (vec![], vec![])
+        };
+        ports.retain(|port| !self.exclusions.excludes_port(*port));
+        udp_ports.retain(|port| !self.exclusions.excludes_port(*port));
+        let has_ports = !ports.is_empty() || !udp_ports.is_empty();
+        if module.scans_ports() && !has_ports {
+            return Err(String::from("All ports are excluded"));
+        }
+
+        let start = Instant::now();
+        let mut scanner = sync_scan::Scanner::new(self.src_ip)?;
+        scanner.set_destinations(
+            ips.iter()
+                .map(|ip| {
+                    let mut dst = Destination::new(*ip, ports.clone());
+                    dst.set_dst_udp_port(udp_ports.clone());
+                    dst
+                })
+                .collect(),
+        );
+        scanner.set_scan_type(module.scan_type.clone());
+        scanner.set_timeout(module.timeout);
+        scanner.set_wait_time(module.wait_time);
+        scanner.set_connect_timeout(module.connect_timeout);
+        if let Some(scope) = &self.scope {
+            scanner.set_scope(scope.clone());
+        }
+        scanner.set_exclusions(self.exclusions.clone());
+        // fall back to unprivileged mode
+        #[cfg(target_os = "linux")]
+        if !crate::privilege::has_raw_socket_privilege() {
+            scanner.set_unprivileged(true);
+        }
+        let counters = scanner.get_stats_counters();
+        self.state
+            .lock()
+            .unwrap()
+            .running
+            .push(Arc::clone(&counters));
+        let result = run_scan(&mut scanner);
+        self.state.lock().unwrap().finish(&counters, &result);
+
+        let mut snapshot = ScanSnapshot::new(result);
+        if module.service {
+            snapshot.set_banners(detect_banners(&snapshot.result));
+        }
+        let result = &snapshot.result;
+        // port modules succeed on an open port, host modules on a host that is up
+        let success = matches!(result.scan_status, ScanStatus::Done)
+            && ips.iter().any(|ip| {
+                if has_ports {
+                    !result.get_open_ports(*ip).is_empty()
+                } else {
+                    result.is_host_up(*ip)
+                }
+            });
+        let mut m = Metrics(String::new());
+        m.single("probe_success", "gauge", "Probe succeeded", success as u8);
+        m.single(
+            "probe_duration_seconds",
+            "gauge",
+            "Time taken by the probe",
+            start.elapsed().as_secs_f64(),
+        );
+        write_stats(&mut m, "probe", &result.stats, false);
+        write_results(&mut m, "probe", &snapshot, &ips);
+        Ok(m.0)
+    }
+    /// Run the scheduled scan once and keep its results for `/metrics`
+    pub fn scan_once(&self, scanner: &mut sync_scan::Scanner) {
+        let counters = scanner.get_stats_counters();
+        // counters of the previous run must not be counted twice
+        counters.reset();
+        self.state
+            .lock()
+            .unwrap()
+            .running
+            .push(Arc::clone(&counters));
+        let result = run_scan(scanner);
+        let mut snapshot = ScanSnapshot::new(result);
+        if self.service {
+            snapshot.set_banners(detect_banners(&snapshot.result));
+        }
+        let targets = scanner
+            .get_destinations()
+            .iter()
+            .map(|dst| dst.dst_ip)
+            .collect();
+        let mut state = self.state.lock().unwrap();
+        state.finish(&counters, &snapshot.result);
+        state.last = Some((snapshot, targets));
+        state.last_time = Some(SystemTime::now());
+    }
+    /// Serve until stopped, running the scheduled scan every `interval`
+    pub fn run(&self, pstop: Option<Arc<Mutex<bool>>>) -> Result<(), String> {
+        let listener =
+            TcpListener::bind(self.listen).map_err(|e| format!("{}: {}", self.listen, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let stopped =
+            |pstop: &Option<Arc<Mutex<bool>>>| pstop.as_ref().map_or(false, |p| *p.lock().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        if let Some(scanner) = &self.scanner {
+            let exporter = self.clone();
+            let mut scanner = scanner.clone();
+            let pstop = pstop.clone();
+            thread::spawn(move || {
+                while !stopped(&pstop) {
+                    let start = Instant::now();
+                    exporter.scan_once(&mut scanner);
+                    while !stopped(&pstop) && start.elapsed() < exporter.interval {
+                        thread::sleep((exporter.interval - start.elapsed()).min(STOP_POLL));
+                    }
+                }
+            });
+        }
+        while !stopped(&pstop) {
+            match listener.accept() {
+                Ok((mut stream, _)) => match Slot::take(&connections, MAX_CONNECTIONS) {
+                    Some(slot) => {
+                        let exporter = self.clone();
+                        thread::spawn(move || {
+                            exporter.handle(stream);
+                            drop(slot);
+                        });
+                    }
+                    None => {
+                        let _ = stream.set_nonblocking(false);
+                        write_response(&mut stream, 503, "Too many connections\n");
+                    }
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(STOP_POLL),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        Ok(())
+    }
+    /// Answer one HTTP request
+    fn handle(&self, mut stream: TcpStream) {
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+        let (status, body) = match read_request_line(&mut stream) {
+            Ok(line) => self.respond(&line),
+            Err(e) => (400, e),
+        };
+        write_response(&mut stream, status, &body);
+    }
+    /// Status and body answering a request line like `GET /metrics HTTP/1.1`
+    fn respond(&self, line: &str) -> (u16, String) {
+        let mut parts = line.split_whitespace();
+        let (method, target) = (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+        );
+        if method != "GET" {
+            return (405, format!("Method {} not allowed\n", method));
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params: BTreeMap<String, String> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (decode(key), decode(value)))
+            .collect();
+        match path {
+            "/metrics" => (200, self.metrics()),
+            "/probe" => {
+                let target = params.get("target").map_or("", |t| t.as_str());
+                let module = params.get("module").map_or("tcp_connect", |m| m.as_str());
+                let slot = match Slot::take(&self.probes, self.max_probes) {
+                    Some(slot) => slot,
+                    None => return (503, String::from("Too many probes running\n")),
+                };
+                let response = match self.probe(target, module) {
+                    Ok(body) => (200, body),
+                    Err(e) => (400, format!("{}\n", e)),
+                };
+                drop(slot);
+                response
+            }
+            "/" => {
+                let modules: Vec<&str> = self.modules.keys().map(|name| name.as_str()).collect();
+                (
+                    200,
+                    format!(
+                        "e-libscanner exporter\n/metrics\n/probe?target=<target>&module=<{}>\n",
+                        modules.join("|")
+                    ),
+                )
+            }
+            _ => (404, format!("{} not found\n", path)),
+        }
+    }
+}
+
+/// Place in a bounded count of running tasks, freed when dropped
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    /// Take a place if fewer than `max` are taken
+    fn take(count: &Arc<AtomicUsize>, max: usize) -> Option<Slot> {
+        count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < max).then(|| n + 1)
+            })
+            .ok()
+            .map(|_| Slot(Arc::clone(count)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether a probe target covers more than one address
+fn is_range(target: &str) -> bool {
+    match target.parse::<IpNet>() {
+        Ok(net) => net.prefix_len() < net.max_prefix_len(),
+        Err(_) => target.contains('/') || target.contains('-'),
+    }
+}
+
+/// Write a plain text response and close the connection
+fn write_response(stream: &mut TcpStream, status: u16, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Method Not Allowed",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Read the request head and return its first line
+fn read_request_line(stream: &mut TcpStream) -> Result<String, String> {
+    let mut head = vec![];
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > MAX_REQUEST {
+            return Err(String::from("Request too large\n"));
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    Ok(head.lines().next().unwrap_or_default().to_owned())
+}
+
+/// Decode a percent-encoded query component
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = value
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => out.push(b' '),
+            (byte, _) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,295 @@
+use crate::data::Protocol;
+use crate::diff::ScanSnapshot;
+use crate::frame::result::{PortInfo, PortStatus};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// One recorded scan run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanRun {
+    /// Run id, assigned by the store
+    pub id: u64,
+    /// Scan parameters, e.g. targets, ports and scan type
+    pub params: BTreeMap<String, String>,
+    /// Time the scan started
+    pub started: SystemTime,
+    /// Time the scan finished
+    pub finished: SystemTime,
+    /// Hosts, ports, banners and OS guesses observed
+    pub snapshot: ScanSnapshot,
+}
+
+impl ScanRun {
+    /// Run that finished now and took the scan time of the result
+    pub fn new(snapshot: ScanSnapshot) -> ScanRun {
+        let finished = SystemTime::now();
+        ScanRun {
+            id: 0,
+            params: BTreeMap::new(),
+            started: finished
+                .checked_sub(snapshot.result.scan_time)
+                .unwrap_or(finished),
+            finished,
+            snapshot,
+        }
+    }
+    /// Set a scan parameter
+    pub fn set_param(&mut self, key: &str, value: &str) {
+        self.params.insert(key.to_owned(), value.to_owned());
+    }
+    /// Set time the scan started
+    pub fn set_started(&mut self, started: SystemTime) {
+        self.started = started;
+    }
+    /// Set time the scan finished
+    pub fn set_finished(&mut self, finished: SystemTime) {
+        self.finished = finished;
+    }
+    /// Host was up in this run, see `ScanResult::is_host_up`
+    pub fn has_host(&self, ip: IpAddr) -> bool {
+        self.snapshot.result.is_host_up(ip)
+    }
+    /// Status of a port in this run
+    pub fn port_status(&self, ip: IpAddr, protocol: Protocol, port: u16) -> Option<PortStatus> {
+        self.snapshot
+            .result
+            .ip_with_port
+            .get(&ip)?
+            .iter()
+            .find(|p| p.protocol == protocol && p.port == port)
+            .map(|p| p.status)
+    }
+}
+
+/// Observation of a host in one run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HostObservation {
+    /// Id of the run
+    pub run_id: u64,
+    /// Time the run finished
+    pub time: SystemTime,
+    /// Host was up
+    pub up: bool,
+    /// Scanned ports of the host
+    pub ports: Vec<PortInfo>,
+    /// Service banners by TCP port
+    pub banners: BTreeMap<u16, String>,
+}
+
+/// Storage of scan runs
+///
+/// Backends store and load runs; the queries are provided on top of `runs`
+/// and can be overridden by backends that index their data.
+pub trait HistoryStore {
+    /// Store a run and return the id given to it
+    fn record(&mut self, run: ScanRun) -> Result<u64, String>;
+    /// All runs, oldest first
+    fn runs(&self) -> Result<Vec<ScanRun>, String>;
+
+    /// Latest run
+    fn last_run(&self) -> Result<Option<ScanRun>, String> {
+        Ok(self.runs()?.pop())
+    }
+    /// Time of the first run the host was up in
+    fn first_seen(&self, ip: IpAddr) -> Result<Option<SystemTime>, String> {
+        Ok(self
+            .runs()?
+            .iter()
+            .find(|run| run.has_host(ip))
+            .map(|run| run.finished))
+    }
+    /// Time of the first run the port was open in
+    fn first_seen_open(
+        &self,
+        ip: IpAddr,
+        protocol: Protocol,
+        port: u16,
+    ) -> Result<Option<SystemTime>, String> {
+        Ok(self
+            .runs()?
+            .iter()
+            .find(|run| run.port_status(ip, protocol, port) == Some(PortStatus::Open))
+            .map(|run| run.finished))
+    }
+    /// Time of the last run the port was open in
+    fn last_seen_open(
+        &self,
+        ip: IpAddr,
+        protocol: Protocol,
+        port: u16,
+    ) -> Result<Option<SystemTime>, String> {
+        Ok(self
+            .runs()?
+            .iter()
+            .rev()
+            .find(|run| run.port_status(ip, protocol, port) == Some(PortStatus::Open))
+            .map(|run| run.finished))
+    }
+    /// Observations of a host in every run, oldest first
+    fn host_history(&self, ip: IpAddr) -> Result<Vec<HostObservation>, String> {
+        Ok(self
+            .runs()?
+            .into_iter()
+            .map(|run| HostObservation {
+                run_id: run.id,
+                time: run.finished,
+                up: run.has_host(ip),
+                ports: run
+                    .snapshot
+                    .result
+                    .ip_with_port
+                    .get(&ip)
+                    .cloned()
+                    .unwrap_or_default(),
+                banners: run
+                    .snapshot
+                    .banners
+                    .get_host_banners(ip)
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+/// History kept in memory, lost when the process exits
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    runs: Vec<ScanRun>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl HistoryStore for MemoryStore {
+    fn record(&mut self, mut run: ScanRun) -> Result<u64, String> {
+        run.id = self.runs.last().map(|r| r.id + 1).unwrap_or(1);
+        let id = run.id;
+        self.runs.push(run);
+        Ok(id)
+    }
+    fn runs(&self) -> Result<Vec<ScanRun>, String> {
+        Ok(self.runs.clone())
+    }
+}
+
+/// History in a local append-only file, one JSON run per line
+///
+/// Runs are only ever appended, so an interrupted write can damage at most
+/// the last line; it is cut off when the file is opened and skipped when read.
+/// The offset of the last line is kept, `last_run` reads that line only.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    next_id: u64,
+    last_offset: Option<u64>,
+}
+
+impl FileStore {
+    /// Open the history file, creating it if missing
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FileStore, String> {
+        let path = path.as_ref().to_path_buf();
+        let err = |e: std::io::Error| format!("{}: {}", path.display(), e);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .read(true)
+            .open(&path)
+            .map_err(err)?;
+        // drop the damaged tail of an interrupted write
+        let mut content = std::fs::read(&path).map_err(err)?;
+        if content.last().map_or(false, |b| *b != b'\n') {
+            let len = content
+                .iter()
+                .rposition(|b| *b == b'\n')
+                .map_or(0, |i| i + 1);
+            file.set_len(len as u64).map_err(err)?;
+            content.truncate(len);
+        }
+        let mut last_offset = None;
+        let mut offset = 0;
+        for line in content.split(|b| *b == b'\n') {
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                last_offset = Some(offset as u64);
+            }
+            offset += line.len() + 1;
+        }
+        let mut store = FileStore {
+            path,
+            next_id: 1,
+            last_offset,
+        };
+        store.next_id = store.last_run()?.map(|r| r.id + 1).unwrap_or(1);
+        Ok(store)
+    }
+    /// Path of the history file
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl HistoryStore for FileStore {
+    fn record(&mut self, mut run: ScanRun) -> Result<u64, String> {
+        run.id = self.next_id;
+        let mut line = serde_json::to_string(&run).map_err(|e| e.to_string())?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        let offset = file
+            .metadata()
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?
+            .len();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        self.next_id += 1;
+        self.last_offset = Some(offset);
+        Ok(run.id)
+    }
+    fn last_run(&self) -> Result<Option<ScanRun>, String> {
+        let offset = match self.last_offset {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let err = |e: std::io::Error| format!("{}: {}", self.path.display(), e);
+        let mut file = File::open(&self.path).map_err(err)?;
+        file.seek(SeekFrom::Start(offset)).map_err(err)?;
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line).map_err(err)?;
+        serde_json::from_str(&line)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+    fn runs(&self) -> Result<Vec<ScanRun>, String> {
+        let file = File::open(&self.path).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        let mut runs = vec![];
+        for (n, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(run) => runs.push(run),
+                // write of the last run was interrupted
+                Err(_) if n + 1 == lines.len() => {}
+                Err(e) => return Err(format!("{}:{}: {}", self.path.display(), n + 1, e)),
+            }
+        }
+        Ok(runs)
+    }
+}
@@ -0,0 +1,241 @@
+use crate::data::Protocol;
+use crate::frame::result::{HostInfo, PortInfo, PortStatus, ScanResult, ScanStatus};
+use serde_derive::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Port record of the masscan JSON output
+#[derive(Deserialize)]
+struct MasscanPort {
+    port: u16,
+    proto: String,
+    status: Option<String>,
+    ttl: Option<u8>,
+}
+
+/// Host record of the masscan JSON output
+#[derive(Deserialize)]
+struct MasscanRecord {
+    ip: IpAddr,
+    ports: Vec<MasscanPort>,
+}
+
+/// Import a result file of nmap (XML) or masscan (JSON, NDJSON or list)
+pub fn import_file(path: &str) -> Result<ScanResult, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    import_str(&content).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Import nmap XML or masscan output, the format is told from the content
+pub fn import_str(content: &str) -> Result<ScanResult, String> {
+    let first = content
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if first.starts_with('<') {
+        parse_nmap_xml(content)
+    } else if first.starts_with('#') || first.starts_with("open ") || first.starts_with("closed ") {
+        parse_masscan_list(content)
+    } else {
+        parse_masscan_json(content)
+    }
+}
+
+/// Parse nmap XML output (`-oX`); hosts that are down are left out
+pub fn parse_nmap_xml(xml: &str) -> Result<ScanResult, String> {
+    // nmap always writes a DOCTYPE declaration
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(xml, options).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    if !root.has_tag_name("nmaprun") {
+        return Err(format!(
+            "Not nmap XML, root element is <{}>",
+            root.tag_name().name()
+        ));
+    }
+    let mut result = ScanResult::new();
+    for host in root.children().filter(|n| n.has_tag_name("host")) {
+        let status = host.children().find(|n| n.has_tag_name("status"));
+        if status.and_then(|n| n.attribute("state")) != Some("up") {
+            continue;
+        }
+        let ip = host
+            .children()
+            .filter(|n| n.has_tag_name("address"))
+            .filter(|n| matches!(n.attribute("addrtype"), Some("ipv4") | Some("ipv6")))
+            .find_map(|n| n.attribute("addr")?.parse::<IpAddr>().ok());
+        let ip = match ip {
+            Some(ip) => ip,
+            None => continue,
+        };
+        let ttl = status
+            .and_then(|n| n.attribute("reason_ttl")?.parse().ok())
+            .unwrap_or(0);
+        add_host(&mut result, ip, ttl);
+        let ports = host
+            .children()
+            .filter(|n| n.has_tag_name("ports"))
+            .flat_map(|n| n.children())
+            .filter(|n| n.has_tag_name("port"));
+        for port in ports {
+            let number = match port.attribute("portid").and_then(|p| p.parse().ok()) {
+                Some(number) => number,
+                None => continue,
+            };
+            let protocol = match port.attribute("protocol").and_then(parse_protocol) {
+                Some(protocol) => protocol,
+                None => continue,
+            };
+            let status = port
+                .children()
+                .find(|n| n.has_tag_name("state"))
+                .and_then(|n| parse_status(n.attribute("state")?));
+            let status = match status {
+                Some(status) => status,
+                None => continue,
+            };
+            let mut port_info = PortInfo::new(number, protocol, status);
+            let service = port
+                .children()
+                .find(|n| n.has_tag_name("service"))
+                .and_then(|n| n.attribute("name"));
+            if let Some(name) = service {
+                port_info.describe = name.to_owned();
+            }
+            add_port(&mut result, ip, port_info);
+        }
+    }
+    let finished = root
+        .children()
+        .filter(|n| n.has_tag_name("runstats"))
+        .flat_map(|n| n.children())
+        .find(|n| n.has_tag_name("finished"));
+    if let Some(finished) = finished {
+        if let Some(elapsed) = finished.attribute("elapsed") {
+            // Duration::from_secs_f64 panics on negative, non finite and overflowing values
+            match elapsed.parse::<f64>() {
+                Ok(secs) if secs.is_finite() && secs >= 0.0 && secs < u64::MAX as f64 => {
+                    result.scan_time = Duration::from_secs_f64(secs);
+                }
+                _ => return Err(format!("invalid elapsed time {}", elapsed)),
+            }
+        }
+        result.scan_status = match finished.attribute("exit") {
+            Some("error") => ScanStatus::Error,
+            _ => ScanStatus::Done,
+        };
+    } else {
+        // nmap was interrupted before writing the run statistics
+        result.scan_status = ScanStatus::Error;
+    }
+    Ok(result)
+}
+
+/// Parse masscan JSON (`-oJ`) or NDJSON (`-oD`) output
+///
+/// masscan writes one record per line, so each line is parsed on its own; this
+/// also accepts the trailing comma older versions leave before the closing bracket.
+pub fn parse_masscan_json(json: &str) -> Result<ScanResult, String> {
+    let mut result = ScanResult::new();
+    for (n, line) in json.lines().enumerate() {
+        let line = line.trim().trim_matches(',').trim();
+        if line.is_empty() || line == "[" || line == "]" {
+            continue;
+        }
+        let record: MasscanRecord =
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", n + 1, e))?;
+        for port in record.ports {
+            // banner records carry a service instead of a status
+            let status = match port.status.as_deref().and_then(parse_status) {
+                Some(status) => status,
+                None => continue,
+            };
+            let protocol = parse_protocol(&port.proto).ok_or(format!(
+                "line {}: unknown protocol {}",
+                n + 1,
+                port.proto
+            ))?;
+            add_host(&mut result, record.ip, port.ttl.unwrap_or(0));
+            add_port(
+                &mut result,
+                record.ip,
+                PortInfo::new(port.port, protocol, status),
+            );
+        }
+    }
+    result.scan_status = ScanStatus::Done;
+    Ok(result)
+}
+
+/// Parse masscan list output (`-oL`), lines like `open tcp 80 192.168.1.1 1660000000`
+pub fn parse_masscan_list(list: &str) -> Result<ScanResult, String> {
+    let mut result = ScanResult::new();
+    for (n, line) in list.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || fields[0].starts_with('#') || fields[0] == "banner" {
+            continue;
+        }
+        if fields.len() < 4 {
+            return Err(format!("line {}: invalid record {}", n + 1, line));
+        }
+        let status = parse_status(fields[0]).ok_or(format!(
+            "line {}: unknown status {}",
+            n + 1,
+            fields[0]
+        ))?;
+        let protocol = parse_protocol(fields[1]).ok_or(format!(
+            "line {}: unknown protocol {}",
+            n + 1,
+            fields[1]
+        ))?;
+        let port = fields[2]
+            .parse()
+            .map_err(|_| format!("line {}: invalid port {}", n + 1, fields[2]))?;
+        let ip = fields[3]
+            .parse()
+            .map_err(|_| format!("line {}: invalid ip {}", n + 1, fields[3]))?;
+        add_host(&mut result, ip, 0);
+        add_port(&mut result, ip, PortInfo::new(port, protocol, status));
+    }
+    result.scan_status = ScanStatus::Done;
+    Ok(result)
+}
+
+fn parse_protocol(protocol: &str) -> Option<Protocol> {
+    match protocol {
+        "tcp" => Some(Protocol::Tcp),
+        "udp" => Some(Protocol::Udp),
+        "sctp" => Some(Protocol::Sctp),
+        _ => None,
+    }
+}
+
+/// Port status of an nmap or masscan state; `unfiltered` tells nothing and gives None
+fn parse_status(state: &str) -> Option<PortStatus> {
+    match state {
+        "open" => Some(PortStatus::Open),
+        "closed" => Some(PortStatus::Closed),
+        "filtered" | "closed|filtered" => Some(PortStatus::Filtered),
+        "open|filtered" => Some(PortStatus::OpenFiltered),
+        _ => None,
+    }
+}
+
+fn add_host(result: &mut ScanResult, ip: IpAddr, ttl: u8) {
+    match result.ips.iter_mut().find(|host| host.ip_addr == ip) {
+        Some(host) if host.ttl == 0 => host.ttl = ttl,
+        Some(_) => {}
+        None => result.ips.push(HostInfo { ip_addr: ip, ttl }),
+    }
+}
+
+/// Add a port of a host, a later record of the same port replaces the earlier one
+fn add_port(result: &mut ScanResult, ip: IpAddr, port_info: PortInfo) {
+    let ports = result.ip_with_port.entry(ip).or_default();
+    ports.retain(|p| p.port != port_info.port || p.protocol != port_info.protocol);
+    ports.push(port_info);
+}
@@ -0,0 +1,91 @@
+use super::ports::parse_port_spec;
+use std::io::{self, BufRead, BufReader};
+use std::net::IpAddr;
+
+/// Target read from an input file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputTarget {
+    /// Host, address or range in any format `parse_ip_range` understands
+    pub target: String,
+    /// Ports of this target, empty to use the ports of the scan
+    pub ports: Vec<u16>,
+    /// UDP ports of this target, selected with `U:`
+    pub udp_ports: Vec<u16>,
+}
+
+/// Parse a single target line
+///
+/// Accepted forms: `192.168.1.0/24`, `host`, `host:80`, `host:80,443`,
+/// `host:T:80,U:53`, `[fe80::1]:22`. Empty lines and `#` comments give None.
+pub fn parse_target_line(line: &str) -> Result<Option<InputTarget>, String> {
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let (target, ports) = if line.parse::<IpAddr>().is_ok() {
+        (line, None)
+    } else if let Some(rest) = line.strip_prefix('[') {
+        // ipv6 with ports: [fe80::1]:22
+        match rest.split_once(']') {
+            Some((host, ports)) => match ports.strip_prefix(':') {
+                Some(ports) => (host, Some(ports)),
+                None if ports.is_empty() => (host, None),
+                None => return Err(format!("cannot parse target -> {}", line)),
+            },
+            None => return Err(format!("cannot parse target -> {}", line)),
+        }
+    } else if let Some((host, ports)) = line
+        .split_once(':')
+        .filter(|(host, _)| line.matches(':').count() == 1 || !is_ipv6_group(host))
+    {
+        (host, Some(ports))
+    } else {
+        // bare ipv6 range or network
+        (line, None)
+    };
+    let (ports, udp_ports) = match ports {
+        Some(spec) => {
+            let ports = parse_port_spec(&[spec.to_string()])?;
+            let udp_ports = ports.udp_to_scan();
+            if ports.tcp.is_empty() && udp_ports.is_empty() {
+                return Err(format!("no ports in `{}`", spec));
+            }
+            (ports.tcp, udp_ports)
+        }
+        None => (vec![], vec![]),
+    };
+    Ok(Some(InputTarget {
+        target: target.trim().to_string(),
+        ports,
+        udp_ports,
+    }))
+}
+
+/// Check if the text can start an IPv6 address(1 to 4 hex digits or nothing)
+fn is_ipv6_group(s: &str) -> bool {
+    s.len() <= 4 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Read targets from a reader, one target per line
+pub fn read_targets<R: io::Read>(reader: R) -> Result<Vec<InputTarget>, String> {
+    let mut targets = vec![];
+    for (n, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.map_err(|e| format!("line {}: {}", n + 1, e))?;
+        if let Some(target) =
+            parse_target_line(&line).map_err(|e| format!("line {}: {}", n + 1, e))?
+        {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+/// Read targets from a file, `-` reads standard input
+pub fn read_targets_file(path: &str) -> Result<Vec<InputTarget>, String> {
+    if path == "-" {
+        read_targets(io::stdin().lock()).map_err(|e| format!("stdin: {}", e))
+    } else {
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        read_targets(file).map_err(|e| format!("{}: {}", path, e))
+    }
+}
@@ -63,4 +63,368 @@ pub mod dns;
 /// }
 /// ```
 pub mod traceroute;
-pub use cmd_input::{parse_ip_range, Opts, ScriptsRequired, ScanModelType, ScanOrderType};
+/// Privilege checks
+/// # Examples
+/// ```
+/// use e_libscanner::privilege;
+/// if !privilege::has_raw_socket_privilege() {
+///     println!("raw sockets unavailable, scans run in unprivileged mode");
+/// }
+/// ```
+pub mod privilege;
+/// Preflight diagnostics
+/// # Examples
+/// ```
+/// use e_libscanner::{diagnose::DiagnoseReport, Opts};
+/// fn main() -> Result<(), String> {
+///     let opts = Opts::new(Some(&[
+///         "e-libscanner",
+///         "--ips",
+///         "127.0.0.1",
+///         "--model",
+///         "diagnose",
+///     ]))?
+///     .init()?
+///     .downcast::<DiagnoseReport>();
+///     match opts {
+///         Ok(report) => {
+///             println!("{}", report);
+///             for check in report.problems() {
+///                 eprintln!("{:?}: {}", check.status, check.message);
+///             }
+///         }
+///         Err(e) => panic!("{:?}", e),
+///     }
+///     Ok(())
+/// }
+/// ```
+pub mod diagnose;
+/// Changes between two scans, like nmap's ndiff
+/// # Examples
+/// ```
+/// use e_libscanner::data::Protocol;
+/// use e_libscanner::diff::{Change, ScanDiff, ScanSnapshot};
+/// use e_libscanner::frame::result::{PortInfo, PortStatus, ScanResult, ScanStatus};
+/// use e_libscanner::output::to_json;
+/// fn main() -> Result<(), String> {
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut monday = ScanResult::new();
+///     monday.scan_status = ScanStatus::Done;
+///     monday.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, PortStatus::Open)]);
+///     let mut tuesday = ScanResult::new();
+///     tuesday.scan_status = ScanStatus::Done;
+///     tuesday.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, PortStatus::Closed)]);
+///     // serialized results load as snapshots without banners or OS guesses
+///     let old = ScanSnapshot::from_json(&to_json(&monday)?)?;
+///     let new = ScanSnapshot::new(tuesday.clone());
+///     let diff = ScanDiff::compare(&old, &new)?;
+///     assert!(matches!(diff.changes[0], Change::Port { port: 22, new: Some(PortStatus::Closed), .. }));
+///     assert_eq!(diff.to_string(), "192.168.1.1:\n-22/tcp     open\n+22/tcp     closed\n");
+///     // failed or timed out scans are not compared
+///     tuesday.scan_status = ScanStatus::Timeout;
+///     assert!(ScanDiff::compare(&old, &tuesday.into()).is_err());
+///     // the CLI form: --model diff --diff monday.json,tuesday.json
+///     Ok(())
+/// }
+/// ```
+pub mod diff;
+/// Excluded hosts and ports
+/// # Examples
+/// ```
+/// use e_libscanner::{exclude::Exclusions, parse_ip_range_except};
+/// fn main() -> Result<(), String> {
+///     let mut exclusions = Exclusions::new();
+///     exclusions.add_host("10.0.0.0/17")?;
+///     exclusions.add_host("10.0.128.1")?;
+///     exclusions.add_ports("9100")?;
+///     // the excluded half of the range is never generated
+///     let ips = parse_ip_range_except(&vec![String::from("10.0.0.0/16")], &exclusions)?;
+///     assert_eq!(ips.len(), 32767 - 1);
+///     assert!(exclusions.excludes_port(9100));
+///     Ok(())
+/// }
+/// ```
+pub mod exclude;
+/// Persistent scan history
+/// # Examples
+/// ```
+/// use e_libscanner::data::Protocol;
+/// use e_libscanner::frame::result::{PortInfo, PortStatus, ScanResult};
+/// use e_libscanner::history::{FileStore, HistoryStore, ScanRun};
+/// fn main() -> Result<(), String> {
+///     let path = std::env::temp_dir().join("e-libscanner-history-doc.jsonl");
+///     let _ = std::fs::remove_file(&path);
+///     let mut store = FileStore::open(&path)?;
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut result = ScanResult::new();
+///     result.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, PortStatus::Open)]);
+///     let mut run = ScanRun::new(result.into());
+///     run.set_param("ips", "192.168.1.1");
+///     store.record(run)?;
+///     // reopened later, e.g. by the next nightly run
+///     let store = FileStore::open(&path)?;
+///     assert!(store.last_seen_open(ip, Protocol::Tcp, 22)?.is_some());
+///     assert_eq!(store.host_history(ip)?.len(), 1);
+///     Ok(())
+/// }
+/// ```
+pub mod history;
+/// Scheduled scans with change notifications
+/// # Examples
+/// ```
+/// use e_libscanner::data::Protocol;
+/// use e_libscanner::frame::result::{PortInfo, PortStatus, ScanResult, ScanStatus};
+/// use e_libscanner::history::MemoryStore;
+/// use e_libscanner::monitor::{Monitor, WebhookNotifier};
+/// use std::io::{Read, Write};
+/// use std::time::Duration;
+/// fn main() -> Result<(), String> {
+///     // local stub webhook server
+///     let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+///     let url = format!("http://{}/hook", server.local_addr().unwrap());
+///     let hook = std::thread::spawn(move || {
+///         let (mut stream, _) = server.accept().unwrap();
+///         let mut request = [0u8; 4096];
+///         let n = stream.read(&mut request).unwrap();
+///         stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+///         String::from_utf8_lossy(&request[..n]).to_string()
+///     });
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut status = PortStatus::Closed;
+///     // any scan works, e.g. a sync scanner; here port 22 opens on the second run
+///     let scan = move || {
+///         let mut result = ScanResult::new();
+///         result.scan_status = ScanStatus::Done;
+///         result.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, status)]);
+///         status = PortStatus::Open;
+///         Ok(result.into())
+///     };
+///     let mut monitor = Monitor::new(scan, MemoryStore::new(), Duration::from_secs(3600));
+///     monitor.add_notifier(WebhookNotifier::new(&url)?);
+///     assert!(monitor.run_once()?.diff.is_empty());
+///     let run = monitor.run_once()?;
+///     assert!(run.errors.is_empty());
+///     assert!(hook.join().unwrap().contains("\"change\":\"port\""));
+///     Ok(())
+/// }
+/// ```
+pub mod monitor;
+/// Allowed exposure policies
+/// # Examples
+/// ```
+/// use e_libscanner::data::Protocol;
+/// use e_libscanner::frame::result::{PortInfo, PortStatus, ScanResult, ScanStatus};
+/// use e_libscanner::output::Banners;
+/// use e_libscanner::policy::{Policy, Violation};
+/// fn main() -> Result<(), String> {
+///     let policy = Policy::parse(
+///         "[[rule]]\nsubnet = \"10.1.0.0/24\"\nallowed_ports = [\"22\", \"443\"]\nrequired_services = [\"https\"]\nforbidden_services = [\"OpenSSH_7\"]\n",
+///     )?;
+///     let ip = "10.1.0.5".parse().unwrap();
+///     let mut result = ScanResult::new();
+///     result.scan_status = ScanStatus::Done;
+///     result.ip_with_port.insert(ip, vec![
+///         PortInfo::new(22, Protocol::Tcp, PortStatus::Open),
+///         PortInfo::new(8080, Protocol::Tcp, PortStatus::Open),
+///     ]);
+///     let mut banners = Banners::new();
+///     banners.set_banner(ip, 22, "SSH-2.0-OpenSSH_7.4");
+///     let report = policy.evaluate(&result, &banners)?;
+///     assert!(!report.is_compliant());
+///     assert!(matches!(report.violations[0], Violation::UnexpectedPort { port: 8080, .. }));
+///     assert_eq!(report.violations[1].to_string(), "10.1.0.5 service https missing, required by 10.1.0.0/24");
+///     assert!(matches!(report.violations[2], Violation::ForbiddenService { port: 22, .. }));
+///     // the CLI form: --model policy --policy policy.toml --results scan.json
+///     Ok(())
+/// }
+/// ```
+pub mod policy;
+/// Prometheus exporter of scanner metrics, scan results and on-demand probes
+/// # Examples
+/// ```
+/// fn main() -> Result<(), String> {
+/// #[cfg(feature = "exporter")]
+/// {
+///     use e_libscanner::data::Protocol;
+///     use e_libscanner::diff::ScanSnapshot;
+///     use e_libscanner::exporter::{parse_modules, snapshot_metrics, Exporter};
+///     use e_libscanner::frame::result::{PortInfo, PortStatus, ScanResult};
+///     use std::io::{Read, Write};
+///     use std::sync::{Arc, Mutex};
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut result = ScanResult::new();
+///     result.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, PortStatus::Open)]);
+///     let text = snapshot_metrics("scan", &ScanSnapshot::new(result), &[ip]);
+///     assert!(text.contains("scan_host_up{ip=\"192.168.1.1\"} 1"));
+///     assert!(text.contains("scan_port_open{ip=\"192.168.1.1\",port=\"22\",protocol=\"tcp\""));
+///     // probe modules of a file; the CLI form: --model exporter --modules modules.toml
+///     let modules = parse_modules("[module.ssh]\nscan = \"tcpconnect\"\nports = [\"22\"]\nservice = true\n")?;
+///     assert!(modules["ssh"].service);
+///     // serve /metrics and /probe?target=..&module=.. until stopped
+///     let exporter = Exporter::new("127.0.0.1:19539".parse().unwrap(), ip);
+///     // without a scope a probe targets a single address
+///     assert!(exporter.probe("192.168.1.0/24", "tcp_connect").is_err());
+///     let stop = Arc::new(Mutex::new(false));
+///     let pstop = Arc::clone(&stop);
+///     let server = std::thread::spawn(move || exporter.run(Some(pstop)));
+///     std::thread::sleep(std::time::Duration::from_millis(200));
+///     let mut stream = std::net::TcpStream::connect("127.0.0.1:19539").unwrap();
+///     stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+///     let mut response = String::new();
+///     stream.read_to_string(&mut response).unwrap();
+///     assert!(response.contains("e_libscanner_packets_sent_total 0"));
+///     *stop.lock().unwrap() = true;
+///     server.join().unwrap()?;
+/// }
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "exporter")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "exporter")))]
+pub mod exporter;
+/// Targets from files and standard input
+/// # Examples
+/// ```
+/// use e_libscanner::input::read_targets;
+/// fn main() -> Result<(), String> {
+///     let targets = read_targets(
+///         "# cmdb export\n192.168.1.0/30\nweb01.lan:80,443\n[fe80::1]:22\n".as_bytes(),
+///     )?;
+///     assert_eq!(targets.len(), 3);
+///     assert_eq!(targets[1].ports, vec![80, 443]);
+///     let target = e_libscanner::input::parse_target_line("dns01.lan:T:53,U:53")?.unwrap();
+///     assert_eq!((target.ports, target.udp_ports), (vec![53], vec![53]));
+///     // or through Opts: --input-file targets.txt, --input-file - for stdin
+///     Ok(())
+/// }
+/// ```
+pub mod input;
+/// Port specifications
+/// # Examples
+/// ```
+/// use e_libscanner::{ports::parse_port_spec, Opts};
+/// fn main() -> Result<(), String> {
+///     let spec = parse_port_spec(&[String::from("20-22,ssh,T:443,U:53")])?;
+///     assert_eq!(spec.tcp, vec![20, 21, 22, 443]);
+///     assert_eq!(spec.udp, vec![20, 21, 22, 53]);
+///     // `U:` adds a UDP scan of the UDP ports
+///     assert_eq!(spec.udp_to_scan(), spec.udp);
+///     assert!(parse_port_spec(&[String::from("80")])?.udp_to_scan().is_empty());
+///     assert_eq!(parse_port_spec(&[String::from("-")])?.tcp.len(), 65535);
+///     assert!(parse_port_spec(&[String::from("30-20")]).is_err());
+///     // --top-ports larger than the frequency table is refused
+///     let opts = Opts::new(Some(&[
+///         "e-libscanner",
+///         "--ips",
+///         "127.0.0.1",
+///         "--top-ports",
+///         "1000",
+///         "--model",
+///         "sync",
+///     ]))?;
+///     assert!(opts.init().is_err());
+///     Ok(())
+/// }
+/// ```
+pub mod ports;
+/// Linux network namespaces
+/// # Examples
+/// ```
+/// use e_libscanner::{netns, Opts};
+/// fn main() -> Result<(), String> {
+///     // run a closure inside `ip netns add lab`
+///     if let Ok(ip) = netns::run_in(Some("lab"), e_libscanner::interface::get_local_ipaddr) {
+///         println!("lab source address {:?}", ip);
+///     }
+///     // scanners created by Opts run inside the namespace
+///     let _opts = Opts::new(Some(&[
+///         "e-libscanner",
+///         "--ips",
+///         "10.0.0.2",
+///         "--netns",
+///         "lab",
+///         "--model",
+///         "sync",
+///     ]))?;
+///     Ok(())
+/// }
+/// ```
+pub mod netns;
+/// JSON and NDJSON output
+/// # Examples
+/// ```
+/// use e_libscanner::data::Protocol;
+/// use e_libscanner::frame::result::{PortInfo, PortStatus, ScanEvent, ScanResult};
+/// use e_libscanner::output::{from_json, to_csv, to_greppable, to_json, Banners, NdjsonWriter, Record};
+/// fn main() -> Result<(), String> {
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut writer = NdjsonWriter::new(vec![]);
+///     // one line per event as it arrives, e.g. from `scan_stream`
+///     let port = PortInfo::new(22, Protocol::Tcp, PortStatus::Open);
+///     writer.write_event(&ScanEvent::Port(ip, port.clone()))?;
+///     let out = String::from_utf8(writer.into_inner()).unwrap();
+///     assert_eq!(
+///         out,
+///         "{\"type\":\"port\",\"ip\":\"192.168.1.1\",\"port\":22,\"protocol\":\"tcp\",\"status\":\"open\",\"service\":\"ssh\"}\n"
+///     );
+///     assert!(matches!(from_json::<Record>(out.trim())?, Record::Port { port: 22, .. }));
+///     // whole results round trip
+///     let mut result = ScanResult::new();
+///     result.ip_with_port.insert(ip, vec![port]);
+///     let result: ScanResult = from_json(&to_json(&result)?)?;
+///     assert_eq!(result.get_open_ports(ip), vec![22]);
+///     // greppable and CSV, with service detection banners joined in
+///     let mut banners = Banners::new();
+///     banners.set_banner(ip, 22, "SSH-2.0-OpenSSH_8.9");
+///     assert!(to_greppable(&result, &banners)
+///         .starts_with("Host: 192.168.1.1 ()\tStatus: Up\tPorts: 22/open/tcp//ssh//SSH-2.0-OpenSSH_8.9/\n"));
+///     assert!(to_csv(&result, &banners).ends_with("192.168.1.1,tcp,22,open,ssh,SSH-2.0-OpenSSH_8.9\r\n"));
+///     Ok(())
+/// }
+/// ```
+pub mod output;
+/// Import nmap XML and masscan results
+/// # Examples
+/// ```
+/// use e_libscanner::import::import_str;
+/// fn main() -> Result<(), String> {
+///     let result = import_str("#masscan\nopen tcp 22 192.168.1.1 1660000000\n# end\n")?;
+///     assert_eq!(result.get_open_ports("192.168.1.1".parse().unwrap()), vec![22]);
+///     // skip discovery, go straight to service detection / OS fingerprinting
+///     #[cfg(feature = "service")]
+///     for detector in e_libscanner::service::ServiceDetector::from_result(&result) {
+///         println!("{:?}", detector.open_ports);
+///     }
+///     #[cfg(feature = "os")]
+///     for target in e_libscanner::os::ProbeTarget::from_result(&result) {
+///         println!("{:?}", target);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub mod import;
+/// Authorized scan scope
+/// # Examples
+/// ```
+/// use e_libscanner::{parse_ip_range_in_scope, scope::Scope};
+/// fn main() -> Result<(), String> {
+///     let mut scope = Scope::parse("allow 192.168.1.0/24\nexclude 192.168.1.1")?;
+///     let (ips, violations) = parse_ip_range_in_scope(
+///         &vec![String::from("192.168.1.1-3"), String::from("10.0.0.1")],
+///         &scope,
+///     )?;
+///     assert_eq!(ips.len(), 2);
+///     for v in violations {
+///         eprintln!("{}", v);
+///     }
+///     // refuse the whole target list on any violation
+///     scope.set_strict(true);
+///     assert!(parse_ip_range_in_scope(&vec![String::from("10.0.0.1")], &scope).is_err());
+///     Ok(())
+/// }
+/// ```
+pub mod scope;
+pub use cmd_input::{
+    parse_ip_range, parse_ip_range_except, parse_ip_range_in_scope,
+    parse_ip_range_in_scope_except, Opts, ScanModelType, ScanOrderType, ScriptsRequired,
+};
@@ -0,0 +1,365 @@
+use crate::diff::{Change, ScanDiff, ScanSnapshot};
+use crate::frame::result::ScanStatus;
+use crate::history::{HistoryStore, ScanRun};
+use crate::output::NdjsonWriter;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest sleep between checks of the stop flag while waiting for the next run
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Most undelivered events kept per notifier, older ones are dropped first
+const MAX_PENDING: usize = 1000;
+
+/// Change found by a monitor run
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MonitorEvent {
+    /// Id of the run that found the change
+    pub run_id: u64,
+    /// Time of the run in seconds since the unix epoch
+    pub time: u64,
+    /// The change
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+impl fmt::Display for MonitorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[run {}] {}", self.run_id, self.change)
+    }
+}
+
+/// Delivery of change events
+pub trait Notifier {
+    /// Deliver one event
+    fn notify(&mut self, event: &MonitorEvent) -> Result<(), String>;
+}
+
+impl<N: Notifier + ?Sized> Notifier for Box<N> {
+    fn notify(&mut self, event: &MonitorEvent) -> Result<(), String> {
+        (**self).notify(event)
+    }
+}
+
+/// Notifier of a command line spec: `stdout`, `jsonl:<path>` or `webhook:<url>`
+pub fn parse_notifier(spec: &str) -> Result<Box<dyn Notifier + Send>, String> {
+    match spec.split_once(':') {
+        _ if spec == "stdout" => Ok(Box::new(StdoutNotifier)),
+        Some(("jsonl", path)) => Ok(Box::new(JsonlNotifier::open(path)?)),
+        Some(("webhook", url)) => Ok(Box::new(WebhookNotifier::new(url)?)),
+        _ => Err(format!(
+            "Invalid notifier {}, use stdout, jsonl:<path> or webhook:<url>",
+            spec
+        )),
+    }
+}
+
+/// Prints events as text lines on stdout
+#[derive(Clone, Debug, Default)]
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn notify(&mut self, event: &MonitorEvent) -> Result<(), String> {
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "{}", event)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Appends events to a JSONL file, one JSON event per line
+#[derive(Debug)]
+pub struct JsonlNotifier {
+    writer: NdjsonWriter<File>,
+}
+
+impl JsonlNotifier {
+    /// Open the file for appending, creating it if missing
+    pub fn open(path: &str) -> Result<JsonlNotifier, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        Ok(JsonlNotifier {
+            writer: NdjsonWriter::new(file),
+        })
+    }
+}
+
+impl Notifier for JsonlNotifier {
+    fn notify(&mut self, event: &MonitorEvent) -> Result<(), String> {
+        self.writer.write(event)
+    }
+}
+
+/// Posts every event as JSON to an HTTP webhook
+#[derive(Clone, Debug)]
+pub struct WebhookNotifier {
+    host: String,
+    port: u16,
+    path: String,
+    /// Connect, write and read timeout
+    pub timeout: Duration,
+}
+
+impl WebhookNotifier {
+    /// Webhook at a plain `http://host[:port]/path` URL
+    pub fn new(url: &str) -> Result<WebhookNotifier, String> {
+        let rest = url.strip_prefix("http://").ok_or(format!(
+            "Unsupported webhook URL {}, only http:// is supported",
+            url
+        ))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        // bracketed IPv6 literal, e.g. [::1]:8080
+        let (host, port) = match authority.rfind(':') {
+            Some(i) if !authority[i..].contains(']') => (
+                &authority[..i],
+                authority[i + 1..]
+                    .parse()
+                    .map_err(|_| format!("Invalid port in webhook URL {}", url))?,
+            ),
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in webhook URL {}", url));
+        }
+        Ok(WebhookNotifier {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+            timeout: Duration::from_secs(5),
+        })
+    }
+    /// Set connect, write and read timeout
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&mut self, event: &MonitorEvent) -> Result<(), String> {
+        let body = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        let addr = (host, self.port)
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or(format!("Cannot resolve webhook host {}", self.host))?;
+        let mut stream =
+            TcpStream::connect_timeout(&addr, self.timeout).map_err(|e| e.to_string())?;
+        let _ = stream.set_read_timeout(Some(self.timeout));
+        let _ = stream.set_write_timeout(Some(self.timeout));
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            self.port,
+            body.len(),
+            body
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        let status = response.split_whitespace().nth(1).unwrap_or_default();
+        if status.starts_with('2') {
+            Ok(())
+        } else {
+            Err(format!(
+                "Webhook {}:{}{} answered {:?}",
+                self.host,
+                self.port,
+                self.path,
+                response.lines().next().unwrap_or_default()
+            ))
+        }
+    }
+}
+
+/// Outcome of one monitor run
+#[derive(Clone, Debug)]
+pub struct MonitorRun {
+    /// Id of the recorded run
+    pub run_id: u64,
+    /// Changes against the previous run; empty for the first run
+    pub diff: ScanDiff,
+    /// Delivery errors; undelivered events are retried on the next run
+    pub errors: Vec<String>,
+}
+
+/// Notifier with the events it failed to deliver, oldest first
+struct Delivery {
+    notifier: Box<dyn Notifier + Send>,
+    pending: Vec<MonitorEvent>,
+}
+
+impl Delivery {
+    /// Deliver the pending events then `events` in order, queueing them from the first failure
+    fn deliver(&mut self, events: &[MonitorEvent]) -> Result<(), String> {
+        self.pending.extend_from_slice(events);
+        let mut delivered = 0;
+        let mut result = Ok(());
+        for event in self.pending.iter() {
+            if let Err(e) = self.notifier.notify(event) {
+                result = Err(e);
+                break;
+            }
+            delivered += 1;
+        }
+        self.pending.drain(..delivered);
+        if self.pending.len() > MAX_PENDING {
+            let dropped = self.pending.len() - MAX_PENDING;
+            self.pending.drain(..dropped);
+            result = result.map_err(|e| format!("{}; {} undelivered events dropped", e, dropped));
+        }
+        result
+    }
+}
+
+/// Re-runs a scan on a schedule and notifies about changes
+///
+/// Each run is compared with the previous run in the history store, so a
+/// monitor with a file store picks up where the last process stopped.
+pub struct Monitor {
+    /// Time between the starts of two runs
+    pub interval: Duration,
+    /// Parameters recorded with every run
+    pub params: BTreeMap<String, String>,
+    scan: Box<dyn FnMut() -> Result<ScanSnapshot, String> + Send>,
+    store: Box<dyn HistoryStore + Send>,
+    deliveries: Vec<Delivery>,
+}
+
+impl fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Monitor")
+            .field("interval", &self.interval)
+            .field("params", &self.params)
+            .field("notifiers", &self.deliveries.len())
+            .finish()
+    }
+}
+
+impl Monitor {
+    /// Monitor running `scan` every `interval`, keeping the runs in `store`
+    pub fn new<F, S>(scan: F, store: S, interval: Duration) -> Monitor
+    where
+        F: FnMut() -> Result<ScanSnapshot, String> + Send + 'static,
+        S: HistoryStore + Send + 'static,
+    {
+        Monitor {
+            interval,
+            params: BTreeMap::new(),
+            scan: Box::new(scan),
+            store: Box::new(store),
+            deliveries: vec![],
+        }
+    }
+    /// Set time between the starts of two runs
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+    /// Set a parameter recorded with every run
+    pub fn set_param(&mut self, key: &str, value: &str) {
+        self.params.insert(key.to_owned(), value.to_owned());
+    }
+    /// Add an event delivery
+    pub fn add_notifier<N: Notifier + Send + 'static>(&mut self, notifier: N) {
+        self.deliveries.push(Delivery {
+            notifier: Box::new(notifier),
+            pending: vec![],
+        });
+    }
+    /// Number of events waiting to be delivered again, over all notifiers
+    pub fn get_pending(&self) -> usize {
+        self.deliveries.iter().map(|d| d.pending.len()).sum()
+    }
+    /// History of the runs
+    pub fn get_store(&self) -> &dyn HistoryStore {
+        self.store.as_ref()
+    }
+    /// Scan once, record the run and notify about the changes
+    ///
+    /// Only scans that are `Done` are recorded; failed or timed out scans
+    /// would show the hosts they missed as gone. Events a notifier fails to
+    /// deliver are queued and sent again before the events of the next run.
+    pub fn run_once(&mut self) -> Result<MonitorRun, String> {
+        let started = SystemTime::now();
+        let snapshot = (self.scan)()?;
+        if snapshot.result.scan_status != ScanStatus::Done {
+            let mut message = format!(
+                "Scan ended with status {:?}, run not recorded",
+                snapshot.result.scan_status
+            );
+            for error in snapshot.result.errors.iter() {
+                message.push_str(": ");
+                message.push_str(error);
+            }
+            return Err(message);
+        }
+        let previous = self.store.last_run()?;
+        let diff = match &previous {
+            Some(previous) => ScanDiff::new(&previous.snapshot, &snapshot),
+            None => ScanDiff::default(),
+        };
+        let mut run = ScanRun::new(snapshot);
+        run.set_started(started);
+        run.params = self.params.clone();
+        let time = run
+            .finished
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let run_id = self.store.record(run)?;
+        let events: Vec<MonitorEvent> = diff
+            .changes
+            .iter()
+            .map(|change| MonitorEvent {
+                run_id,
+                time,
+                change: change.clone(),
+            })
+            .collect();
+        let mut errors = vec![];
+        for delivery in self.deliveries.iter_mut() {
+            if let Err(e) = delivery.deliver(&events) {
+                errors.push(e);
+            }
+        }
+        Ok(MonitorRun {
+            run_id,
+            diff,
+            errors,
+        })
+    }
+    /// Run until stopped, `report` gets the outcome of every run
+    pub fn run<R>(&mut self, pstop: Option<Arc<Mutex<bool>>>, mut report: R)
+    where
+        R: FnMut(Result<MonitorRun, String>),
+    {
+        let stopped = || pstop.as_ref().map_or(false, |p| *p.lock().unwrap());
+        while !stopped() {
+            let start = Instant::now();
+            report(self.run_once());
+            while !stopped() {
+                let elapsed = start.elapsed();
+                if elapsed >= self.interval {
+                    break;
+                }
+                thread::sleep((self.interval - elapsed).min(STOP_POLL));
+            }
+        }
+    }
+}
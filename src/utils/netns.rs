@@ -0,0 +1,52 @@
+use std::thread;
+
+/// Directory where `ip netns add` creates named network namespaces
+pub const NETNS_RUN_DIR: &str = "/var/run/netns";
+
+/// Move the calling thread into the named network namespace
+///
+/// Only the calling thread changes namespace, threads it spawns afterwards
+/// inherit it. Requires CAP_SYS_ADMIN. Linux only.
+pub fn enter(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains('/') {
+        return Err(format!("invalid network namespace name -> {}", name));
+    }
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let path = format!("{}/{}", NETNS_RUN_DIR, name);
+        let file = std::fs::File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+        if unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) } != 0 {
+            return Err(format!(
+                "Failed to enter {}: {}",
+                path,
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    Err(String::from("network namespaces are Linux only"))
+}
+
+/// Run `f` on a new thread inside the named network namespace
+///
+/// Without a namespace `f` runs on the calling thread.
+pub fn run_in<F, T>(name: Option<&str>, f: F) -> Result<T, String>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match name {
+        Some(name) => {
+            let name = name.to_string();
+            thread::spawn(move || {
+                enter(&name)?;
+                Ok(f())
+            })
+            .join()
+            .map_err(|_| String::from("network namespace thread panicked"))?
+        }
+        None => Ok(f()),
+    }
+}
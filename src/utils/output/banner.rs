@@ -0,0 +1,56 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// Service detection banners by host and TCP port, joined into the text reports
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Banners {
+    banners: BTreeMap<IpAddr, BTreeMap<u16, String>>,
+}
+
+impl Banners {
+    /// Create an empty banner set
+    pub fn new() -> Banners {
+        Banners::default()
+    }
+    /// Banners of service detection results
+    #[cfg(feature = "service")]
+    pub fn from_service_results(results: &[crate::service::ScanServiceResult]) -> Banners {
+        let mut banners = Banners::new();
+        for result in results {
+            banners.add_service_result(result);
+        }
+        banners
+    }
+    /// Set banner of a TCP port
+    pub fn set_banner(&mut self, ip: IpAddr, port: u16, banner: &str) {
+        self.banners
+            .entry(ip)
+            .or_default()
+            .insert(port, banner.to_owned());
+    }
+    /// Get banner of a TCP port
+    pub fn get_banner(&self, ip: IpAddr, port: u16) -> Option<&str> {
+        self.banners.get(&ip)?.get(&port).map(|b| b.as_str())
+    }
+    /// Set banners of a service detection result
+    #[cfg(feature = "service")]
+    pub fn add_service_result(&mut self, result: &crate::service::ScanServiceResult) {
+        for (port, banner) in &result.ports {
+            self.set_banner(result.dst_ip, *port, banner);
+        }
+    }
+    /// Banners of a host by port
+    pub fn get_host_banners(&self, ip: IpAddr) -> Option<&BTreeMap<u16, String>> {
+        self.banners.get(&ip)
+    }
+    /// Number of banners
+    pub fn len(&self) -> usize {
+        self.banners.values().map(|ports| ports.len()).sum()
+    }
+    /// No banner was set
+    pub fn is_empty(&self) -> bool {
+        self.banners.is_empty()
+    }
+}
@@ -0,0 +1,57 @@
+use super::{port_state, Banners};
+use crate::data::Protocol;
+use crate::frame::result::ScanResult;
+use std::io::Write;
+use std::net::IpAddr;
+
+/// Header row of the CSV export
+const CSV_HEADER: &str = "ip,proto,port,state,service,banner";
+
+/// Render a scan result as CSV, one row per scanned port of a host
+pub fn to_csv(result: &ScanResult, banners: &Banners) -> String {
+    let mut text = String::from(CSV_HEADER);
+    text.push_str("\r\n");
+    let mut ips: Vec<&IpAddr> = result.ip_with_port.keys().collect();
+    ips.sort();
+    for ip in ips {
+        let mut ports: Vec<_> = result.ip_with_port[ip].iter().collect();
+        ports.sort_by_key(|p| (p.protocol, p.port));
+        for p in ports {
+            let banner = match p.protocol {
+                Protocol::Tcp => banners.get_banner(*ip, p.port).unwrap_or_default(),
+                _ => "",
+            };
+            let row = [
+                ip.to_string(),
+                p.protocol.to_string(),
+                p.port.to_string(),
+                port_state(p.status).to_owned(),
+                quote(&p.describe),
+                quote(banner),
+            ];
+            text.push_str(&row.join(","));
+            text.push_str("\r\n");
+        }
+    }
+    text
+}
+
+/// Write a scan result as CSV
+pub fn write_csv<W: Write>(
+    mut writer: W,
+    result: &ScanResult,
+    banners: &Banners,
+) -> Result<(), String> {
+    writer
+        .write_all(to_csv(result, banners).as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// Quote a field when it holds a separator, quote or line break (RFC 4180)
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
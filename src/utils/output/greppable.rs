@@ -0,0 +1,87 @@
+use super::{hosts_of, port_state, Banners};
+use crate::data::Protocol;
+use crate::frame::result::{PortStatus, ScanResult};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// Render a scan result in the greppable format, one line per host
+///
+/// Lines look like nmap's `-oG`, tab separated fields with the open ports as
+/// `port/state/protocol//service//banner/`, for example
+/// `Host: 192.168.1.1 () Status: Up Ports: 22/open/tcp//ssh//SSH-2.0-OpenSSH_8.9/`
+pub fn to_greppable(result: &ScanResult, banners: &Banners) -> String {
+    let mut text = String::new();
+    let hosts = hosts_of(result);
+    for ip in &hosts {
+        let status = if result.is_host_up(*ip) { "Up" } else { "Down" };
+        let _ = write!(text, "Host: {} ()\tStatus: {}", ip, status);
+        let mut ports: Vec<_> = result
+            .ip_with_port
+            .get(ip)
+            .map(|ports| {
+                ports
+                    .iter()
+                    .filter(|p| matches!(p.status, PortStatus::Open | PortStatus::OpenFiltered))
+                    .collect()
+            })
+            .unwrap_or_default();
+        ports.sort_by_key(|p| (p.protocol, p.port));
+        let ports: Vec<String> = ports
+            .iter()
+            .map(|p| {
+                let banner = match p.protocol {
+                    Protocol::Tcp => banners.get_banner(*ip, p.port).unwrap_or_default(),
+                    _ => "",
+                };
+                format!(
+                    "{}/{}/{}//{}//{}/",
+                    p.port,
+                    port_state(p.status),
+                    p.protocol,
+                    field(&p.describe),
+                    field(banner)
+                )
+            })
+            .collect();
+        if !ports.is_empty() {
+            let _ = write!(text, "\tPorts: {}", ports.join(", "));
+        }
+        text.push('\n');
+    }
+    let up = hosts.iter().filter(|ip| result.is_host_up(**ip)).count();
+    let _ = writeln!(
+        text,
+        "# e-libscanner done: {} IP address{} ({} host{} up) scanned in {:.2} seconds",
+        hosts.len(),
+        if hosts.len() == 1 { "" } else { "es" },
+        up,
+        if up == 1 { "" } else { "s" },
+        result.scan_time.as_secs_f64()
+    );
+    text
+}
+
+/// Write a scan result in the greppable format
+pub fn write_greppable<W: Write>(
+    mut writer: W,
+    result: &ScanResult,
+    banners: &Banners,
+) -> Result<(), String> {
+    writer
+        .write_all(to_greppable(result, banners).as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// Keep a field on its line and out of the port separators; `/` becomes `|` like nmap
+fn field(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '/' => '|',
+            ',' => ';',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
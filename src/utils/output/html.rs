@@ -0,0 +1,349 @@
+use super::{escape, hosts_of, port_state, Banners, OsMatch};
+use crate::data::Protocol;
+use crate::frame::result::{PortInfo, PortStatus, ScanResult};
+use crate::traceroute::TracertQueryResult;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Inlined style sheet of the report
+const STYLE: &str = r#"
+body{font-family:system-ui,-apple-system,"Segoe UI",sans-serif;margin:2em;color:#222}
+h1{margin-bottom:0}.meta{color:#666;margin-top:.3em}
+.cards{display:flex;flex-wrap:wrap;gap:1em;margin:1.5em 0}
+.card{border:1px solid #ddd;border-radius:6px;padding:.8em 1.2em;min-width:8em}
+.card b{display:block;font-size:1.6em}
+table{border-collapse:collapse;margin:.5em 0 1.5em;min-width:40%}
+th,td{border:1px solid #ddd;padding:.3em .7em;text-align:left;vertical-align:top}
+th{background:#f3f3f3}table.sortable th{cursor:pointer;user-select:none}
+th.asc::after{content:" \25B2"}th.desc::after{content:" \25BC"}
+td.open{color:#17803d;font-weight:bold}td.closed{color:#b42318}td.filtered,td.open-filtered{color:#8a6d00}
+section.host{border-top:2px solid #ddd;margin-top:2em}
+pre{white-space:pre-wrap;word-break:break-all;margin:0}
+"#;
+
+/// Inlined script sorting a table by the clicked column; numbers and addresses sort numerically
+const SCRIPT: &str = r#"
+function key(v){var p=v.split(/[.:\/]/);if(p.length>1&&p.every(function(x){return /^[0-9]+$/.test(x)}))return p.map(function(x){return ("0000000000"+x).slice(-10)}).join(".");return /^-?[0-9.]+$/.test(v)?("0000000000"+parseFloat(v).toFixed(3)).slice(-14):v.toLowerCase()}
+document.querySelectorAll("table.sortable").forEach(function(t){t.querySelectorAll("th").forEach(function(th,i){th.addEventListener("click",function(){var asc=!th.classList.contains("asc");t.querySelectorAll("th").forEach(function(h){h.classList.remove("asc","desc")});th.classList.add(asc?"asc":"desc");var b=t.tBodies[0];Array.from(b.rows).sort(function(x,y){var a=key(x.cells[i].textContent),c=key(y.cells[i].textContent);return (a<c?-1:a>c?1:0)*(asc?1:-1)}).forEach(function(r){b.appendChild(r)})})})});
+"#;
+
+/// Details about a host not carried by the scan result
+#[derive(Clone, Debug, Default)]
+struct HostDetail {
+    os_matches: Vec<OsMatch>,
+    os_probes: Vec<(String, String)>,
+    hops: Vec<TracertQueryResult>,
+}
+
+/// Single file HTML report of a completed scan
+///
+/// Styles and the table sorting script are inlined, so the file works offline.
+/// # Examples
+/// ```
+/// use e_libscanner::frame::result::{PortInfo, PortStatus, ScanResult};
+/// use e_libscanner::{data::Protocol, output::HtmlReport};
+/// fn main() -> Result<(), String> {
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut result = ScanResult::new();
+///     result.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, PortStatus::Open)]);
+///     let mut report = HtmlReport::new("Weekly scan");
+///     report.set_banner(ip, 22, "SSH-2.0-OpenSSH_8.9");
+///     let html = report.render(&result);
+///     assert!(html.contains("<title>Weekly scan</title>"));
+///     assert!(html.contains("SSH-2.0-OpenSSH_8.9"));
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HtmlReport {
+    /// Title of the report
+    pub title: String,
+    /// Time the scan started
+    pub start_time: SystemTime,
+    /// Service detection banners
+    pub banners: Banners,
+    hosts: BTreeMap<IpAddr, HostDetail>,
+}
+
+impl HtmlReport {
+    /// Create a report of a scan that starts now
+    pub fn new(title: &str) -> HtmlReport {
+        HtmlReport {
+            title: title.to_owned(),
+            start_time: SystemTime::now(),
+            banners: Banners::new(),
+            hosts: BTreeMap::new(),
+        }
+    }
+    /// Set time the scan started
+    pub fn set_start_time(&mut self, start_time: SystemTime) {
+        self.start_time = start_time;
+    }
+    /// Set service detection banners
+    pub fn set_banners(&mut self, banners: Banners) {
+        self.banners = banners;
+    }
+    /// Set banner of a TCP port found by service detection
+    pub fn set_banner(&mut self, ip: IpAddr, port: u16, banner: &str) {
+        self.banners.set_banner(ip, port, banner);
+    }
+    /// Set banners of a service detection result
+    #[cfg(feature = "service")]
+    pub fn add_service_result(&mut self, result: &crate::service::ScanServiceResult) {
+        self.banners.add_service_result(result);
+    }
+    /// Add OS guess of a host
+    pub fn add_os_match(&mut self, ip: IpAddr, os_match: OsMatch) {
+        self.host(ip).os_matches.push(os_match);
+    }
+    /// Add probe results of the os scanner, one row per answered probe
+    #[cfg(feature = "os")]
+    pub fn add_probe_result(&mut self, result: &crate::os::ProbeResult) -> Result<(), String> {
+        let value = serde_json::to_value(result).map_err(|e| e.to_string())?;
+        let probes = &mut self.host(result.ip_addr).os_probes;
+        if let serde_json::Value::Object(fields) = value {
+            for (name, value) in fields {
+                if name != "ip_addr" && !value.is_null() {
+                    probes.push((name, value.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Set traceroute hops of a host
+    pub fn set_trace(&mut self, ip: IpAddr, hops: Vec<TracertQueryResult>) {
+        self.host(ip).hops = hops;
+    }
+    /// Render the report of a scan result
+    pub fn render(&self, result: &ScanResult) -> String {
+        let hosts = hosts_of(result);
+        let ports: Vec<(IpAddr, &PortInfo)> = hosts
+            .iter()
+            .flat_map(|ip| {
+                let mut ports: Vec<&PortInfo> = result
+                    .ip_with_port
+                    .get(ip)
+                    .map(|ports| ports.iter().collect())
+                    .unwrap_or_default();
+                ports.sort_by_key(|p| (p.protocol, p.port));
+                ports.into_iter().map(move |p| (*ip, p))
+            })
+            .collect();
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title)
+        );
+        let start = self
+            .start_time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let _ = writeln!(
+            html,
+            "<p class=\"meta\">Started at {} (unix time), took {:.2} seconds, status {:?}; e-libscanner {}</p>",
+            start,
+            result.scan_time.as_secs_f64(),
+            result.scan_status,
+            env!("CARGO_PKG_VERSION")
+        );
+        // summary
+        let count = |status: PortStatus| ports.iter().filter(|(_, p)| p.status == status).count();
+        html.push_str("<div class=\"cards\">");
+        for (name, value) in [
+            (
+                "Hosts up",
+                hosts.iter().filter(|ip| result.is_host_up(**ip)).count(),
+            ),
+            ("Ports scanned", ports.len()),
+            ("Open", count(PortStatus::Open)),
+            ("Open|Filtered", count(PortStatus::OpenFiltered)),
+            ("Closed", count(PortStatus::Closed)),
+            ("Filtered", count(PortStatus::Filtered)),
+        ] {
+            let _ = write!(
+                html,
+                "<div class=\"card\"><b>{}</b>{}</div>",
+                value,
+                escape(name)
+            );
+        }
+        html.push_str("</div>\n");
+        let mut services: HashMap<&str, usize> = HashMap::new();
+        for (_, p) in ports.iter().filter(|(_, p)| p.status == PortStatus::Open) {
+            *services.entry(p.describe.as_str()).or_default() += 1;
+        }
+        if !services.is_empty() {
+            let mut services: Vec<(&str, usize)> = services.into_iter().collect();
+            services.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            html.push_str("<h2>Open services</h2>\n<table class=\"sortable\"><thead><tr><th>Service</th><th>Open ports</th></tr></thead><tbody>\n");
+            for (service, n) in services {
+                let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", escape(service), n);
+            }
+            html.push_str("</tbody></table>\n");
+        }
+        // hosts
+        html.push_str("<h2>Hosts</h2>\n<table class=\"sortable\"><thead><tr><th>Address</th><th>TTL</th><th>Open</th><th>Closed</th><th>Filtered</th><th>OS</th></tr></thead><tbody>\n");
+        for ip in &hosts {
+            let host_ports: Vec<&PortInfo> = ports
+                .iter()
+                .filter(|(i, _)| i == ip)
+                .map(|(_, p)| *p)
+                .collect();
+            let count =
+                |f: fn(PortStatus) -> bool| host_ports.iter().filter(|p| f(p.status)).count();
+            let _ = writeln!(
+                html,
+                "<tr><td><a href=\"#host-{ip}\">{ip}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                ttl_of(result, *ip).map(|t| t.to_string()).unwrap_or_default(),
+                count(|s| s == PortStatus::Open),
+                count(|s| s == PortStatus::Closed),
+                count(|s| s == PortStatus::Filtered),
+                self.hosts
+                    .get(ip)
+                    .and_then(|d| d.os_matches.first())
+                    .map(|m| escape(&m.name))
+                    .unwrap_or_default(),
+                ip = ip
+            );
+        }
+        html.push_str("</tbody></table>\n");
+        // ports
+        if !ports.is_empty() {
+            html.push_str("<h2>Ports</h2>\n<table class=\"sortable\"><thead><tr><th>Address</th><th>Port</th><th>Protocol</th><th>State</th><th>Service</th><th>Banner</th></tr></thead><tbody>\n");
+            for (ip, p) in &ports {
+                let _ = writeln!(html, "<tr><td>{}</td>{}</tr>", ip, self.port_cells(*ip, p));
+            }
+            html.push_str("</tbody></table>\n");
+        }
+        // host details
+        for ip in &hosts {
+            self.render_host(&mut html, result, *ip, &ports);
+        }
+        let _ = write!(html, "<script>{}</script>\n</body>\n</html>\n", SCRIPT);
+        html
+    }
+    /// Write the report of a scan result
+    pub fn write<W: Write>(&self, writer: &mut W, result: &ScanResult) -> Result<(), String> {
+        writer
+            .write_all(self.render(result).as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    fn host(&mut self, ip: IpAddr) -> &mut HostDetail {
+        self.hosts.entry(ip).or_default()
+    }
+    fn port_cells(&self, ip: IpAddr, p: &PortInfo) -> String {
+        let banner = match p.protocol {
+            Protocol::Tcp => self.banners.get_banner(ip, p.port).unwrap_or_default(),
+            _ => "",
+        };
+        let state = port_state(p.status);
+        format!(
+            "<td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td><pre>{}</pre></td>",
+            p.port,
+            p.protocol,
+            state.replace('|', "-"),
+            state,
+            escape(&p.describe),
+            escape(banner)
+        )
+    }
+    fn render_host(
+        &self,
+        html: &mut String,
+        result: &ScanResult,
+        ip: IpAddr,
+        ports: &[(IpAddr, &PortInfo)],
+    ) {
+        let _ = writeln!(
+            html,
+            "<section class=\"host\" id=\"host-{ip}\">\n<h2>{ip}</h2>",
+            ip = ip
+        );
+        if let Some(ttl) = ttl_of(result, ip) {
+            let _ = writeln!(html, "<p>Host is up, TTL {}</p>", ttl);
+        }
+        let host_ports: Vec<&PortInfo> = ports
+            .iter()
+            .filter(|(i, _)| *i == ip)
+            .map(|(_, p)| *p)
+            .collect();
+        if !host_ports.is_empty() {
+            html.push_str("<h3>Ports</h3>\n<table class=\"sortable\"><thead><tr><th>Port</th><th>Protocol</th><th>State</th><th>Service</th><th>Banner</th></tr></thead><tbody>\n");
+            for p in host_ports {
+                let _ = writeln!(html, "<tr>{}</tr>", self.port_cells(ip, p));
+            }
+            html.push_str("</tbody></table>\n");
+        }
+        if let Some(detail) = self.hosts.get(&ip) {
+            if !detail.os_matches.is_empty() || !detail.os_probes.is_empty() {
+                html.push_str("<h3>OS</h3>\n");
+            }
+            if !detail.os_matches.is_empty() {
+                html.push_str(
+                    "<table><thead><tr><th>Guess</th><th>Accuracy</th></tr></thead><tbody>\n",
+                );
+                for m in &detail.os_matches {
+                    let _ = writeln!(
+                        html,
+                        "<tr><td>{}</td><td>{}%</td></tr>",
+                        escape(&m.name),
+                        m.accuracy
+                    );
+                }
+                html.push_str("</tbody></table>\n");
+            }
+            if !detail.os_probes.is_empty() {
+                html.push_str(
+                    "<table><thead><tr><th>Probe</th><th>Result</th></tr></thead><tbody>\n",
+                );
+                for (name, value) in &detail.os_probes {
+                    let _ = writeln!(
+                        html,
+                        "<tr><td>{}</td><td><pre>{}</pre></td></tr>",
+                        escape(name),
+                        escape(value)
+                    );
+                }
+                html.push_str("</tbody></table>\n");
+            }
+            if !detail.hops.is_empty() {
+                html.push_str("<h3>Traceroute</h3>\n<table><thead><tr><th>Hop</th><th>RTT (ms)</th><th>Address</th></tr></thead><tbody>\n");
+                for hop in &detail.hops {
+                    let mut addrs: Vec<&String> = hop.addr.iter().flatten().collect();
+                    addrs.dedup();
+                    let addrs: Vec<String> = addrs.into_iter().map(|a| escape(a)).collect();
+                    let _ = writeln!(
+                        html,
+                        "<tr><td>{}</td><td>{:.2}</td><td>{}</td></tr>",
+                        hop.id,
+                        hop.rtt.as_secs_f64() * 1000.0,
+                        if addrs.is_empty() {
+                            String::from("*")
+                        } else {
+                            addrs.join(", ")
+                        }
+                    );
+                }
+                html.push_str("</tbody></table>\n");
+            }
+        }
+        html.push_str("</section>\n");
+    }
+}
+
+fn ttl_of(result: &ScanResult, ip: IpAddr) -> Option<u8> {
+    result
+        .ips
+        .iter()
+        .find(|host| host.ip_addr == ip)
+        .map(|host| host.ttl)
+}
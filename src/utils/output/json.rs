@@ -0,0 +1,159 @@
+use crate::data::Protocol;
+use crate::frame::result::{PortStatus, ScanEvent, ScanResult, ScanStatus};
+use serde_derive::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Record of the NDJSON output; one per host, port, error or finished scan
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    /// Host is up
+    Host {
+        /// IP address of the host
+        ip: IpAddr,
+        /// IP Time to Live (Hop Limit)
+        ttl: u8,
+    },
+    /// Port status of a host
+    Port {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Port number
+        port: u16,
+        /// Transport protocol
+        protocol: Protocol,
+        /// Port status
+        status: PortStatus,
+        /// Service name
+        service: String,
+    },
+    /// Non fatal error during the scan
+    Error {
+        /// Error message
+        message: String,
+    },
+    /// Scan finished
+    Done {
+        /// Status of the scan task
+        scan_status: ScanStatus,
+        /// Time taken to scan
+        scan_time: Duration,
+    },
+}
+
+impl Record {
+    /// Record of a scan event; probes give None
+    pub fn from_event(event: &ScanEvent) -> Option<Record> {
+        match event {
+            ScanEvent::Probe(_) => None,
+            ScanEvent::Host(host_info) => Some(Record::Host {
+                ip: host_info.ip_addr,
+                ttl: host_info.ttl,
+            }),
+            ScanEvent::Port(ip, port_info) => Some(Record::Port {
+                ip: *ip,
+                port: port_info.port,
+                protocol: port_info.protocol,
+                status: port_info.status,
+                service: port_info.describe.clone(),
+            }),
+            ScanEvent::Error(message) => Some(Record::Error {
+                message: message.clone(),
+            }),
+            ScanEvent::Done(result) => Some(Record::Done {
+                scan_status: result.scan_status.clone(),
+                scan_time: result.scan_time,
+            }),
+        }
+    }
+    /// Records of a finished scan: hosts, then ports ordered by address, then done
+    pub fn from_result(result: &ScanResult) -> Vec<Record> {
+        let mut records: Vec<Record> = result
+            .ips
+            .iter()
+            .map(|host_info| Record::Host {
+                ip: host_info.ip_addr,
+                ttl: host_info.ttl,
+            })
+            .collect();
+        let mut ips: Vec<&IpAddr> = result.ip_with_port.keys().collect();
+        ips.sort();
+        for ip in ips {
+            for port_info in result.ip_with_port[ip].iter() {
+                records.push(Record::Port {
+                    ip: *ip,
+                    port: port_info.port,
+                    protocol: port_info.protocol,
+                    status: port_info.status,
+                    service: port_info.describe.clone(),
+                });
+            }
+        }
+        records.push(Record::Done {
+            scan_status: result.scan_status.clone(),
+            scan_time: result.scan_time,
+        });
+        records
+    }
+}
+
+/// Serialize value to pretty printed JSON
+pub fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
+
+/// Write value as pretty printed JSON
+pub fn write_json<W: Write, T: serde::Serialize + ?Sized>(
+    mut writer: W,
+    value: &T,
+) -> Result<(), String> {
+    serde_json::to_writer_pretty(&mut writer, value).map_err(|e| e.to_string())?;
+    writer.write_all(b"\n").map_err(|e| e.to_string())
+}
+
+/// Parse value from JSON
+pub fn from_json<'a, T: serde::Deserialize<'a>>(json: &'a str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+/// Streaming NDJSON writer, one JSON document per line
+///
+/// Every line is flushed as soon as it is written, so readers on the other
+/// end of a pipe see records while the scan is still running.
+#[derive(Debug)]
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    /// Create new writer
+    pub fn new(writer: W) -> NdjsonWriter<W> {
+        NdjsonWriter { writer }
+    }
+    /// Write any serializable value as one line
+    pub fn write<T: serde::Serialize + ?Sized>(&mut self, value: &T) -> Result<(), String> {
+        serde_json::to_writer(&mut self.writer, value).map_err(|e| e.to_string())?;
+        self.writer.write_all(b"\n").map_err(|e| e.to_string())?;
+        self.writer.flush().map_err(|e| e.to_string())
+    }
+    /// Write record of a scan event; returns false if the event has none
+    pub fn write_event(&mut self, event: &ScanEvent) -> Result<bool, String> {
+        match Record::from_event(event) {
+            Some(record) => self.write(&record).map(|_| true),
+            None => Ok(false),
+        }
+    }
+    /// Write records of a finished scan
+    pub fn write_result(&mut self, result: &ScanResult) -> Result<(), String> {
+        for record in Record::from_result(result) {
+            self.write(&record)?;
+        }
+        Ok(())
+    }
+    /// Get the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,59 @@
+mod banner;
+mod csv;
+mod greppable;
+mod html;
+mod json;
+mod xml;
+
+pub use banner::*;
+pub use csv::*;
+pub use greppable::*;
+pub use html::*;
+pub use json::*;
+pub use xml::*;
+
+use crate::frame::result::{PortStatus, ScanResult};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::net::IpAddr;
+
+/// Hosts that answered the host scan or have ports in the result, in address order
+///
+/// Hosts with filtered ports only are listed too; `ScanResult::is_host_up` tells them apart.
+fn hosts_of(result: &ScanResult) -> BTreeSet<IpAddr> {
+    result
+        .ips
+        .iter()
+        .map(|host| host.ip_addr)
+        .chain(result.ip_with_port.keys().copied())
+        .collect()
+}
+
+/// Nmap name of the port state
+fn port_state(status: PortStatus) -> &'static str {
+    match status {
+        PortStatus::Open => "open",
+        PortStatus::Closed => "closed",
+        PortStatus::Filtered => "filtered",
+        PortStatus::OpenFiltered => "open|filtered",
+    }
+}
+
+/// Escape text for XML and HTML; control characters become `\xNN` like nmap
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\x{:02X}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
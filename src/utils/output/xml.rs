@@ -0,0 +1,389 @@
+use super::{escape, hosts_of, port_state};
+use crate::data::Protocol;
+use crate::dns::{DnsResult, DnsResultType};
+use crate::frame::result::{PortInfo, PortStatus, ScanResult, ScanStatus};
+use crate::frame::ScanType;
+use crate::traceroute::TracertQueryResult;
+use pnet_datalink::MacAddr;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the nmap XML output format
+const XML_OUTPUT_VERSION: &str = "1.05";
+
+/// OS guess of a host
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OsMatch {
+    /// OS name
+    pub name: String,
+    /// Accuracy in percent
+    pub accuracy: u8,
+}
+
+/// Extra information about a host not carried by the scan result
+#[derive(Clone, Debug, Default)]
+struct HostExtra {
+    mac: Option<MacAddr>,
+    hostnames: Vec<(String, &'static str)>,
+    banners: HashMap<u16, String>,
+    os_matches: Vec<OsMatch>,
+    hops: Vec<TracertQueryResult>,
+}
+
+/// Nmap compatible XML report (`nmaprun` schema) of a completed scan
+/// # Examples
+/// ```
+/// use e_libscanner::frame::{result::{PortInfo, PortStatus, ScanResult}, ScanType};
+/// use e_libscanner::{data::Protocol, output::NmapXml};
+/// fn main() -> Result<(), String> {
+///     let ip = "192.168.1.1".parse().unwrap();
+///     let mut result = ScanResult::new();
+///     result.ip_with_port.insert(ip, vec![PortInfo::new(22, Protocol::Tcp, PortStatus::Open)]);
+///     let mut report = NmapXml::new(ScanType::TcpSynScan);
+///     report.add_hostname(ip, "router.lan");
+///     report.set_banner(ip, 22, "SSH-2.0-OpenSSH_8.9");
+///     let xml = report.render(&result);
+///     assert!(xml.contains(r#"<port protocol="tcp" portid="22"><state state="open" reason="syn-ack""#));
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NmapXml {
+    /// Type of the scan
+    pub scan_type: ScanType,
+    /// Command line of the scan
+    pub args: String,
+    /// Time the scan started
+    pub start_time: SystemTime,
+    hosts: BTreeMap<IpAddr, HostExtra>,
+}
+
+impl NmapXml {
+    /// Create a report of a scan that starts now
+    pub fn new(scan_type: ScanType) -> NmapXml {
+        NmapXml {
+            scan_type,
+            args: String::new(),
+            start_time: SystemTime::now(),
+            hosts: BTreeMap::new(),
+        }
+    }
+    /// Set command line of the scan
+    pub fn set_args(&mut self, args: String) {
+        self.args = args;
+    }
+    /// Set time the scan started
+    pub fn set_start_time(&mut self, start_time: SystemTime) {
+        self.start_time = start_time;
+    }
+    /// Set MAC address of a host
+    pub fn set_mac(&mut self, ip: IpAddr, mac: MacAddr) {
+        self.host(ip).mac = Some(mac);
+    }
+    /// Add user supplied hostname of a host
+    pub fn add_hostname(&mut self, ip: IpAddr, name: &str) {
+        self.push_hostname(ip, name, "user");
+    }
+    /// Add hostnames from a DNS lookup; reverse lookups become PTR records
+    pub fn add_dns_result(&mut self, dns: &DnsResult) {
+        match &dns.result {
+            DnsResultType::Host(name) => {
+                if let Ok(ip) = dns.src.parse() {
+                    self.push_hostname(ip, name, "PTR");
+                }
+            }
+            DnsResultType::Addr(ips) => {
+                for ip in ips {
+                    self.push_hostname(*ip, &dns.src, "user");
+                }
+            }
+            DnsResultType::Error(_) => {}
+        }
+    }
+    /// Set banner of a TCP port found by service detection
+    pub fn set_banner(&mut self, ip: IpAddr, port: u16, banner: &str) {
+        self.host(ip).banners.insert(port, banner.to_owned());
+    }
+    /// Set banners of a service detection result
+    #[cfg(feature = "service")]
+    pub fn add_service_result(&mut self, result: &crate::service::ScanServiceResult) {
+        for (port, banner) in &result.ports {
+            self.set_banner(result.dst_ip, *port, banner);
+        }
+        if !result.dst_name.is_empty() && result.dst_name != result.dst_ip.to_string() {
+            self.add_hostname(result.dst_ip, &result.dst_name);
+        }
+    }
+    /// Add OS guess of a host
+    pub fn add_os_match(&mut self, ip: IpAddr, os_match: OsMatch) {
+        self.host(ip).os_matches.push(os_match);
+    }
+    /// Set traceroute hops of a host
+    pub fn set_trace(&mut self, ip: IpAddr, hops: Vec<TracertQueryResult>) {
+        self.host(ip).hops = hops;
+    }
+    /// Render the report of a scan result
+    pub fn render(&self, result: &ScanResult) -> String {
+        let start = unix_secs(self.start_time);
+        let end = unix_secs(self.start_time + result.scan_time);
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE nmaprun>\n");
+        let _ = writeln!(
+            xml,
+            r#"<nmaprun scanner="e-libscanner" args="{}" start="{}" version="{}" xmloutputversion="{}">"#,
+            escape(&self.args),
+            start,
+            env!("CARGO_PKG_VERSION"),
+            XML_OUTPUT_VERSION
+        );
+        for (protocol, ports) in scanned_ports(result) {
+            let _ = writeln!(
+                xml,
+                r#"<scaninfo type="{}" protocol="{}" numservices="{}" services="{}"/>"#,
+                scan_name(&self.scan_type, protocol),
+                protocol,
+                ports.len(),
+                port_ranges(&ports)
+            );
+        }
+        xml.push_str("<verbose level=\"0\"/>\n<debugging level=\"0\"/>\n");
+        let hosts = hosts_of(result);
+        for ip in &hosts {
+            self.render_host(&mut xml, result, *ip, start, end);
+        }
+        let up = hosts.iter().filter(|ip| result.is_host_up(**ip)).count();
+        let elapsed = result.scan_time.as_secs_f64();
+        let (exit, errormsg) = match result.scan_status {
+            ScanStatus::Error => ("error", r#" errormsg="scan failed""#),
+            _ => ("success", ""),
+        };
+        let _ = writeln!(
+            xml,
+            r#"<runstats><finished time="{}" elapsed="{:.2}" summary="{} IP address{} ({} host{} up) scanned in {:.2} seconds" exit="{}"{}/><hosts up="{}" down="{}" total="{}"/></runstats>"#,
+            end,
+            elapsed,
+            hosts.len(),
+            if hosts.len() == 1 { "" } else { "es" },
+            up,
+            if up == 1 { "" } else { "s" },
+            elapsed,
+            exit,
+            errormsg,
+            up,
+            hosts.len() - up,
+            hosts.len()
+        );
+        xml.push_str("</nmaprun>\n");
+        xml
+    }
+    /// Write the report of a scan result
+    pub fn write<W: Write>(&self, writer: &mut W, result: &ScanResult) -> Result<(), String> {
+        writer
+            .write_all(self.render(result).as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| e.to_string())
+    }
+
+    fn host(&mut self, ip: IpAddr) -> &mut HostExtra {
+        self.hosts.entry(ip).or_default()
+    }
+    fn push_hostname(&mut self, ip: IpAddr, name: &str, kind: &'static str) {
+        let hostnames = &mut self.host(ip).hostnames;
+        if !hostnames.iter().any(|(n, _)| n == name) {
+            hostnames.push((name.to_owned(), kind));
+        }
+    }
+    fn render_host(&self, xml: &mut String, result: &ScanResult, ip: IpAddr, start: u64, end: u64) {
+        let extra = self.hosts.get(&ip).cloned().unwrap_or_default();
+        let ttl = result
+            .ips
+            .iter()
+            .find(|host| host.ip_addr == ip)
+            .map(|host| host.ttl);
+        let answered = |status: PortStatus| {
+            result
+                .ip_with_port
+                .get(&ip)
+                .map_or(false, |ports| ports.iter().any(|p| p.status == status))
+        };
+        let (state, reason) = match (ttl, &self.scan_type) {
+            (Some(_), ScanType::IcmpPingScan) => ("up", "echo-reply"),
+            (Some(_), ScanType::UdpPingScan) => ("up", "udp-response"),
+            (Some(_), _) => ("up", "syn-ack"),
+            (None, _) if answered(PortStatus::Open) => ("up", "syn-ack"),
+            (None, _) if answered(PortStatus::Closed) => ("up", "reset"),
+            (None, _) => ("down", "no-response"),
+        };
+        let _ = writeln!(xml, r#"<host starttime="{}" endtime="{}">"#, start, end);
+        let _ = writeln!(
+            xml,
+            r#"<status state="{}" reason="{}" reason_ttl="{}"/>"#,
+            state,
+            reason,
+            ttl.unwrap_or(0)
+        );
+        let addrtype = if ip.is_ipv4() { "ipv4" } else { "ipv6" };
+        let _ = writeln!(xml, r#"<address addr="{}" addrtype="{}"/>"#, ip, addrtype);
+        if let Some(mac) = extra.mac {
+            let _ = writeln!(
+                xml,
+                r#"<address addr="{}" addrtype="mac"/>"#,
+                mac.to_string().to_uppercase()
+            );
+        }
+        xml.push_str("<hostnames>\n");
+        for (name, kind) in &extra.hostnames {
+            let _ = writeln!(
+                xml,
+                r#"<hostname name="{}" type="{}"/>"#,
+                escape(name),
+                kind
+            );
+        }
+        xml.push_str("</hostnames>\n");
+        let mut ports = result.ip_with_port.get(&ip).cloned().unwrap_or_default();
+        if !ports.is_empty() {
+            ports.sort_by_key(|p| (p.protocol, p.port));
+            xml.push_str("<ports>");
+            for port in &ports {
+                self.render_port(xml, port, &extra, ttl.unwrap_or(0));
+            }
+            xml.push_str("</ports>\n");
+        }
+        if !extra.os_matches.is_empty() {
+            xml.push_str("<os>");
+            for os_match in &extra.os_matches {
+                let _ = write!(
+                    xml,
+                    r#"<osmatch name="{}" accuracy="{}" line="0"/>"#,
+                    escape(&os_match.name),
+                    os_match.accuracy
+                );
+            }
+            xml.push_str("</os>\n");
+        }
+        if !extra.hops.is_empty() {
+            xml.push_str("<trace>\n");
+            for hop in &extra.hops {
+                let addrs: Vec<&String> = hop.addr.iter().flatten().collect();
+                let ipaddr = addrs.iter().find(|a| a.parse::<IpAddr>().is_ok());
+                let _ = write!(
+                    xml,
+                    r#"<hop ttl="{}" ipaddr="{}" rtt="{}""#,
+                    hop.id,
+                    ipaddr.map(|a| escape(a)).unwrap_or_default(),
+                    rtt_ms(hop.rtt)
+                );
+                if let Some(host) = addrs.iter().find(|a| a.parse::<IpAddr>().is_err()) {
+                    let _ = write!(xml, r#" host="{}""#, escape(host));
+                }
+                xml.push_str("/>\n");
+            }
+            xml.push_str("</trace>\n");
+        }
+        xml.push_str("</host>\n");
+    }
+    fn render_port(&self, xml: &mut String, port: &PortInfo, extra: &HostExtra, ttl: u8) {
+        let _ = write!(
+            xml,
+            r#"<port protocol="{}" portid="{}"><state state="{}" reason="{}" reason_ttl="{}"/>"#,
+            port.protocol,
+            port.port,
+            port_state(port.status),
+            port_reason(&self.scan_type, port.protocol, port.status),
+            ttl
+        );
+        let banner = match port.protocol {
+            Protocol::Tcp => extra.banners.get(&port.port),
+            _ => None,
+        };
+        let (method, conf) = if banner.is_some() {
+            ("probed", 10)
+        } else {
+            ("table", 3)
+        };
+        if !port.describe.is_empty() || banner.is_some() {
+            let _ = write!(
+                xml,
+                r#"<service name="{}" method="{}" conf="{}"/>"#,
+                escape(&port.describe),
+                method,
+                conf
+            );
+        }
+        if let Some(banner) = banner {
+            let _ = write!(xml, r#"<script id="banner" output="{}"/>"#, escape(banner));
+        }
+        xml.push_str("</port>\n");
+    }
+}
+
+/// Nmap reason of the port state
+fn port_reason(scan_type: &ScanType, protocol: Protocol, status: PortStatus) -> &'static str {
+    match (protocol, status) {
+        (Protocol::Udp, PortStatus::Open) => "udp-response",
+        (Protocol::Udp, PortStatus::Closed) => "port-unreach",
+        (Protocol::Udp, PortStatus::Filtered) => "admin-prohibited",
+        (_, PortStatus::Open) => "syn-ack",
+        (_, PortStatus::Closed) => match scan_type {
+            ScanType::TcpConnectScan => "conn-refused",
+            _ => "reset",
+        },
+        (_, PortStatus::Filtered) | (_, PortStatus::OpenFiltered) => "no-response",
+    }
+}
+
+/// Nmap name of the scan of a protocol
+fn scan_name(scan_type: &ScanType, protocol: Protocol) -> &'static str {
+    match (protocol, scan_type) {
+        (Protocol::Udp, _) => "udp",
+        (_, ScanType::TcpConnectScan) => "connect",
+        _ => "syn",
+    }
+}
+
+/// Scanned ports of the result by protocol
+fn scanned_ports(result: &ScanResult) -> BTreeMap<Protocol, BTreeSet<u16>> {
+    let mut ports: BTreeMap<Protocol, BTreeSet<u16>> = BTreeMap::new();
+    for port in result.ip_with_port.values().flatten() {
+        ports.entry(port.protocol).or_default().insert(port.port);
+    }
+    ports
+}
+
+/// Compress sorted ports into a list of ranges like `22,80-81`
+fn port_ranges(ports: &BTreeSet<u16>) -> String {
+    let mut ranges: Vec<(u16, u16)> = vec![];
+    for port in ports {
+        match ranges.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(*port) => *end = *port,
+            _ => ranges.push((*port, *port)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Round-trip time in milliseconds
+fn rtt_ms(rtt: Duration) -> String {
+    format!("{:.2}", rtt.as_secs_f64() * 1000.0)
+}
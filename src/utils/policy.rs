@@ -0,0 +1,363 @@
+use crate::data::Protocol;
+use crate::diff::ScanSnapshot;
+use crate::frame::result::{PortInfo, PortStatus, ScanResult, ScanStatus};
+use crate::output::Banners;
+use crate::ports::{parse_port_spec, PortSpec};
+use crate::scope::parse_net;
+use ipnet::IpNet;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Rule as written in the policy file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    subnet: String,
+    allowed_ports: Option<Vec<String>>,
+    #[serde(default)]
+    required_services: Vec<String>,
+    #[serde(default)]
+    forbidden_services: Vec<String>,
+}
+
+/// Policy file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    rule: Vec<RuleFile>,
+}
+
+/// Allowed exposure of a subnet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyRule {
+    /// Hosts the rule applies to
+    pub subnet: IpNet,
+    /// Ports allowed to be open; None allows every port
+    pub allowed_ports: Option<PortSpec>,
+    /// Services every host of the subnet must offer
+    pub required_services: Vec<String>,
+    /// Services no host of the subnet may offer
+    pub forbidden_services: Vec<String>,
+}
+
+impl PolicyRule {
+    /// Rule for a subnet that allows everything
+    pub fn new(subnet: IpNet) -> PolicyRule {
+        PolicyRule {
+            subnet,
+            allowed_ports: None,
+            required_services: vec![],
+            forbidden_services: vec![],
+        }
+    }
+    /// Set ports allowed to be open, a port specification like `22,T:443`
+    pub fn set_allowed_ports(&mut self, spec: &[String]) -> Result<(), String> {
+        self.allowed_ports = Some(parse_port_spec(spec)?);
+        Ok(())
+    }
+    /// Add a service every host must offer
+    pub fn add_required_service(&mut self, service: &str) {
+        self.required_services.push(service.to_owned());
+    }
+    /// Add a service no host may offer
+    pub fn add_forbidden_service(&mut self, service: &str) {
+        self.forbidden_services.push(service.to_owned());
+    }
+    /// Port is allowed to be open
+    pub fn allows(&self, protocol: Protocol, port: u16) -> bool {
+        match &self.allowed_ports {
+            None => true,
+            Some(spec) => match protocol {
+                Protocol::Udp => spec.udp.contains(&port),
+                _ => spec.tcp.contains(&port),
+            },
+        }
+    }
+}
+
+/// Policy violation of a host
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "violation", rename_all = "snake_case")]
+pub enum Violation {
+    /// Open port the rule does not allow
+    UnexpectedPort {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Port number
+        port: u16,
+        /// Transport protocol
+        protocol: Protocol,
+        /// Subnet of the violated rule
+        subnet: IpNet,
+    },
+    /// Required service not found on any open port
+    MissingService {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Required service
+        service: String,
+        /// Subnet of the violated rule
+        subnet: IpNet,
+    },
+    /// Forbidden service found on an open port
+    ForbiddenService {
+        /// IP address of the host
+        ip: IpAddr,
+        /// Port number
+        port: u16,
+        /// Transport protocol
+        protocol: Protocol,
+        /// Forbidden service
+        service: String,
+        /// Service detection banner the service was found in
+        banner: Option<String>,
+        /// Subnet of the violated rule
+        subnet: IpNet,
+    },
+}
+
+impl Violation {
+    /// IP address of the host violating the policy
+    pub fn ip(&self) -> IpAddr {
+        match self {
+            Violation::UnexpectedPort { ip, .. }
+            | Violation::MissingService { ip, .. }
+            | Violation::ForbiddenService { ip, .. } => *ip,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    /// One line, like `10.1.0.5 8080/tcp open, not allowed by 10.1.0.0/24`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::UnexpectedPort {
+                ip,
+                port,
+                protocol,
+                subnet,
+            } => write!(
+                f,
+                "{} {}/{} open, not allowed by {}",
+                ip, port, protocol, subnet
+            ),
+            Violation::MissingService {
+                ip,
+                service,
+                subnet,
+            } => write!(
+                f,
+                "{} service {} missing, required by {}",
+                ip, service, subnet
+            ),
+            Violation::ForbiddenService {
+                ip,
+                port,
+                protocol,
+                service,
+                banner,
+                subnet,
+            } => {
+                write!(
+                    f,
+                    "{} {}/{} service {} forbidden by {}",
+                    ip, port, protocol, service, subnet
+                )?;
+                if let Some(banner) = banner {
+                    write!(f, " (banner {:?})", banner)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Outcome of a policy check
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyReport {
+    /// Number of hosts a rule applied to
+    pub hosts_checked: usize,
+    /// Violations ordered by host
+    pub violations: Vec<Violation>,
+}
+
+impl PolicyReport {
+    /// No violation was found
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for PolicyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for violation in &self.violations {
+            writeln!(f, "{}", violation)?;
+        }
+        writeln!(
+            f,
+            "{} hosts checked, {} violations",
+            self.hosts_checked,
+            self.violations.len()
+        )
+    }
+}
+
+/// Allowed exposure per subnet
+///
+/// A host is checked against the most specific rule containing it, so a rule
+/// for a /24 overrides the rule of the enclosing /16; hosts no rule contains
+/// are not checked. Only ports found `open` count as exposed.
+///
+/// Services are matched case-insensitively against the service name of the
+/// port and, for TCP ports, as part of the service detection banner.
+/// # File format
+/// ```text
+/// [[rule]]
+/// subnet = "10.1.0.0/24"
+/// # port specification, every open port outside it is a violation
+/// allowed_ports = ["22", "443"]
+/// required_services = ["ssh"]
+/// forbidden_services = ["telnet", "Apache/2.2"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Rules in file order
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Policy {
+    /// Create empty policy
+    pub fn new() -> Policy {
+        Policy::default()
+    }
+    /// Load policy from a TOML file
+    pub fn from_file(path: &str) -> Result<Policy, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Policy::parse(&content).map_err(|e| format!("{}: {}", path, e))
+    }
+    /// Parse policy from TOML
+    pub fn parse(content: &str) -> Result<Policy, String> {
+        let file: PolicyFile = toml::from_str(content).map_err(|e| e.to_string())?;
+        let mut policy = Policy::new();
+        for (n, rule) in file.rule.into_iter().enumerate() {
+            let err = |e: String| format!("rule {}: {}", n + 1, e);
+            let mut parsed = PolicyRule::new(parse_net(rule.subnet.trim()).map_err(err)?);
+            if let Some(spec) = rule.allowed_ports {
+                parsed.set_allowed_ports(&spec).map_err(err)?;
+            }
+            parsed.required_services = rule.required_services;
+            parsed.forbidden_services = rule.forbidden_services;
+            policy.add_rule(parsed);
+        }
+        Ok(policy)
+    }
+    /// Add rule
+    pub fn add_rule(&mut self, rule: PolicyRule) {
+        self.rules.push(rule);
+    }
+    /// Most specific rule containing the address; the first one on a tie
+    pub fn rule_for(&self, ip: &IpAddr) -> Option<&PolicyRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.subnet.contains(ip))
+            .fold(None, |best: Option<&PolicyRule>, rule| match best {
+                Some(best) if best.subnet.prefix_len() >= rule.subnet.prefix_len() => Some(best),
+                _ => Some(rule),
+            })
+    }
+    /// Check a scan result and the service detection banners found for it
+    ///
+    /// Only completed scans are checked: a failed or timed out scan misses
+    /// ports, which would pass for compliance.
+    pub fn evaluate(&self, result: &ScanResult, banners: &Banners) -> Result<PolicyReport, String> {
+        if result.scan_status != ScanStatus::Done {
+            let mut message = format!(
+                "Scan ended with status {:?}, cannot check the policy",
+                result.scan_status
+            );
+            for error in result.errors.iter() {
+                message.push_str(": ");
+                message.push_str(error);
+            }
+            return Err(message);
+        }
+        let hosts: BTreeSet<IpAddr> = result
+            .ips
+            .iter()
+            .map(|host| host.ip_addr)
+            .chain(result.ip_with_port.keys().copied())
+            .filter(|ip| result.is_host_up(*ip))
+            .collect();
+        let mut report = PolicyReport::default();
+        for ip in hosts {
+            let rule = match self.rule_for(&ip) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            report.hosts_checked += 1;
+            let mut open: Vec<&PortInfo> = result
+                .ip_with_port
+                .get(&ip)
+                .map(|ports| {
+                    ports
+                        .iter()
+                        .filter(|p| p.status == PortStatus::Open)
+                        .collect()
+                })
+                .unwrap_or_default();
+            open.sort_by_key(|p| (p.protocol, p.port));
+            let banner_of = |p: &PortInfo| match p.protocol {
+                Protocol::Tcp => banners.get_banner(ip, p.port),
+                _ => None,
+            };
+            for p in &open {
+                if !rule.allows(p.protocol, p.port) {
+                    report.violations.push(Violation::UnexpectedPort {
+                        ip,
+                        port: p.port,
+                        protocol: p.protocol,
+                        subnet: rule.subnet,
+                    });
+                }
+            }
+            for service in &rule.required_services {
+                if !open.iter().any(|p| offers(p, banner_of(p), service)) {
+                    report.violations.push(Violation::MissingService {
+                        ip,
+                        service: service.clone(),
+                        subnet: rule.subnet,
+                    });
+                }
+            }
+            for p in &open {
+                for service in &rule.forbidden_services {
+                    if offers(p, banner_of(p), service) {
+                        report.violations.push(Violation::ForbiddenService {
+                            ip,
+                            port: p.port,
+                            protocol: p.protocol,
+                            service: service.clone(),
+                            banner: banner_of(p).map(String::from),
+                            subnet: rule.subnet,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+    /// Check a snapshot with its banners
+    pub fn evaluate_snapshot(&self, snapshot: &ScanSnapshot) -> Result<PolicyReport, String> {
+        self.evaluate(&snapshot.result, &snapshot.banners)
+    }
+}
+
+/// Port offers the service by name or banner
+fn offers(port: &PortInfo, banner: Option<&str>, service: &str) -> bool {
+    let service = service.to_lowercase();
+    port.describe.to_lowercase() == service
+        || banner.map_or(false, |b| b.to_lowercase().contains(&service))
+}
@@ -0,0 +1,166 @@
+use crate::data::{Protocol, DATA};
+use std::collections::HashSet;
+
+/// Common names missing from the port map
+const SERVICE_ALIASES: [(&str, &str); 3] =
+    [("http", "www-http"), ("www", "www-http"), ("dns", "domain")];
+
+/// Ports selected by a port specification
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PortSpec {
+    /// TCP ports
+    pub tcp: Vec<u16>,
+    /// UDP ports
+    pub udp: Vec<u16>,
+    /// UDP was selected with a `U:` prefix
+    pub udp_selected: bool,
+}
+
+impl PortSpec {
+    /// Returns true if no port is selected
+    pub fn is_empty(&self) -> bool {
+        self.tcp.is_empty() && self.udp.is_empty()
+    }
+    /// UDP ports to scan; empty unless UDP was selected with `U:`,
+    /// as ports without prefix alone do not start a UDP scan
+    pub fn udp_to_scan(&self) -> Vec<u16> {
+        if self.udp_selected {
+            self.udp.clone()
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Protocols following items apply to
+#[derive(Clone, Copy)]
+enum Selection {
+    Any,
+    Tcp,
+    Udp,
+}
+
+impl Selection {
+    fn tcp(self) -> bool {
+        matches!(self, Selection::Any | Selection::Tcp)
+    }
+    fn udp(self) -> bool {
+        matches!(self, Selection::Any | Selection::Udp)
+    }
+}
+
+/// Parse a port specification
+///
+/// Items are separated by `,`; each item is one of
+/// - a port: `80`
+/// - an inclusive range: `20-30`, `-1024`(from 1), `60000-`(up to 65535)
+/// - all ports: `-`
+/// - a service name of the service database: `ssh`, `https`
+///
+/// `T:` and `U:` select TCP or UDP for the following items, items before
+/// any prefix apply to both. Ports keep their first position, duplicates are dropped.
+pub fn parse_port_spec(input: &[String]) -> Result<PortSpec, String> {
+    let mut spec = PortSpec::default();
+    let mut seen: (HashSet<u16>, HashSet<u16>) = (HashSet::new(), HashSet::new());
+    let mut selection = Selection::Any;
+    for item in input.iter().flat_map(|s| s.split(',')) {
+        let mut item = item.trim();
+        if let Some((prefix, rest)) = item.split_once(':') {
+            selection = match prefix.trim() {
+                "T" | "t" => Selection::Tcp,
+                "U" | "u" => {
+                    spec.udp_selected = true;
+                    Selection::Udp
+                }
+                p => return Err(format!("unknown protocol prefix `{}:` in `{}`", p, item)),
+            };
+            item = rest.trim();
+        }
+        if item.is_empty() {
+            return Err(String::from("empty item in port list"));
+        }
+        let (tcp, udp) = parse_item(item, selection)?;
+        for port in tcp {
+            if seen.0.insert(port) {
+                spec.tcp.push(port);
+            }
+        }
+        for port in udp {
+            if seen.1.insert(port) {
+                spec.udp.push(port);
+            }
+        }
+    }
+    Ok(spec)
+}
+
+/// Parse single item of a port specification into TCP and UDP ports
+fn parse_item(item: &str, selection: Selection) -> Result<(Vec<u16>, Vec<u16>), String> {
+    let ports: Vec<u16> = if item == "-" {
+        (1..=u16::MAX).collect()
+    } else if let Some((start, end)) = item.split_once('-') {
+        let start = if start.is_empty() {
+            1
+        } else {
+            parse_port(start, item)?
+        };
+        let end = if end.is_empty() {
+            u16::MAX
+        } else {
+            parse_port(end, item)?
+        };
+        if start > end {
+            return Err(format!("port range `{}` is reversed", item));
+        }
+        (start..=end).collect()
+    } else if item.chars().all(|c| c.is_ascii_digit()) {
+        vec![parse_port(item, item)?]
+    } else {
+        // service names resolve per protocol
+        let tcp = if selection.tcp() {
+            service_ports(Protocol::Tcp, item)
+        } else {
+            vec![]
+        };
+        let udp = if selection.udp() {
+            service_ports(Protocol::Udp, item)
+        } else {
+            vec![]
+        };
+        if tcp.is_empty() && udp.is_empty() {
+            return Err(format!("unknown service name `{}`", item));
+        }
+        return Ok((tcp, udp));
+    };
+    Ok((
+        if selection.tcp() {
+            ports.clone()
+        } else {
+            vec![]
+        },
+        if selection.udp() { ports } else { vec![] },
+    ))
+}
+
+/// Get ports of a service name or of its alias
+fn service_ports(proto: Protocol, name: &str) -> Vec<u16> {
+    let ports = DATA.service_ports(proto, name);
+    if !ports.is_empty() {
+        return ports;
+    }
+    match SERVICE_ALIASES.iter().find(|(alias, _)| *alias == name) {
+        Some((_, name)) => DATA.service_ports(proto, name),
+        None => ports,
+    }
+}
+
+fn parse_port(s: &str, item: &str) -> Result<u16, String> {
+    match s.trim().parse::<u32>() {
+        Ok(port) if port <= u16::MAX as u32 => Ok(port as u16),
+        Ok(port) => Err(format!(
+            "port {} in `{}` is out of range 0-65535",
+            port, item
+        )),
+        Err(_) => Err(format!("invalid port `{}` in `{}`", s, item)),
+    }
+}
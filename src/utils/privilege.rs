@@ -0,0 +1,16 @@
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// Check if the process may open raw sockets and datalink channels
+///
+/// On Linux this requires root or CAP_NET_RAW, on windows an administrator.
+pub fn has_raw_socket_privilege() -> bool {
+    Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).is_ok()
+}
+
+/// Check if ICMP datagram sockets are allowed for this process
+///
+/// Governed by the `net.ipv4.ping_group_range` sysctl.
+#[cfg(target_os = "linux")]
+pub fn has_icmp_datagram_socket() -> bool {
+    Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4)).is_ok()
+}
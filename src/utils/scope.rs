@@ -0,0 +1,214 @@
+use crate::frame::Destination;
+use ipnet::IpNet;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+
+/// Target refused by a scope
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeViolation {
+    /// Target as given(address, range or host name)
+    pub target: String,
+    /// Address the target resolved to
+    pub ip: IpAddr,
+    /// Why the address is refused
+    pub reason: String,
+}
+
+impl fmt::Display for ScopeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.target == self.ip.to_string() {
+            write!(f, "{} is out of scope: {}", self.ip, self.reason)
+        } else {
+            write!(
+                f,
+                "{}({}) is out of scope: {}",
+                self.target, self.ip, self.reason
+            )
+        }
+    }
+}
+
+/// Authorized scan scope
+///
+/// An address is in scope when an allowed network contains it and no
+/// excluded network does. A scope without allowed networks refuses everything.
+/// # File format
+/// ```text
+/// # one entry per line
+/// allow 192.168.1.0/24
+/// allow 10.0.0.5
+/// exclude 192.168.1.1
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Scope {
+    /// Allowed networks
+    pub allowed: Vec<IpNet>,
+    /// Excluded networks, take precedence over allowed ones
+    pub excluded: Vec<IpNet>,
+    /// Refuse the whole scan on any violation
+    pub strict: bool,
+}
+
+impl Scope {
+    /// Create empty scope
+    pub fn new() -> Scope {
+        Scope::default()
+    }
+    /// Load scope from file
+    pub fn from_file(path: &str) -> Result<Scope, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Scope::parse(&content).map_err(|e| format!("{}: {}", path, e))
+    }
+    /// Parse scope from file content
+    pub fn parse(content: &str) -> Result<Scope, String> {
+        let mut scope = Scope::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, net) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("line {}: expected `allow|exclude <cidr>`", n + 1))?;
+            let net = parse_net(net.trim()).map_err(|e| format!("line {}: {}", n + 1, e))?;
+            match kind {
+                "allow" => scope.allow(net),
+                "exclude" => scope.exclude(net),
+                _ => return Err(format!("line {}: unknown entry -> {}", n + 1, kind)),
+            }
+        }
+        Ok(scope)
+    }
+    /// Add allowed network
+    pub fn allow(&mut self, net: IpNet) {
+        self.allowed.push(net);
+    }
+    /// Add excluded network
+    pub fn exclude(&mut self, net: IpNet) {
+        self.excluded.push(net);
+    }
+    /// Set strict mode
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+    /// Get strict mode
+    pub fn get_strict(&self) -> bool {
+        self.strict
+    }
+    /// Check address against the scope
+    pub fn check(&self, ip: &IpAddr) -> Result<(), String> {
+        if let Some(net) = self.excluded.iter().find(|net| net.contains(ip)) {
+            return Err(format!("excluded by {}", net));
+        }
+        if self.allowed.iter().any(|net| net.contains(ip)) {
+            Ok(())
+        } else {
+            Err(String::from("not in any allowed network"))
+        }
+    }
+    /// Check a whole network against the scope
+    ///
+    /// Fails when no address of the network can be in scope, so that it need not be walked.
+    pub fn check_net(&self, target: &IpNet) -> Result<(), String> {
+        if let Some(net) = self.excluded.iter().find(|net| net.contains(target)) {
+            return Err(format!("excluded by {}", net));
+        }
+        // networks are either nested or disjoint
+        if self
+            .allowed
+            .iter()
+            .any(|net| net.contains(target) || target.contains(net))
+        {
+            Ok(())
+        } else {
+            Err(String::from("not in any allowed network"))
+        }
+    }
+    /// Largest subnets of `target` that are out of scope, with the reason
+    pub fn refused_nets(&self, target: &IpNet) -> Vec<(IpNet, String)> {
+        let mut refused = vec![];
+        self.split_refused(*target, &mut refused);
+        refused
+    }
+    fn split_refused(&self, target: IpNet, refused: &mut Vec<(IpNet, String)>) {
+        if let Err(reason) = self.check_net(&target) {
+            refused.push((target, reason));
+            return;
+        }
+        let inside = self.allowed.iter().any(|net| net.contains(&target))
+            && !self.excluded.iter().any(|net| target.contains(net));
+        if inside {
+            return;
+        }
+        // partly in scope, check both halves
+        if let Ok(halves) = target.subnets(target.prefix_len() + 1) {
+            for half in halves {
+                self.split_refused(half, refused);
+            }
+        }
+    }
+    /// Returns true if the address is in scope
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.check(ip).is_ok()
+    }
+    /// Split addresses resolved from `target` into in scope addresses and violations
+    ///
+    /// In strict mode the first violation is returned as error.
+    pub fn filter(
+        &self,
+        target: &str,
+        ips: Vec<IpAddr>,
+    ) -> Result<(Vec<IpAddr>, Vec<ScopeViolation>), String> {
+        let mut allowed = vec![];
+        let mut violations = vec![];
+        for ip in ips {
+            match self.violation(target, ip) {
+                None => allowed.push(ip),
+                Some(violation) if self.strict => return Err(violation.to_string()),
+                Some(violation) => violations.push(violation),
+            }
+        }
+        Ok((allowed, violations))
+    }
+    /// Violation of an address resolved from `target`, none when it is in scope
+    ///
+    /// Strict mode is left to the caller.
+    pub fn violation(&self, target: &str, ip: IpAddr) -> Option<ScopeViolation> {
+        self.check(&ip).err().map(|reason| ScopeViolation {
+            target: target.to_string(),
+            ip,
+            reason,
+        })
+    }
+    /// Split destinations into in scope destinations and violations
+    ///
+    /// In strict mode the first violation is returned as error.
+    pub fn filter_destinations(
+        &self,
+        destinations: Vec<Destination>,
+    ) -> Result<(Vec<Destination>, Vec<ScopeViolation>), String> {
+        let mut allowed = vec![];
+        let mut violations = vec![];
+        for dst in destinations {
+            let (ips, mut refused) = self.filter(&dst.dst_ip.to_string(), vec![dst.dst_ip])?;
+            if ips.is_empty() {
+                violations.append(&mut refused);
+            } else {
+                allowed.push(dst);
+            }
+        }
+        Ok((allowed, violations))
+    }
+}
+
+/// Parse network, a bare address is a single host network
+pub(crate) fn parse_net(s: &str) -> Result<IpNet, String> {
+    match s.parse::<IpNet>() {
+        Ok(net) => Ok(net),
+        Err(_) => s
+            .parse::<IpAddr>()
+            .map(IpNet::from)
+            .map_err(|_| format!("cannot parse network -> {}", s)),
+    }
+}
@@ -9,9 +9,10 @@ use std::{
     time::Duration,
 };
 use e_utils::traceroute::Traceroute;
+use serde_derive::{Deserialize, Serialize};
 
 /// Traceroute reuslt model
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TracertQueryResult {
     #[doc(hidden)]
     pub id: u8,